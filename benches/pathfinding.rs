@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rust_ttd::pathfinding::{self, Heuristic, PathCosts};
+use rust_ttd::world::{TerrainType, TileContent, World, WorldGenConfig};
+
+/// Same map every run so the numbers compare.
+const SEED: u64 = 42;
+
+/// The open land tile nearest to `pos`, searching along the row.
+fn open_land_near(world: &World, pos: (usize, usize)) -> (usize, usize) {
+    (pos.0..world.width)
+        .map(|x| (x, pos.1))
+        .find(|&(x, y)| world.get_tile(x, y).is_some_and(|tile| {
+            matches!(tile.content, TileContent::Empty)
+                && !matches!(tile.terrain, TerrainType::Water | TerrainType::Mountain)
+        }))
+        .expect("no open land on the row")
+}
+
+/// The breadth-first search road vehicles used before A*, kept as the
+/// baseline: it ignores step costs and floods outwards until it reaches
+/// the goal.
+fn bfs_path(
+    start: (usize, usize),
+    goal: (usize, usize),
+    neighbors: impl Fn((usize, usize)) -> Vec<(usize, usize)>,
+) -> Option<Vec<(usize, usize)>> {
+    let mut open_set = VecDeque::new();
+    let mut came_from = HashMap::new();
+    let mut visited = HashSet::new();
+
+    open_set.push_back(start);
+
+    while let Some(current) = open_set.pop_front() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut current_pos = current;
+            while let Some(&previous) = came_from.get(&current_pos) {
+                path.push(previous);
+                current_pos = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        if !visited.insert(current) {
+            continue;
+        }
+
+        for neighbor in neighbors(current) {
+            if !visited.contains(&neighbor) && !came_from.contains_key(&neighbor) {
+                came_from.insert(neighbor, current);
+                open_set.push_back(neighbor);
+            }
+        }
+    }
+
+    None
+}
+
+fn road_paths(c: &mut Criterion) {
+    let world = World::from_config(WorldGenConfig::new(1024, 1024, SEED));
+    let costs = PathCosts::default();
+    let mut group = c.benchmark_group("road_path");
+    group.sample_size(10);
+
+    for distance in [50, 200] {
+        let start = open_land_near(&world, (400, 500));
+        let goal = open_land_near(&world, (400 + distance, 500 + distance / 2));
+        for (name, heuristic) in [("a_star", Heuristic::Manhattan), ("dijkstra", Heuristic::None)] {
+            group.bench_with_input(BenchmarkId::new(name, distance), &(start, goal), |b, &(start, goal)| {
+                b.iter(|| pathfinding::find_path(&world, start, goal, &costs, heuristic, |pos| {
+                    pathfinding::road_neighbors(&world, pos, 0)
+                }))
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("bfs", distance), &(start, goal), |b, &(start, goal)| {
+            b.iter(|| bfs_path(start, goal, |pos| pathfinding::road_neighbors(&world, pos, 0)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, road_paths);
+criterion_main!(benches);
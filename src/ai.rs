@@ -1,6 +1,7 @@
 use crate::player::Player;
-use crate::world::{World, CargoType};
+use crate::world::{World, CargoType, CompanyId, StationType, ROAD_MAINTENANCE};
 use crate::economy::Economy;
+use crate::finance::LedgerCategory;
 use crate::vehicle::{Vehicle, VehicleType, TrainEngine, TrainCar, TruckType};
 use rand::Rng;
 
@@ -45,15 +46,18 @@ pub enum AITargetType {
 }
 
 impl AIPlayer {
-    pub fn new(name: String, difficulty: AIDifficulty, strategy: AIStrategy) -> Self {
+    pub fn new(company_id: CompanyId, name: String, difficulty: AIDifficulty, strategy: AIStrategy) -> Self {
         let starting_money = match difficulty {
             AIDifficulty::Easy => 500000,
             AIDifficulty::Medium => 800000,
             AIDifficulty::Hard => 1200000,
         };
 
+        let mut player = Player::new(name, starting_money);
+        player.company_id = company_id;
+
         Self {
-            player: Player::new(name, starting_money),
+            player,
             difficulty,
             strategy,
             decision_timer: 0,
@@ -94,7 +98,7 @@ impl AIPlayer {
         self.consider_vehicle_purchases(world, economy);
         self.identify_station_locations(world, economy);
 
-        self.targets.sort_by(|a, b| b.priority.cmp(&a.priority));
+        self.targets.sort_by_key(|target| std::cmp::Reverse(target.priority));
     }
 
     fn find_profitable_routes(&mut self, world: &World, economy: &Economy) {
@@ -228,18 +232,16 @@ impl AIPlayer {
             let x = rng.gen_range(0..world.width);
             let y = rng.gen_range(0..world.height);
             
-            if let Some(tile) = world.get_tile(x, y) {
-                if matches!(tile.content, crate::world::TileContent::Empty) {
-                    let nearby_value = self.calculate_location_value(x, y, world);
-                    
-                    if nearby_value > 50000 {
-                        self.targets.push(AITarget {
-                            target_type: AITargetType::BuildStation { location: (x, y) },
-                            priority: (nearby_value / 1000) as u32,
-                            estimated_profit: nearby_value,
-                            location: (x, y),
-                        });
-                    }
+            if world.is_clear(x, y) {
+                let nearby_value = self.calculate_location_value(x, y, world);
+                
+                if nearby_value > 50000 {
+                    self.targets.push(AITarget {
+                        target_type: AITargetType::BuildStation { location: (x, y) },
+                        priority: (nearby_value / 1000) as u32,
+                        estimated_profit: nearby_value,
+                        location: (x, y),
+                    });
                 }
             }
         }
@@ -259,7 +261,7 @@ impl AIPlayer {
             return None;
         }
 
-        let best_target = match &self.strategy {
+        match &self.strategy {
             AIStrategy::Aggressive => {
                 filtered_targets.into_iter()
                     .max_by_key(|target| {
@@ -292,12 +294,10 @@ impl AIPlayer {
                         }
                     })
             },
-        };
-
-        best_target
+        }
     }
 
-    fn execute_target(&mut self, target: AITarget, _world: &mut World, _economy: &Economy) {
+    fn execute_target(&mut self, target: AITarget, world: &mut World, _economy: &Economy) {
         match target.target_type {
             AITargetType::BuildRoute { from, to, cargo } => {
                 let route_id = self.player.create_route(
@@ -307,7 +307,7 @@ impl AIPlayer {
                 );
                 
                 if let Some(vehicle_id) = self.buy_suitable_vehicle(from, &cargo) {
-                    self.player.assign_vehicle_to_route(vehicle_id, route_id);
+                    self.player.assign_vehicle_to_route(world, vehicle_id, route_id);
                 }
             },
             AITargetType::ExpandExistingRoute { route_id } => {
@@ -318,7 +318,7 @@ impl AIPlayer {
                 };
                 
                 if let Some(vehicle_id) = self.buy_suitable_vehicle(start_location, &cargo_type) {
-                    self.player.assign_vehicle_to_route(vehicle_id, route_id);
+                    self.player.assign_vehicle_to_route(world, vehicle_id, route_id);
                 }
             },
             AITargetType::BuyVehicle { vehicle_type } => {
@@ -330,7 +330,14 @@ impl AIPlayer {
                 self.player.add_vehicle(vehicle_type, location.0, location.1);
             },
             AITargetType::BuildStation { location } => {
-                self.player.stations.push(location);
+                let (x, y) = location;
+                if let Ok(cost) = world.construction_cost(x, y, StationType::Road.build_cost())
+                    && self.player.can_afford(cost) && world.build_station(x, y, StationType::Road, self.player.company_id).is_ok()
+                {
+                    self.player.spend_money(cost, LedgerCategory::Construction);
+                    world.record_build_cost(location, cost);
+                    self.player.stations.push(location);
+                }
             },
         }
 
@@ -361,7 +368,12 @@ impl AIPlayer {
             _ => 150,
         };
         
-        ((price * estimated_monthly_cargo as f32) * 12.0) as i64
+        // A road the length of the route plus a stop at each end
+        let monthly_upkeep = economy.inflate(
+            distance.ceil() as i64 * ROAD_MAINTENANCE + 2 * StationType::Road.maintenance_cost()
+        );
+
+        ((price * estimated_monthly_cargo as f32) * 12.0) as i64 - monthly_upkeep * 12
     }
 
     fn estimate_vehicle_profit(&self, vehicle_type: &VehicleType, _economy: &Economy) -> i64 {
@@ -401,24 +413,25 @@ impl AIPlayer {
         let mut value = 0i64;
         let search_radius = 5;
 
-        for dy in -(search_radius as i32)..(search_radius as i32) {
-            for dx in -(search_radius as i32)..(search_radius as i32) {
+        for dy in -search_radius..search_radius {
+            for dx in -search_radius..search_radius {
                 let check_x = x as i32 + dx;
                 let check_y = y as i32 + dy;
 
-                if check_x >= 0 && check_y >= 0 &&
-                   (check_x as usize) < world.width && (check_y as usize) < world.height {
-                    
-                    if let Some(tile) = world.get_tile(check_x as usize, check_y as usize) {
-                        match &tile.content {
-                            crate::world::TileContent::Town(town) => {
-                                value += town.population as i64 * 10;
-                            },
-                            crate::world::TileContent::Industry(_) => {
-                                value += 25000;
-                            },
-                            _ => {}
-                        }
+                if check_x >= 0 && check_y >= 0
+                    && let Some(tile) = world.get_tile(check_x as usize, check_y as usize)
+                {
+                    match &tile.content {
+                        crate::world::TileContent::Town(town) => {
+                            value += town.population as i64 * 10;
+                        },
+                        crate::world::TileContent::House(house) => {
+                            value += house.residents as i64 * 10;
+                        },
+                        crate::world::TileContent::Industry(_) => {
+                            value += 25000;
+                        },
+                        _ => {}
                     }
                 }
             }
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::world::CargoType;
+
+/// A batch of one cargo that entered the network together. Packets remember
+/// where they came from so delivery can be paid by the distance and time
+/// actually travelled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CargoPacket {
+    pub cargo_type: CargoType,
+    pub amount: u32,
+    /// Industry or town the cargo was produced at
+    pub source: (usize, usize),
+    /// Station the cargo was first picked up from
+    pub source_station: (usize, usize),
+    /// Ticks spent on board vehicles
+    pub age: u32,
+    /// Station the cargo must be delivered to, if it has one
+    pub destination: Option<(usize, usize)>,
+}
+
+impl CargoPacket {
+    pub fn new(cargo_type: CargoType, amount: u32, source: (usize, usize), source_station: (usize, usize)) -> Self {
+        Self {
+            cargo_type,
+            amount,
+            source,
+            source_station,
+            age: 0,
+            destination: None,
+        }
+    }
+
+    /// Tiles between the station the cargo was picked up from and `station`.
+    pub fn distance_to(&self, station: (usize, usize)) -> u32 {
+        (self.source_station.0.abs_diff(station.0) + self.source_station.1.abs_diff(station.1)) as u32
+    }
+
+    fn can_merge(&self, other: &CargoPacket) -> bool {
+        self.cargo_type == other.cargo_type
+            && self.source == other.source
+            && self.source_station == other.source_station
+            && self.age == other.age
+            && self.destination == other.destination
+    }
+}
+
+/// The packets waiting at a station or carried by a vehicle, oldest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CargoList {
+    packets: Vec<CargoPacket>,
+}
+
+impl CargoList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn packets(&self) -> &[CargoPacket] {
+        &self.packets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub fn total(&self) -> u32 {
+        self.packets.iter().map(|packet| packet.amount).sum()
+    }
+
+    pub fn amount_of(&self, cargo_type: CargoType) -> u32 {
+        self.packets.iter()
+            .filter(|packet| packet.cargo_type == cargo_type)
+            .map(|packet| packet.amount)
+            .sum()
+    }
+
+    /// Totals per cargo type, for display.
+    pub fn by_type(&self) -> HashMap<CargoType, u32> {
+        let mut totals = HashMap::new();
+        for packet in &self.packets {
+            *totals.entry(packet.cargo_type).or_insert(0) += packet.amount;
+        }
+        totals
+    }
+
+    /// Adds a packet, folding it into an existing one with the same origin
+    /// and age so lists stay short.
+    pub fn push(&mut self, packet: CargoPacket) {
+        if packet.amount == 0 {
+            return;
+        }
+        match self.packets.iter_mut().find(|existing| existing.can_merge(&packet)) {
+            Some(existing) => existing.amount += packet.amount,
+            None => self.packets.push(packet),
+        }
+    }
+
+    /// Removes up to `max` units, oldest packets first, splitting the last
+    /// packet if it doesn't fit.
+    pub fn take(&mut self, max: u32) -> Vec<CargoPacket> {
+        let mut taken = Vec::new();
+        let mut remaining = max;
+        while remaining > 0 && !self.packets.is_empty() {
+            if self.packets[0].amount <= remaining {
+                let packet = self.packets.remove(0);
+                remaining -= packet.amount;
+                taken.push(packet);
+            } else {
+                let mut part = self.packets[0].clone();
+                part.amount = remaining;
+                self.packets[0].amount -= remaining;
+                remaining = 0;
+                taken.push(part);
+            }
+        }
+        taken
+    }
+
+    /// Removes every packet `deliverable` says yes to.
+    pub fn take_where(&mut self, deliverable: impl Fn(&CargoPacket) -> bool) -> Vec<CargoPacket> {
+        let (taken, kept) = std::mem::take(&mut self.packets).into_iter().partition(deliverable);
+        self.packets = kept;
+        taken
+    }
+
+    /// Throws away up to `amount` of one cargo, newest packets first.
+    /// Returns how much was removed.
+    pub fn discard(&mut self, cargo_type: CargoType, amount: u32) -> u32 {
+        let mut remaining = amount;
+        for packet in self.packets.iter_mut().rev().filter(|packet| packet.cargo_type == cargo_type) {
+            let removed = packet.amount.min(remaining);
+            packet.amount -= removed;
+            remaining -= removed;
+            if remaining == 0 {
+                break;
+            }
+        }
+        self.packets.retain(|packet| packet.amount > 0);
+        amount - remaining
+    }
+
+    /// Ages every packet by one tick. Only cargo on board vehicles ages.
+    pub fn age(&mut self) {
+        for packet in &mut self.packets {
+            packet.age = packet.age.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coal(amount: u32, source: (usize, usize)) -> CargoPacket {
+        CargoPacket::new(CargoType::Coal, amount, source, (0, 0))
+    }
+
+    #[test]
+    fn packets_from_the_same_place_merge() {
+        let mut list = CargoList::new();
+        list.push(coal(10, (1, 1)));
+        list.push(coal(5, (1, 1)));
+        list.push(coal(0, (2, 2)));
+        assert_eq!(list.packets().len(), 1);
+        assert_eq!(list.total(), 15);
+
+        let mut aged = coal(5, (1, 1));
+        aged.age = 3;
+        list.push(aged);
+        list.push(coal(7, (2, 2)));
+        list.push(CargoPacket::new(CargoType::Mail, 4, (1, 1), (0, 0)));
+        assert_eq!(list.packets().len(), 4);
+        assert_eq!(list.amount_of(CargoType::Coal), 27);
+        assert_eq!(list.by_type().get(&CargoType::Mail), Some(&4));
+    }
+
+    #[test]
+    fn packets_bound_for_different_stations_stay_apart() {
+        let mut list = CargoList::new();
+        let mut bound = coal(5, (1, 1));
+        bound.destination = Some((9, 9));
+        list.push(coal(5, (1, 1)));
+        list.push(bound.clone());
+        assert_eq!(list.packets().len(), 2);
+
+        let delivered = list.take_where(|packet| packet.destination.is_none_or(|station| station == (4, 4)));
+        assert_eq!(delivered, vec![coal(5, (1, 1))]);
+        assert_eq!(list.packets(), &[bound]);
+    }
+
+    #[test]
+    fn distance_counts_from_the_pickup_station_not_the_source() {
+        let packet = CargoPacket::new(CargoType::Coal, 10, (50, 50), (3, 8));
+        assert_eq!(packet.distance_to((3, 8)), 0);
+        assert_eq!(packet.distance_to((10, 2)), 13);
+        assert_eq!(packet.distance_to((0, 9)), 4);
+    }
+
+    #[test]
+    fn empty_lists_and_old_packets_are_left_alone() {
+        let mut list = CargoList::new();
+        assert!(list.take(10).is_empty());
+        assert_eq!(list.discard(CargoType::Coal, 10), 0);
+
+        let mut ancient = coal(1, (1, 1));
+        ancient.age = u32::MAX;
+        list.push(ancient);
+        list.age();
+        assert_eq!(list.packets()[0].age, u32::MAX);
+    }
+
+    #[test]
+    fn take_removes_the_oldest_first_and_splits_the_last() {
+        let mut list = CargoList::new();
+        list.push(coal(10, (1, 1)));
+        list.push(coal(10, (2, 2)));
+
+        let taken = list.take(15);
+        assert_eq!(taken, vec![coal(10, (1, 1)), coal(5, (2, 2))]);
+        assert_eq!(list.packets(), &[coal(5, (2, 2))]);
+        assert!(list.take(0).is_empty());
+        assert_eq!(list.take(100), vec![coal(5, (2, 2))]);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn discard_removes_the_newest_of_one_cargo() {
+        let mut list = CargoList::new();
+        list.push(coal(10, (1, 1)));
+        list.push(CargoPacket::new(CargoType::Mail, 4, (1, 1), (0, 0)));
+        list.push(coal(6, (2, 2)));
+
+        assert_eq!(list.discard(CargoType::Coal, 8), 8);
+        assert_eq!(list.packets(), &[coal(8, (1, 1)), CargoPacket::new(CargoType::Mail, 4, (1, 1), (0, 0))]);
+        assert_eq!(list.discard(CargoType::Coal, 20), 8);
+        assert_eq!(list.total(), 4);
+    }
+
+    #[test]
+    fn take_where_keeps_the_rest_and_age_ticks_every_packet() {
+        let mut list = CargoList::new();
+        list.push(coal(10, (1, 1)));
+        list.push(CargoPacket::new(CargoType::Mail, 4, (1, 1), (0, 0)));
+        list.age();
+        list.age();
+        assert!(list.packets().iter().all(|packet| packet.age == 2));
+
+        let mail = list.take_where(|packet| packet.cargo_type == CargoType::Mail);
+        assert_eq!(mail.len(), 1);
+        assert_eq!(list.amount_of(CargoType::Mail), 0);
+        assert_eq!(list.amount_of(CargoType::Coal), 10);
+    }
+}
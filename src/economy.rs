@@ -1,12 +1,58 @@
 use std::collections::HashMap;
 use crate::world::{World, CargoType, TileContent, Town, Industry};
 
+/// However late it arrives, cargo still earns this share of its value.
+const MIN_TIME_FACTOR: f32 = 0.125;
+
+/// How a cargo is paid for on delivery: so much per unit for every tile
+/// carried, in full while it arrives within the grace period and then losing
+/// a share of its value for each day it is late.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct PaymentCurve {
+    pub cargo_type: CargoType,
+    /// Dollars per unit per tile, before inflation and market conditions
+    pub distance_rate: f32,
+    /// Days in transit before the cargo starts losing value
+    pub grace_days: u32,
+    /// Share of the full payment lost for each day past the grace period
+    pub decay_rate: f32,
+}
+
+pub const PAYMENT_CURVES: &[PaymentCurve] = &[
+    PaymentCurve { cargo_type: CargoType::Passengers, distance_rate: 12.0, grace_days: 10, decay_rate: 0.010 },
+    PaymentCurve { cargo_type: CargoType::Mail, distance_rate: 20.0, grace_days: 8, decay_rate: 0.012 },
+    PaymentCurve { cargo_type: CargoType::Coal, distance_rate: 8.0, grace_days: 60, decay_rate: 0.002 },
+    PaymentCurve { cargo_type: CargoType::IronOre, distance_rate: 9.0, grace_days: 50, decay_rate: 0.002 },
+    PaymentCurve { cargo_type: CargoType::Steel, distance_rate: 16.0, grace_days: 40, decay_rate: 0.003 },
+    PaymentCurve { cargo_type: CargoType::Wood, distance_rate: 10.0, grace_days: 45, decay_rate: 0.003 },
+    PaymentCurve { cargo_type: CargoType::Oil, distance_rate: 11.0, grace_days: 35, decay_rate: 0.003 },
+    PaymentCurve { cargo_type: CargoType::Goods, distance_rate: 24.0, grace_days: 15, decay_rate: 0.006 },
+    PaymentCurve { cargo_type: CargoType::Food, distance_rate: 15.0, grace_days: 5, decay_rate: 0.008 },
+];
+
+impl PaymentCurve {
+    pub fn for_cargo(cargo_type: &CargoType) -> &'static PaymentCurve {
+        PAYMENT_CURVES
+            .iter()
+            .find(|curve| curve.cargo_type == *cargo_type)
+            .expect("every cargo type has a payment curve")
+    }
+
+    /// Share of the full payment earned after `transit_days` on board.
+    pub fn time_factor(&self, transit_days: u32) -> f32 {
+        let late_days = transit_days.saturating_sub(self.grace_days) as f32;
+        (1.0 - late_days * self.decay_rate).max(MIN_TIME_FACTOR)
+    }
+}
+
 pub struct Economy {
     pub cargo_prices: HashMap<CargoType, f32>,
     pub supply_demand: HashMap<CargoType, SupplyDemand>,
     pub inflation_rate: f32,
     pub economic_state: EconomicState,
     pub month: u32,
+    /// Prices relative to the start of the game; `inflation_rate` is yearly
+    pub price_level: f32,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -23,6 +69,12 @@ pub enum EconomicState {
     Recession,
 }
 
+impl Default for Economy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Economy {
     pub fn new() -> Self {
         let mut cargo_prices = HashMap::new();
@@ -55,13 +107,14 @@ impl Economy {
             inflation_rate: 0.02,
             economic_state: EconomicState::Stable,
             month: 0,
+            price_level: 1.0,
         }
     }
 
     pub fn update(&mut self, world: &mut World) {
         self.month += 1;
         
-        if self.month % 30 == 0 {
+        if self.month.is_multiple_of(30) {
             self.update_monthly_economics(world);
         }
 
@@ -77,6 +130,7 @@ impl Economy {
             let inflated_price = base_price * (1.0 + self.inflation_rate);
             self.cargo_prices.insert(cargo_type, inflated_price);
         }
+        self.price_level *= 1.0 + self.inflation_rate / 12.0;
 
         match self.economic_state {
             EconomicState::Boom => {
@@ -105,7 +159,7 @@ impl Economy {
                             self.process_town_demand_supply(town, &mut new_demand, &mut new_supply);
                         }
                         TileContent::Industry(industry) => {
-                            self.process_industry_supply(industry, &mut new_supply);
+                            self.process_industry_supply(industry, &mut new_supply, &mut new_demand);
                         }
                         _ => {}
                     }
@@ -134,15 +188,11 @@ impl Economy {
                 2.0
             };
 
-            sd.price_multiplier = match supply_demand_ratio {
-                x if x > 1.5 => 1.5,
-                x if x < 0.5 => 0.5,
-                x => x,
-            };
+            sd.price_multiplier = supply_demand_ratio.clamp(0.5, 1.5);
 
             if let Some(base_price) = self.cargo_prices.get(cargo_type) {
                 let adjusted_price = base_price * sd.price_multiplier;
-                self.cargo_prices.insert(cargo_type.clone(), adjusted_price);
+                self.cargo_prices.insert(*cargo_type, adjusted_price);
             }
         }
     }
@@ -151,7 +201,7 @@ impl Economy {
         use rand::Rng;
         let mut rng = rand::thread_rng();
 
-        if self.month % 120 == 0 {
+        if self.month.is_multiple_of(120) {
             self.economic_state = match rng.gen_range(0..10) {
                 0..=2 => EconomicState::Boom,
                 3..=6 => EconomicState::Stable,
@@ -172,27 +222,53 @@ impl Economy {
         *supply.entry(CargoType::Mail).or_insert(0) += (population_factor * 15.0) as u32;
     }
 
-    fn process_industry_supply(&self, industry: &Industry, supply: &mut HashMap<CargoType, u32>) {
+    fn process_industry_supply(&self, industry: &Industry, supply: &mut HashMap<CargoType, u32>, demand: &mut HashMap<CargoType, u32>) {
         for cargo_type in &industry.cargo_output {
-            *supply.entry(cargo_type.clone()).or_insert(0) += industry.production_rate;
+            *supply.entry(*cargo_type).or_insert(0) += industry.production_rate;
+        }
+        // Secondary industries want their inputs
+        for cargo_type in &industry.cargo_input {
+            *demand.entry(*cargo_type).or_insert(0) += industry.production_rate;
         }
     }
 
+    /// What one unit earns for being carried `distance` tiles, if it
+    /// arrives within the grace period.
     pub fn get_cargo_price(&self, cargo_type: &CargoType, distance: f32) -> f32 {
-        let base_price = self.cargo_prices.get(cargo_type).unwrap_or(&10.0);
-        let distance_multiplier = 1.0 + (distance / 100.0).min(0.5);
+        self.current_distance_rate(cargo_type) * distance
+    }
+
+    /// Pays for `quantity` units carried `distance` tiles from the station
+    /// they were picked up at, spending `transit_days` on board.
+    pub fn calculate_delivery_payment(&self, cargo_type: &CargoType, quantity: u32, distance: f32, transit_days: u32) -> i64 {
+        let time_factor = PaymentCurve::for_cargo(cargo_type).time_factor(transit_days);
+        (self.get_cargo_price(cargo_type, distance) * quantity as f32 * time_factor) as i64
+    }
+
+    /// Dollars per unit per tile right now, with inflation, the state of the
+    /// economy and the cargo's market applied to its curve.
+    fn current_distance_rate(&self, cargo_type: &CargoType) -> f32 {
+        let market_multiplier = self.supply_demand.get(cargo_type).map_or(1.0, |sd| sd.price_multiplier);
         let economic_multiplier = match self.economic_state {
             EconomicState::Boom => 1.2,
             EconomicState::Stable => 1.0,
             EconomicState::Recession => 0.8,
         };
-        
-        base_price * distance_multiplier * economic_multiplier
+
+        PaymentCurve::for_cargo(cargo_type).distance_rate * market_multiplier * economic_multiplier * self.price_level
     }
 
-    pub fn calculate_delivery_payment(&self, cargo_type: &CargoType, quantity: u32, distance: f32) -> i64 {
-        let price_per_unit = self.get_cargo_price(cargo_type, distance);
-        (price_per_unit * quantity as f32) as i64
+    /// A cost quoted in start-of-game money, in today's money.
+    pub fn inflate(&self, cost: i64) -> i64 {
+        (cost as f32 * self.price_level).round() as i64
+    }
+
+    /// Every cargo's payment curve at today's rates.
+    pub fn get_payment_rates(&self) -> Vec<PaymentCurve> {
+        PAYMENT_CURVES.iter().map(|curve| PaymentCurve {
+            distance_rate: self.current_distance_rate(&curve.cargo_type),
+            ..curve.clone()
+        }).collect()
     }
 
     pub fn get_market_info(&self, cargo_type: &CargoType) -> MarketInfo {
@@ -201,7 +277,7 @@ impl Economy {
         let price = self.cargo_prices.get(cargo_type).unwrap_or(&0.0);
 
         MarketInfo {
-            cargo_type: cargo_type.clone(),
+            cargo_type: *cargo_type,
             current_price: *price,
             supply: sd.supply,
             demand: sd.demand,
@@ -228,7 +304,7 @@ impl Economy {
 
     fn get_top_commodities(&self) -> Vec<(CargoType, f32)> {
         let mut commodities: Vec<_> = self.cargo_prices.iter()
-            .map(|(cargo, price)| (cargo.clone(), *price))
+            .map(|(cargo, price)| (*cargo, *price))
             .collect();
         
         commodities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
@@ -258,4 +334,67 @@ pub struct EconomicReport {
     pub inflation_rate: f32,
     pub month: u32,
     pub top_commodities: Vec<(CargoType, f32)>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_is_paid_in_full_until_its_grace_period_runs_out() {
+        let coal = PaymentCurve::for_cargo(&CargoType::Coal);
+        assert_eq!(coal.time_factor(0), 1.0);
+        assert_eq!(coal.time_factor(coal.grace_days), 1.0);
+        assert!((coal.time_factor(coal.grace_days + 100) - 0.8).abs() < 1e-6);
+        // However late, it never drops below the floor
+        assert_eq!(coal.time_factor(u32::MAX), MIN_TIME_FACTOR);
+    }
+
+    #[test]
+    fn value_drops_from_the_first_day_late() {
+        for curve in PAYMENT_CURVES {
+            let late = curve.time_factor(curve.grace_days + 1);
+            assert!((late - (1.0 - curve.decay_rate)).abs() < 1e-6, "{:?}", curve.cargo_type);
+        }
+    }
+
+    #[test]
+    fn nothing_carried_or_nowhere_travelled_pays_nothing() {
+        let economy = Economy::new();
+        assert_eq!(economy.calculate_delivery_payment(&CargoType::Goods, 0, 80.0, 0), 0);
+        assert_eq!(economy.calculate_delivery_payment(&CargoType::Goods, 50, 0.0, 0), 0);
+        // Very late cargo still earns the floor
+        let on_time = economy.calculate_delivery_payment(&CargoType::Food, 100, 40.0, 0);
+        let stale = economy.calculate_delivery_payment(&CargoType::Food, 100, 40.0, 10_000);
+        assert_eq!(stale, (on_time as f32 * MIN_TIME_FACTOR) as i64);
+    }
+
+    #[test]
+    fn perishable_cargo_loses_value_sooner() {
+        let food = PaymentCurve::for_cargo(&CargoType::Food);
+        let coal = PaymentCurve::for_cargo(&CargoType::Coal);
+        assert!(food.time_factor(30) < coal.time_factor(30));
+    }
+
+    #[test]
+    fn delivery_pays_by_distance_quantity_and_transit_time() {
+        let economy = Economy::new();
+        let on_time = economy.calculate_delivery_payment(&CargoType::Coal, 10, 50.0, 0);
+        assert_eq!(on_time, 4000);
+        assert_eq!(economy.calculate_delivery_payment(&CargoType::Coal, 10, 100.0, 0), 2 * on_time);
+        assert_eq!(economy.calculate_delivery_payment(&CargoType::Coal, 20, 50.0, 0), 2 * on_time);
+        assert!(economy.calculate_delivery_payment(&CargoType::Coal, 10, 50.0, 160) < on_time);
+    }
+
+    #[test]
+    fn payment_rates_follow_inflation_and_the_economy() {
+        let mut economy = Economy::new();
+        economy.price_level = 1.5;
+        economy.economic_state = EconomicState::Recession;
+        let rates = economy.get_payment_rates();
+        assert_eq!(rates.len(), PAYMENT_CURVES.len());
+        let coal = rates.iter().find(|curve| curve.cargo_type == CargoType::Coal).unwrap();
+        assert!((coal.distance_rate - 8.0 * 1.5 * 0.8).abs() < 1e-4);
+        assert_eq!(coal.grace_days, PaymentCurve::for_cargo(&CargoType::Coal).grace_days);
+    }
+}
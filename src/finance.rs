@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::world::CargoType;
+
+/// Months of statements each company keeps: ten years.
+pub const LEDGER_MONTHS: usize = 120;
+
+/// What a transaction was for.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LedgerCategory {
+    Construction,
+    VehiclePurchase,
+    RunningCosts,
+    PropertyMaintenance,
+    Income(CargoType),
+    LoanInterest,
+}
+
+/// Money in and out over one period. Expenses are stored as positive
+/// amounts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Statement {
+    /// Months since the start of the game at which the period begins
+    pub start_month: u32,
+    pub construction: i64,
+    pub vehicle_purchases: i64,
+    pub running_costs: i64,
+    pub property_maintenance: i64,
+    pub loan_interest: i64,
+    pub income: HashMap<CargoType, i64>,
+}
+
+impl Statement {
+    fn new(start_month: u32) -> Self {
+        Self {
+            start_month,
+            ..Self::default()
+        }
+    }
+
+    pub fn record(&mut self, category: LedgerCategory, amount: i64) {
+        match category {
+            LedgerCategory::Construction => self.construction += amount,
+            LedgerCategory::VehiclePurchase => self.vehicle_purchases += amount,
+            LedgerCategory::RunningCosts => self.running_costs += amount,
+            LedgerCategory::PropertyMaintenance => self.property_maintenance += amount,
+            LedgerCategory::LoanInterest => self.loan_interest += amount,
+            LedgerCategory::Income(cargo_type) => *self.income.entry(cargo_type).or_insert(0) += amount,
+        }
+    }
+
+    pub fn total_income(&self) -> i64 {
+        self.income.values().sum()
+    }
+
+    pub fn total_expenses(&self) -> i64 {
+        self.construction + self.vehicle_purchases + self.running_costs
+            + self.property_maintenance + self.loan_interest
+    }
+
+    pub fn net_profit(&self) -> i64 {
+        self.total_income() - self.total_expenses()
+    }
+
+    fn add(&mut self, other: &Statement) {
+        self.construction += other.construction;
+        self.vehicle_purchases += other.vehicle_purchases;
+        self.running_costs += other.running_costs;
+        self.property_maintenance += other.property_maintenance;
+        self.loan_interest += other.loan_interest;
+        for (cargo_type, amount) in &other.income {
+            *self.income.entry(*cargo_type).or_insert(0) += amount;
+        }
+    }
+}
+
+/// Every transaction a company makes, rolled up month by month.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    pub current_month: Statement,
+    /// Closed months, oldest first; capped at LEDGER_MONTHS
+    pub months: Vec<Statement>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, category: LedgerCategory, amount: i64) {
+        self.current_month.record(category, amount);
+    }
+
+    /// Files the current month and starts the next one.
+    pub fn close_month(&mut self) {
+        let next = Statement::new(self.current_month.start_month + 1);
+        self.months.push(std::mem::replace(&mut self.current_month, next));
+        if self.months.len() > LEDGER_MONTHS {
+            self.months.remove(0);
+        }
+    }
+
+    /// Closed months summed into quarters, oldest first.
+    pub fn quarterly(&self) -> Vec<Statement> {
+        self.roll_up(3)
+    }
+
+    /// Closed months summed into years, oldest first.
+    pub fn yearly(&self) -> Vec<Statement> {
+        self.roll_up(12)
+    }
+
+    /// Groups closed months into periods of `length` months aligned to the
+    /// start of the game. A period still in progress holds only the months
+    /// closed so far.
+    fn roll_up(&self, length: u32) -> Vec<Statement> {
+        let mut periods: Vec<Statement> = Vec::new();
+        for month in &self.months {
+            let start_month = month.start_month / length * length;
+            match periods.last_mut() {
+                Some(period) if period.start_month == start_month => period.add(month),
+                _ => {
+                    let mut period = Statement::new(start_month);
+                    period.add(month);
+                    periods.push(period);
+                }
+            }
+        }
+        periods
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ledger with `months` closed months, each earning 100 from coal and
+    /// spending 10 on running costs.
+    fn ledger_with(months: u32) -> Ledger {
+        let mut ledger = Ledger::new();
+        for _ in 0..months {
+            ledger.record(LedgerCategory::Income(CargoType::Coal), 100);
+            ledger.record(LedgerCategory::RunningCosts, 10);
+            ledger.close_month();
+        }
+        ledger
+    }
+
+    #[test]
+    fn statement_totals_split_income_from_expenses() {
+        let mut statement = Statement::new(0);
+        statement.record(LedgerCategory::Income(CargoType::Coal), 300);
+        statement.record(LedgerCategory::Income(CargoType::Mail), 50);
+        statement.record(LedgerCategory::Construction, 120);
+        statement.record(LedgerCategory::LoanInterest, 30);
+        assert_eq!(statement.total_income(), 350);
+        assert_eq!(statement.total_expenses(), 150);
+        assert_eq!(statement.net_profit(), 200);
+    }
+
+    #[test]
+    fn quarters_and_years_align_to_the_start_of_the_game() {
+        let ledger = ledger_with(14);
+        let quarters = ledger.quarterly();
+        assert_eq!(
+            quarters.iter().map(|quarter| quarter.start_month).collect::<Vec<_>>(),
+            vec![0, 3, 6, 9, 12]
+        );
+        assert_eq!(quarters[0].net_profit(), 270);
+        // The quarter in progress holds only the two months closed so far
+        assert_eq!(quarters[4].net_profit(), 180);
+
+        let years = ledger.yearly();
+        assert_eq!(years.len(), 2);
+        assert_eq!(years[0].income.get(&CargoType::Coal), Some(&1200));
+        assert_eq!(years[0].running_costs, 120);
+        assert_eq!(years[1].start_month, 12);
+        assert_eq!(years[1].net_profit(), 180);
+    }
+
+    #[test]
+    fn empty_and_unclosed_months_roll_up_to_nothing() {
+        let mut ledger = Ledger::new();
+        assert!(ledger.quarterly().is_empty());
+        ledger.record(LedgerCategory::Construction, 500);
+        assert!(ledger.yearly().is_empty());
+        assert_eq!(ledger.current_month.total_expenses(), 500);
+    }
+
+    #[test]
+    fn refunds_take_back_expenses_and_losses_go_negative() {
+        let mut ledger = Ledger::new();
+        ledger.record(LedgerCategory::Construction, 1000);
+        // Demolishing books half the price back as a negative expense
+        ledger.record(LedgerCategory::Construction, -500);
+        ledger.record(LedgerCategory::VehiclePurchase, 2000);
+        ledger.close_month();
+
+        let month = &ledger.months[0];
+        assert_eq!(month.construction, 500);
+        assert_eq!(month.total_income(), 0);
+        assert_eq!(month.net_profit(), -2500);
+        assert_eq!(ledger.quarterly()[0].net_profit(), -2500);
+    }
+
+    #[test]
+    fn old_months_fall_off_the_ledger() {
+        let ledger = ledger_with(LEDGER_MONTHS as u32 + 5);
+        assert_eq!(ledger.months.len(), LEDGER_MONTHS);
+        assert_eq!(ledger.months[0].start_month, 5);
+        assert_eq!(ledger.current_month.start_month, LEDGER_MONTHS as u32 + 5);
+        // Quarters stay aligned to the start of the game, so the oldest one
+        // is missing its first two months
+        let quarters = ledger.quarterly();
+        assert_eq!(quarters[0].start_month, 3);
+        assert_eq!(quarters[0].net_profit(), 90);
+    }
+}
@@ -21,8 +21,56 @@ pub struct Game {
     pub build_mode: Option<crate::ui::BuildAction>,
     pub vehicle_order_mode: Option<(u32, crate::ui::VehicleOrder)>,
     pub route_creation_mode: Option<(u32, Vec<(usize, usize)>)>,
+    /// First end of a line being laid from the build menu
+    pub line_start: Option<(LineKind, (usize, usize))>,
     pub notifications: Vec<String>,
     pub notification_timer: u32,
+    /// Newest last; capped at MAX_NEWS_ITEMS
+    pub news: Vec<crate::server::NewsItem>,
+    pub settings: GameSettings,
+}
+
+/// Sides of `path[index]` that lead to its neighbours along the path.
+fn line_directions(path: &[(usize, usize)], index: usize) -> Vec<crate::world::Direction> {
+    [index.checked_sub(1), Some(index + 1)].into_iter()
+        .flatten()
+        .filter_map(|i| path.get(i))
+        .filter_map(|&next| crate::world::Direction::between(path[index], next))
+        .collect()
+}
+
+/// What a line build lays along its path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineKind {
+    Rail,
+    Road,
+}
+
+/// Options chosen for this game rather than hard-coded.
+#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// Sandbox mode: building and buying cost nothing and the company
+    /// never goes bankrupt
+    pub infinite_money: bool,
+    /// Vehicles may use other companies' track and stations
+    pub shared_infrastructure: bool,
+    /// Trains wreck road vehicles left on level crossings instead of
+    /// waiting for them
+    pub level_crossing_crashes: bool,
+    /// Weights vehicles use to choose their routes
+    pub path_costs: crate::pathfinding::PathCosts,
+}
+
+const MAX_NEWS_ITEMS: usize = 50;
+const STARTING_MONEY: i64 = 1_000_000;
+/// Share of the build price paid back when something is demolished.
+const DEMOLISH_REFUND_PERCENT: i64 = 50;
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
@@ -31,7 +79,7 @@ impl Game {
             world: crate::world::World::new(1024, 1024),
             ui: Some(crate::ui::UI::new()),
             economy: crate::economy::Economy::new(),
-            player: crate::player::Player::new("Player".to_string(), 1000000),
+            player: crate::player::Player::new("Player".to_string(), STARTING_MONEY),
             ai_players: Vec::new(),
             running: true,
             last_update: Instant::now(),
@@ -47,8 +95,11 @@ impl Game {
             build_mode: None,
             vehicle_order_mode: None,
             route_creation_mode: None,
+            line_start: None,
             notifications: Vec::new(),
             notification_timer: 0,
+            news: Vec::new(),
+            settings: GameSettings::default(),
         }
     }
 
@@ -57,7 +108,7 @@ impl Game {
             world: crate::world::World::new(1024, 1024),
             ui: None, // No UI in headless mode
             economy: crate::economy::Economy::new(),
-            player: crate::player::Player::new("Player".to_string(), 1000000),
+            player: crate::player::Player::new("Player".to_string(), STARTING_MONEY),
             ai_players: Vec::new(),
             running: true,
             last_update: Instant::now(),
@@ -73,8 +124,11 @@ impl Game {
             build_mode: None,
             vehicle_order_mode: None,
             route_creation_mode: None,
+            line_start: None,
             notifications: Vec::new(),
             notification_timer: 0,
+            news: Vec::new(),
+            settings: GameSettings::default(),
         }
     }
 
@@ -102,11 +156,11 @@ impl Game {
     }
 
     fn handle_input(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(ref mut ui) = self.ui {
-            if let Some(input) = ui.get_input()? {
-                let cursor_pos = ui.get_cursor_position();
-                self.process_input_event(input, cursor_pos);
-            }
+        if let Some(ref mut ui) = self.ui
+            && let Some(input) = ui.get_input()?
+        {
+            let cursor_pos = ui.get_cursor_position();
+            self.process_input_event(input, cursor_pos);
         }
         // In headless mode, input comes from server API calls
         Ok(())
@@ -148,6 +202,8 @@ impl Game {
                         self.economy = loaded_game.economy;
                         self.player = loaded_game.player;
                         self.ai_players = loaded_game.ai_players;
+                        self.apply_settings(loaded_game.settings);
+                        self.news = loaded_game.news;
                     }
                     Err(e) => {
                         self.add_notification(format!("Failed to load game: {}", e));
@@ -166,6 +222,11 @@ impl Game {
                     ui.show_controls = !ui.show_controls;
                 }
             }
+            crate::ui::InputEvent::ShowFinances => {
+                if let Some(ref mut ui) = self.ui {
+                    ui.show_finances = !ui.show_finances;
+                }
+            }
             crate::ui::InputEvent::BuildAction(build_action) => {
                 // Build mode visualization is handled client-side for responsiveness
                 // Server processes the actual build command and validates/executes it
@@ -182,7 +243,45 @@ impl Game {
             crate::ui::InputEvent::VehiclePurchase(vehicle_type) => {
                 self.handle_vehicle_purchase(vehicle_type, self.cursor_x, self.cursor_y);
             }
+            crate::ui::InputEvent::BorrowMoney => {
+                let _ = self.borrow_money();
+            }
+            crate::ui::InputEvent::RepayLoan => {
+                let _ = self.repay_loan();
+            }
+        }
+    }
+
+    /// Takes out another loan step and tells the player how it went.
+    pub fn borrow_money(&mut self) -> Result<String, String> {
+        let result = self.player.borrow(&self.economy)
+            .map(|amount| format!("Borrowed ${}, loan is now ${}", amount, self.player.loan));
+        let message = result.clone().unwrap_or_else(|reason| reason);
+        self.add_notification(message);
+        result
+    }
+
+    /// Pays back a loan step and tells the player how it went.
+    pub fn repay_loan(&mut self) -> Result<String, String> {
+        let result = self.player.repay()
+            .map(|amount| format!("Repaid ${}, loan is now ${}", amount, self.player.loan));
+        let message = result.clone().unwrap_or_else(|reason| reason);
+        self.add_notification(message);
+        result
+    }
+
+    pub fn apply_settings(&mut self, settings: GameSettings) {
+        self.settings = settings;
+        self.player.infinite_money = settings.infinite_money;
+        self.world.set_shared_infrastructure(settings.shared_infrastructure);
+        self.world.level_crossing_crashes = settings.level_crossing_crashes;
+        if self.world.path_costs != settings.path_costs {
+            self.world.path_costs = settings.path_costs;
+            // Runs are costed when they are traced
+            self.world.rebuild_network_graphs();
         }
+        // Routes found with the old costs or access may no longer be best
+        self.world.path_cache.clear();
     }
 
     // Server-side cursor movement (camera movement is now handled client-side)
@@ -229,6 +328,19 @@ impl Game {
         }
     }
 
+    fn add_news(&mut self, event: crate::world::WorldEvent) {
+        self.add_notification(event.message.clone());
+        self.news.push(crate::server::NewsItem {
+            day: self.player.game_time,
+            message: event.message,
+            x: event.location.0,
+            y: event.location.1,
+        });
+        if self.news.len() > MAX_NEWS_ITEMS {
+            self.news.remove(0);
+        }
+    }
+
     pub fn update_notifications(&mut self) {
         if self.notification_timer > 0 {
             self.notification_timer -= 1;
@@ -248,17 +360,75 @@ impl Game {
 
     pub fn update(&mut self) {
         self.world.update();
+        for event in self.world.drain_events() {
+            self.add_news(event);
+        }
         self.economy.update(&mut self.world);
         self.player.update(&mut self.world, &mut self.economy);
         
         for ai_player in &mut self.ai_players {
             ai_player.update(&mut self.world, &mut self.economy);
         }
+        self.remove_crashed_vehicles();
+        self.check_bankruptcy();
         
         // Update notifications timer
         self.update_notifications();
     }
 
+    /// Takes vehicles wrecked on level crossings away from their owners.
+    fn remove_crashed_vehicles(&mut self) {
+        for (owner, vehicle_id) in std::mem::take(&mut self.world.crashed_vehicles) {
+            let company = std::iter::once(&mut self.player)
+                .chain(self.ai_players.iter_mut().map(|ai| &mut ai.player))
+                .find(|player| player.company_id == owner);
+            if let Some(company) = company {
+                company.remove_vehicle(&mut self.world, vehicle_id);
+            }
+        }
+    }
+
+    /// Warns a company that is running out of time to get back in the black
+    /// and closes it once it has been in debt for too long.
+    fn check_bankruptcy(&mut self) {
+        if !self.player.game_time.is_multiple_of(30) {
+            return;
+        }
+
+        if self.player.is_bankrupt() {
+            let name = self.player.name.clone();
+            let location = self.player.stations.first().copied().unwrap_or((self.cursor_x, self.cursor_y));
+            self.add_news(crate::world::WorldEvent {
+                message: format!("{} has gone bankrupt! A new company takes its place", name),
+                location,
+            });
+            self.world.release_company_tiles(self.player.company_id);
+            let mut company = crate::player::Player::new(name, STARTING_MONEY);
+            company.game_time = self.player.game_time;
+            company.infinite_money = self.settings.infinite_money;
+            self.player = company;
+        } else if self.player.months_in_debt > 0 {
+            let months_left = crate::player::BANKRUPTCY_MONTHS - self.player.months_in_debt;
+            self.add_notification(format!(
+                "Your company is in debt! {} months left to recover before bankruptcy",
+                months_left
+            ));
+        }
+
+        let (bankrupt, solvent): (Vec<_>, Vec<_>) = std::mem::take(&mut self.ai_players)
+            .into_iter()
+            .partition(|ai| ai.player.is_bankrupt());
+        self.ai_players = solvent;
+        for ai in bankrupt {
+            self.world.release_company_tiles(ai.player.company_id);
+            let location = ai.player.stations.first().copied().unwrap_or((0, 0));
+            self.add_news(crate::world::WorldEvent {
+                message: format!("{} has gone bankrupt and closed down", ai.player.name),
+                location,
+            });
+        }
+    }
+
     // Create render data for sending to clients
     pub fn get_render_data(&self) -> crate::server::GameRenderData {
         crate::server::GameRenderData {
@@ -266,6 +436,7 @@ impl Game {
             player_data: self.create_player_render_data(),
             ui_state: self.create_ui_render_state(),
             notifications: self.notifications.clone(),
+            news: self.news.clone(),
             game_time: self.player.game_time,
         }
     }
@@ -283,7 +454,9 @@ impl Game {
                         x,
                         y,
                         terrain: tile.terrain.clone(),
-                        content: self.tile_content_to_render_data(&tile.content),
+                        height: tile.height,
+                        content: self.tile_content_to_render_data((x, y), &tile.content),
+                        owner: tile.owner,
                         ascii_char: self.world.get_ascii_char_with_vehicles(x, y, &self.player.vehicles),
                         style_color: self.get_tile_style_color(x, y),
                     });
@@ -299,7 +472,7 @@ impl Game {
                 y: v.y,
                 vehicle_type: self.vehicle_type_to_string(&v.vehicle_type),
                 state: self.vehicle_state_to_string(&v.state),
-                cargo: v.cargo.iter().map(|(cargo_type, &amount)| {
+                cargo: v.cargo.by_type().into_iter().map(|(cargo_type, amount)| {
                     (format!("{:?}", cargo_type), amount)
                 }).collect(),
                 ascii_char: crate::world::World::get_vehicle_char(&v.vehicle_type),
@@ -310,11 +483,23 @@ impl Game {
         crate::server::WorldRenderData {
             width: self.world.width,
             height: self.world.height,
+            seed: self.world.config.seed,
             tiles,
             vehicles,
+            companies: self.company_render_data(),
         }
     }
 
+    fn company_render_data(&self) -> Vec<crate::server::CompanyRenderData> {
+        std::iter::once(&self.player)
+            .chain(self.ai_players.iter().map(|ai| &ai.player))
+            .map(|player| crate::server::CompanyRenderData {
+                id: player.company_id,
+                name: player.name.clone(),
+            })
+            .collect()
+    }
+
     fn create_world_render_data(&self) -> crate::server::WorldRenderData {
         let mut tiles = Vec::new();
         
@@ -330,7 +515,9 @@ impl Game {
                         x,
                         y,
                         terrain: tile.terrain.clone(),
-                        content: self.tile_content_to_render_data(&tile.content),
+                        height: tile.height,
+                        content: self.tile_content_to_render_data((x, y), &tile.content),
+                        owner: tile.owner,
                         ascii_char: self.world.get_ascii_char_with_vehicles(x, y, &self.player.vehicles),
                         style_color: self.get_tile_style_color(x, y),
                     });
@@ -346,7 +533,7 @@ impl Game {
                 y: v.y,
                 vehicle_type: self.vehicle_type_to_string(&v.vehicle_type),
                 state: self.vehicle_state_to_string(&v.state),
-                cargo: v.cargo.iter().map(|(cargo_type, &amount)| {
+                cargo: v.cargo.by_type().into_iter().map(|(cargo_type, amount)| {
                     (format!("{:?}", cargo_type), amount)
                 }).collect(),
                 ascii_char: crate::world::World::get_vehicle_char(&v.vehicle_type),
@@ -357,26 +544,45 @@ impl Game {
         crate::server::WorldRenderData {
             width: self.world.width,
             height: self.world.height,
+            seed: self.world.config.seed,
             tiles,
             vehicles,
+            companies: self.company_render_data(),
         }
     }
 
     fn create_player_render_data(&self) -> crate::server::PlayerRenderData {
         crate::server::PlayerRenderData {
             name: self.player.name.clone(),
+            company_id: self.player.company_id,
             money: self.player.money,
+            loan: self.player.loan,
+            max_loan: self.player.max_loan(&self.economy),
+            interest_rate: self.player.interest_rate(&self.economy),
+            infinite_money: self.player.infinite_money,
             vehicle_count: self.player.vehicles.len(),
             reputation: self.player.reputation,
         }
     }
 
+    /// Income statements for the player followed by every AI company.
+    pub fn get_finances(&self) -> Vec<crate::server::CompanyFinances> {
+        std::iter::once(&self.player)
+            .chain(self.ai_players.iter().map(|ai| &ai.player))
+            .map(|player| crate::server::CompanyFinances {
+                name: player.name.clone(),
+                money: player.money,
+                loan: player.loan,
+                current_month: player.ledger.current_month.clone(),
+                monthly: player.ledger.months.clone(),
+                quarterly: player.ledger.quarterly(),
+                yearly: player.ledger.yearly(),
+            })
+            .collect()
+    }
+
     fn create_ui_render_state(&self) -> crate::server::UIRenderState {
-        let selected_tile_info = if let Some(tile) = &self.selected_tile {
-            Some(self.format_tile_info(tile))
-        } else {
-            None
-        };
+        let selected_tile_info = self.selected_tile.as_ref().map(|tile| self.format_tile_info(tile));
 
         crate::server::UIRenderState {
             cursor_x: self.cursor_x,
@@ -391,12 +597,17 @@ impl Game {
     }
 
     // Helper methods for data conversion
-    fn tile_content_to_render_data(&self, content: &crate::world::TileContent) -> crate::server::TileContentRenderData {
+    fn tile_content_to_render_data(&self, pos: (usize, usize), content: &crate::world::TileContent) -> crate::server::TileContentRenderData {
         match content {
+            crate::world::TileContent::Empty if self.world.buoys.contains(&pos) => crate::server::TileContentRenderData::Buoy,
+            crate::world::TileContent::Empty if self.world.canals.contains_key(&pos) => crate::server::TileContentRenderData::Canal,
+            crate::world::TileContent::Empty if self.world.airport_at(pos).is_some() => crate::server::TileContentRenderData::Airfield,
             crate::world::TileContent::Empty => crate::server::TileContentRenderData::Empty,
             crate::world::TileContent::Town(town) => crate::server::TileContentRenderData::Town {
                 name: town.name.clone(),
                 population: town.population,
+                houses: town.houses.len(),
+                growth_rate: town.growth_rate,
             },
             crate::world::TileContent::Industry(industry) => crate::server::TileContentRenderData::Industry {
                 industry_type: format!("{:?}", industry.industry_type),
@@ -404,24 +615,34 @@ impl Game {
                 stockpile: industry.stockpile.iter().map(|(cargo_type, &amount)| {
                     (format!("{:?}", cargo_type), amount)
                 }).collect(),
+                input_stockpile: industry.input_stockpile.iter().map(|(cargo_type, &amount)| {
+                    (format!("{:?}", cargo_type), amount)
+                }).collect(),
+                last_month_transported: industry.last_month_transported,
             },
             crate::world::TileContent::Station(station) => crate::server::TileContentRenderData::Station {
                 name: station.name.clone(),
                 station_type: format!("{:?}", station.station_type),
-                cargo_waiting: station.cargo_waiting.iter().map(|(cargo_type, &amount)| {
+                cargo_waiting: station.cargo_waiting.by_type().into_iter().map(|(cargo_type, amount)| {
                     (format!("{:?}", cargo_type), amount)
                 }).collect(),
+                accepts: station.acceptance.iter().map(|cargo_type| format!("{:?}", cargo_type)).collect(),
+                catchment_radius: station.station_type.catchment_radius(),
+                ratings: station.ratings.iter().map(|(cargo_type, rating)| {
+                    (format!("{:?}", cargo_type), rating.rating as u32 * 100 / 255)
+                }).collect(),
             },
             crate::world::TileContent::Track(track_type) => crate::server::TileContentRenderData::Track {
-                track_type: match track_type {
-                    crate::world::TrackType::Straight { horizontal } => {
-                        if *horizontal { "Horizontal".to_string() } else { "Vertical".to_string() }
-                    },
-                    crate::world::TrackType::Curve { .. } => "Curve".to_string(),
-                    crate::world::TrackType::Junction => "Junction".to_string(),
-                }
+                track_type: track_type.name(),
+                signal: self.world.signals.get(&pos).map(|signal| signal.name()),
+                signal_state: self.world.signal_state(pos),
+                level_crossing: self.world.level_crossings.contains(&pos),
             },
             crate::world::TileContent::Road => crate::server::TileContentRenderData::Road,
+            crate::world::TileContent::House(house) => crate::server::TileContentRenderData::House {
+                town: self.town_name(house.town),
+                residents: house.residents,
+            },
         }
     }
 
@@ -445,16 +666,22 @@ impl Game {
             crate::vehicle::VehicleState::Loading => "Loading".to_string(),
             crate::vehicle::VehicleState::Unloading => "Unloading".to_string(),
             crate::vehicle::VehicleState::Broken => "Broken".to_string(),
+            crate::vehicle::VehicleState::Lost { .. } => "Lost".to_string(),
+            crate::vehicle::VehicleState::Holding => "Holding".to_string(),
         }
     }
 
     fn get_tile_style_color(&self, x: usize, y: usize) -> String {
         if let Some(tile) = self.world.get_tile(x, y) {
             match &tile.content {
-                crate::world::TileContent::Town(_) => "blue".to_string(),
+                crate::world::TileContent::Town(_) | crate::world::TileContent::House(_) => "blue".to_string(),
                 crate::world::TileContent::Industry(_) => "red".to_string(),
                 crate::world::TileContent::Station(_) => "green".to_string(),
-                crate::world::TileContent::Track(_) => "yellow".to_string(),
+                crate::world::TileContent::Track(_) => match self.world.signal_state((x, y)) {
+                    Some(crate::world::SignalState::Green) => "green".to_string(),
+                    Some(crate::world::SignalState::Red) => "red".to_string(),
+                    None => "yellow".to_string(),
+                },
                 crate::world::TileContent::Road => "gray".to_string(),
                 _ => match tile.terrain {
                     crate::world::TerrainType::Grass => "lightgreen".to_string(),
@@ -491,23 +718,44 @@ impl Game {
                 }
             },
             crate::world::TileContent::Town(town) => {
-                format!("Town: {}\nPopulation: {}\nTerrain: {:?}", town.name, town.population, tile.terrain)
+                format!("Town: {}\nPopulation: {}\nHouses: {}\nTerrain: {:?}", town.name, town.population, town.houses.len(), tile.terrain)
             },
             crate::world::TileContent::Industry(industry) => {
                 format!("Industry: {:?}\nProduction: {}/month\nTerrain: {:?}", industry.industry_type, industry.production_rate, tile.terrain)
             },
             crate::world::TileContent::Station(station) => {
-                format!("Station: {}\nType: {:?}\nTerrain: {:?}", station.name, station.station_type, tile.terrain)
+                let mut ratings: Vec<String> = station.ratings.iter()
+                    .map(|(cargo_type, rating)| format!("{:?} {}%", cargo_type, rating.rating as u32 * 100 / 255))
+                    .collect();
+                ratings.sort();
+                format!("Station: {}\nType: {:?}\nOwner: {}\nAccepts: {:?}\nRatings: {}\nTerrain: {:?}", station.name, station.station_type, self.owner_name(tile.owner), station.acceptance, ratings.join(", "), tile.terrain)
             },
             crate::world::TileContent::Track(_) => {
-                format!("Railway Track\nTerrain: {:?}", tile.terrain)
+                format!("Railway Track\nOwner: {}\nTerrain: {:?}", self.owner_name(tile.owner), tile.terrain)
             },
             crate::world::TileContent::Road => {
-                format!("Road\nTerrain: {:?}", tile.terrain)
+                format!("Road\nOwner: {}\nTerrain: {:?}", self.owner_name(tile.owner), tile.terrain)
+            },
+            crate::world::TileContent::House(house) => {
+                format!("House in {}\nResidents: {}\nTerrain: {:?}", self.town_name(house.town), house.residents, tile.terrain)
             },
         }
     }
 
+    fn owner_name(&self, owner: Option<crate::world::CompanyId>) -> String {
+        std::iter::once(&self.player)
+            .chain(self.ai_players.iter().map(|ai| &ai.player))
+            .find(|player| Some(player.company_id) == owner)
+            .map_or_else(|| "Public".to_string(), |player| player.name.clone())
+    }
+
+    fn town_name(&self, (x, y): (usize, usize)) -> String {
+        match self.world.get_tile(x, y).map(|tile| &tile.content) {
+            Some(crate::world::TileContent::Town(town)) => town.name.clone(),
+            _ => "Unknown".to_string(),
+        }
+    }
+
     fn render(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(ref mut ui) = self.ui {
             ui.render(&self.world, &self.economy, &self.player)?;
@@ -545,127 +793,536 @@ impl Game {
         let (x, y) = (self.cursor_x, self.cursor_y);
 
         let _build_result = match build_action {
-            crate::ui::BuildAction::BuildRailTrack => self.build_rail_track(x, y),
+            crate::ui::BuildAction::BuildRailTrack => self.build_rail_track(x, y, None),
             crate::ui::BuildAction::BuildTrainStation => self.build_train_station(x, y),
             crate::ui::BuildAction::BuildRoad => self.build_road(x, y),
             crate::ui::BuildAction::BuildBusStop => self.build_bus_stop(x, y),
             crate::ui::BuildAction::BuyVehicle => self.buy_vehicle_at_location(x, y),
+            crate::ui::BuildAction::RaiseLand => self.terraform(x, y, true),
+            crate::ui::BuildAction::LowerLand => self.terraform(x, y, false),
+            crate::ui::BuildAction::Demolish => self.demolish(x, y),
+            crate::ui::BuildAction::RailLine => self.mark_line_end(LineKind::Rail, x, y),
+            crate::ui::BuildAction::RoadLine => self.mark_line_end(LineKind::Road, x, y),
+            crate::ui::BuildAction::BuildSignal => self.build_signal(x, y, None),
+            crate::ui::BuildAction::BuildHarbor => self.build_harbor(x, y),
+            crate::ui::BuildAction::BuildCanal => self.build_canal(x, y),
+            crate::ui::BuildAction::BuildBuoy => self.build_buoy(x, y),
+            crate::ui::BuildAction::BuildAirport { size } => self.build_airport(x, y, size),
         };
 
         // Build mode state is now managed entirely client-side
         // Server just processes the build command and returns success/failure
     }
 
-    fn build_rail_track(&mut self, x: usize, y: usize) -> bool {
-        let cost = 10000;
-        if !self.player.can_afford(cost) {
-            return false;
+    /// The first use marks where a line starts, the second builds it to
+    /// the cursor.
+    fn mark_line_end(&mut self, kind: LineKind, x: usize, y: usize) -> bool {
+        match self.line_start.take() {
+            Some((start_kind, from)) if start_kind == kind => self.build_line(kind, from, (x, y)).is_ok(),
+            _ => {
+                self.line_start = Some((kind, (x, y)));
+                self.add_notification(format!("Line starts at ({}, {}), choose the other end", x, y));
+                true
+            }
         }
+    }
 
+    /// Works out what a straight or L-shaped line of track or road from
+    /// `from` to `to` would cost without building anything. Tiles already
+    /// carrying the company's rail the right way, or any road, are reused;
+    /// the company's track running another way is joined into a junction.
+    pub fn plan_line(&self, kind: LineKind, from: (usize, usize), to: (usize, usize)) -> crate::server::LinePlan {
+        let base_cost = match kind {
+            LineKind::Rail => crate::world::TRACK_BUILD_COST,
+            LineKind::Road => crate::world::ROAD_BUILD_COST,
+        };
+        let mut new_tiles = Vec::new();
+        let mut cost = 0;
+        let mut blocked: Vec<(usize, usize)> = [from, to].into_iter()
+            .filter(|&(x, y)| self.world.get_tile(x, y).is_none())
+            .collect();
+        // Ends off the map would have the path run on without bound
+        if !blocked.is_empty() {
+            return crate::server::LinePlan { path: Vec::new(), new_tiles, cost, blocked };
+        }
+        let path = crate::world::World::line_path(from, to);
+
+        for (index, &(x, y)) in path.iter().enumerate() {
+            let Some(tile) = self.world.get_tile(x, y) else {
+                blocked.push((x, y));
+                continue;
+            };
+            let reusable = match (kind, &tile.content) {
+                (LineKind::Rail, crate::world::TileContent::Track(track)) => {
+                    self.world.is_owned_by(x, y, self.player.company_id)
+                        && line_directions(&path, index).iter().all(|&direction| track.connects(direction))
+                }
+                (LineKind::Rail, crate::world::TileContent::Station(station)) => {
+                    matches!(station.station_type, crate::world::StationType::Train)
+                        && self.world.is_usable_by(x, y, self.player.company_id)
+                }
+                (LineKind::Road, crate::world::TileContent::Road) => true,
+                (LineKind::Road, crate::world::TileContent::Track(_)) => self.world.level_crossings.contains(&(x, y)),
+                (LineKind::Road, crate::world::TileContent::Station(station)) => {
+                    matches!(station.station_type, crate::world::StationType::Road)
+                        && self.world.is_usable_by(x, y, self.player.company_id)
+                }
+                _ => false,
+            };
+            if reusable {
+                continue;
+            }
+            // A road may cross straight track at right angles
+            let crosses_track = match (kind, &tile.content) {
+                (LineKind::Road, crate::world::TileContent::Track(crate::world::TrackType::Straight { horizontal })) => {
+                    let road_sides = if *horizontal {
+                        [crate::world::Direction::North, crate::world::Direction::South]
+                    } else {
+                        [crate::world::Direction::East, crate::world::Direction::West]
+                    };
+                    !self.world.signals.contains_key(&(x, y))
+                        && line_directions(&path, index).iter().all(|direction| road_sides.contains(direction))
+                }
+                _ => false,
+            };
+            // Rail meeting the company's own track joins it
+            let joins_track = match (kind, &tile.content) {
+                (LineKind::Rail, crate::world::TileContent::Track(_)) => self.world.can_join_track(x, y, self.player.company_id),
+                _ => false,
+            };
+            if matches!(tile.terrain, crate::world::TerrainType::Water)
+                || !(crosses_track || joins_track || self.world.is_clear(x, y))
+            {
+                blocked.push((x, y));
+                continue;
+            }
+            match self.world.construction_cost(x, y, base_cost) {
+                Ok(tile_cost) => {
+                    cost += tile_cost;
+                    new_tiles.push((x, y));
+                }
+                Err(_) => blocked.push((x, y)),
+            }
+        }
+
+        crate::server::LinePlan { path, new_tiles, cost, blocked }
+    }
+
+    /// Builds a line planned by `plan_line`, picking each track piece so it
+    /// follows the line and joins existing rail at either end. Nothing is
+    /// built if any tile is blocked or the company can't pay for all of it.
+    pub fn build_line(&mut self, kind: LineKind, from: (usize, usize), to: (usize, usize)) -> Result<crate::server::LinePlan, String> {
+        let plan = self.plan_line(kind, from, to);
+        let result = if let Some(&(x, y)) = plan.blocked.first() {
+            Err(format!("{} tile(s) in the way, the first at ({}, {})", plan.blocked.len(), x, y))
+        } else if plan.new_tiles.is_empty() {
+            Err("The whole line is already built".to_string())
+        } else if !self.player.can_afford(plan.cost) {
+            Err(format!("Need ${} to build the line", plan.cost))
+        } else {
+            let company_id = self.player.company_id;
+            let base_cost = match kind {
+                LineKind::Rail => crate::world::TRACK_BUILD_COST,
+                LineKind::Road => crate::world::ROAD_BUILD_COST,
+            };
+            for (index, &(x, y)) in plan.path.iter().enumerate() {
+                if !plan.new_tiles.contains(&(x, y)) {
+                    continue;
+                }
+                let tile_cost = self.world.construction_cost(x, y, base_cost)?;
+                match kind {
+                    LineKind::Rail if matches!(self.world.get_tile(x, y).map(|tile| &tile.content), Some(crate::world::TileContent::Track(_))) => {
+                        self.world.join_track(x, y, &line_directions(&plan.path, index), company_id)?;
+                    }
+                    LineKind::Rail => {
+                        let along = line_directions(&plan.path, index);
+                        let piece = if index == 0 || index == plan.path.len() - 1 {
+                            self.world.joining_track_piece((x, y), &along, company_id)
+                        } else {
+                            crate::world::TrackType::from_connections(&along)
+                        };
+                        self.world.build_track(x, y, Some(piece), company_id)?;
+                    }
+                    LineKind::Road if matches!(self.world.get_tile(x, y).map(|tile| &tile.content), Some(crate::world::TileContent::Track(_))) => {
+                        self.world.build_level_crossing(x, y)?;
+                    }
+                    LineKind::Road => {
+                        self.world.set_tile_content(x, y, crate::world::TileContent::Road);
+                        self.world.set_tile_owner(x, y, Some(company_id));
+                    }
+                }
+                self.world.record_build_cost((x, y), tile_cost);
+            }
+            self.player.spend_money(plan.cost, crate::finance::LedgerCategory::Construction);
+            Ok(plan)
+        };
+
+        let message = match &result {
+            Ok(plan) => format!("Built {} tile(s) for ${}", plan.new_tiles.len(), plan.cost),
+            Err(reason) => reason.clone(),
+        };
+        self.add_notification(message);
+        result
+    }
+
+    /// Lays one piece of track; `None` picks the piece that joins up with
+    /// the neighbouring rail.
+    pub fn build_rail_track(&mut self, x: usize, y: usize, piece: Option<crate::world::TrackType>) -> bool {
         if let Some(tile) = self.world.get_tile(x, y) {
             // Check if tile is suitable for tracks (not water, not already has infrastructure)
             if matches!(tile.terrain, crate::world::TerrainType::Water) {
                 return false;
             }
-            if !matches!(tile.content, crate::world::TileContent::Empty) {
+            if !self.world.is_clear(x, y) {
                 return false;
             }
         } else {
             return false;
         }
 
+        let cost = match self.world.construction_cost(x, y, crate::world::TRACK_BUILD_COST) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        };
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+
         // Build the track
-        self.player.spend_money(cost);
-        self.world.set_tile_content(x, y, crate::world::TileContent::Track(
-            crate::world::TrackType::Straight { horizontal: true }
-        ));
+        if let Err(reason) = self.world.build_track(x, y, piece, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
         true
     }
 
-    fn build_train_station(&mut self, x: usize, y: usize) -> bool {
-        let cost = 50000;
+    /// Puts up a signal on track. `None` steps through the choices instead:
+    /// a block signal facing each way the track runs, then a path signal
+    /// facing each way, then no signal at all. Only a new signal costs
+    /// money.
+    pub fn build_signal(&mut self, x: usize, y: usize, signal: Option<crate::world::Signal>) -> bool {
+        let Some(crate::world::TileContent::Track(track)) = self.world.get_tile(x, y).map(|tile| &tile.content) else {
+            self.add_notification("Signals can only go on track".to_string());
+            return false;
+        };
+        let current = self.world.signals.get(&(x, y)).copied();
+        let signal = signal.or_else(|| {
+            let sides = track.connections();
+            let choices: Vec<crate::world::Signal> = [crate::world::SignalType::Block, crate::world::SignalType::Path].into_iter()
+                .flat_map(|signal_type| sides.iter().map(move |&facing| crate::world::Signal { signal_type, facing }))
+                .collect();
+            match current {
+                Some(current) => choices.iter().skip_while(|&&choice| choice != current).nth(1).copied(),
+                None => choices.first().copied(),
+            }
+        });
+
+        let Some(signal) = signal else {
+            if let Err(reason) = self.world.remove_signal(x, y, self.player.company_id) {
+                self.add_notification(reason);
+                return false;
+            }
+            self.add_notification("Signal removed".to_string());
+            return true;
+        };
+        let cost = if current.is_none() { crate::world::SIGNAL_BUILD_COST } else { 0 };
         if !self.player.can_afford(cost) {
             return false;
         }
+        if let Err(reason) = self.world.build_signal(x, y, signal, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.player.spend_money(cost, crate::finance::LedgerCategory::Construction);
+        self.add_notification(signal.name());
+        true
+    }
 
+    fn build_train_station(&mut self, x: usize, y: usize) -> bool {
         if let Some(tile) = self.world.get_tile(x, y) {
             if matches!(tile.terrain, crate::world::TerrainType::Water) {
                 return false;
             }
-            if !matches!(tile.content, crate::world::TileContent::Empty) {
+            if !self.world.is_clear(x, y) {
                 return false;
             }
         } else {
             return false;
         }
 
-        self.player.spend_money(cost);
-        let station = crate::world::Station {
-            name: format!("Station {}", self.world.stations.len() + 1),
-            station_type: crate::world::StationType::Train,
-            cargo_waiting: std::collections::HashMap::new(),
-            connections: Vec::new(),
+        let cost = match self.world.construction_cost(x, y, crate::world::StationType::Train.build_cost()) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
         };
-        
-        self.world.set_tile_content(x, y, crate::world::TileContent::Station(station));
-        self.world.stations.push((x, y));
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+
+        if let Err(reason) = self.world.build_station(x, y, crate::world::StationType::Train, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
         self.player.stations.push((x, y));
         true
     }
 
     fn build_road(&mut self, x: usize, y: usize) -> bool {
-        let cost = 5000;
-        if !self.player.can_afford(cost) {
-            return false;
-        }
-
         if let Some(tile) = self.world.get_tile(x, y) {
             if matches!(tile.terrain, crate::world::TerrainType::Water) {
                 return false;
             }
-            if !matches!(tile.content, crate::world::TileContent::Empty) {
+            if matches!(tile.content, crate::world::TileContent::Track(_)) {
+                return self.build_level_crossing(x, y);
+            }
+            if !self.world.is_clear(x, y) {
                 return false;
             }
         } else {
             return false;
         }
 
-        self.player.spend_money(cost);
+        let cost = match self.world.construction_cost(x, y, crate::world::ROAD_BUILD_COST) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        };
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+
+        self.pay_for_building((x, y), cost);
         self.world.set_tile_content(x, y, crate::world::TileContent::Road);
+        self.world.set_tile_owner(x, y, Some(self.player.company_id));
+        true
+    }
+
+    /// Takes a road across straight track. The track keeps its owner; the
+    /// road over it is open to everyone.
+    fn build_level_crossing(&mut self, x: usize, y: usize) -> bool {
+        let cost = match self.world.construction_cost(x, y, crate::world::ROAD_BUILD_COST) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        };
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+        if let Err(reason) = self.world.build_level_crossing(x, y) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
         true
     }
 
     fn build_bus_stop(&mut self, x: usize, y: usize) -> bool {
-        let cost = 25000;
+        if let Some(tile) = self.world.get_tile(x, y) {
+            if matches!(tile.terrain, crate::world::TerrainType::Water) {
+                return false;
+            }
+            if !self.world.is_clear(x, y) {
+                return false;
+            }
+        } else {
+            return false;
+        }
+
+        let cost = match self.world.construction_cost(x, y, crate::world::StationType::Road.build_cost()) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        };
         if !self.player.can_afford(cost) {
             return false;
         }
 
+        if let Err(reason) = self.world.build_station(x, y, crate::world::StationType::Road, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
+        self.player.stations.push((x, y));
+        true
+    }
+
+    fn build_harbor(&mut self, x: usize, y: usize) -> bool {
         if let Some(tile) = self.world.get_tile(x, y) {
             if matches!(tile.terrain, crate::world::TerrainType::Water) {
                 return false;
             }
-            if !matches!(tile.content, crate::world::TileContent::Empty) {
+            if !self.world.is_clear(x, y) {
                 return false;
             }
         } else {
             return false;
         }
 
-        self.player.spend_money(cost);
-        let station = crate::world::Station {
-            name: format!("Bus Stop {}", self.world.stations.len() + 1),
-            station_type: crate::world::StationType::Road,
-            cargo_waiting: std::collections::HashMap::new(),
-            connections: Vec::new(),
+        let cost = match self.world.construction_cost(x, y, crate::world::StationType::Harbor.build_cost()) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
         };
-        
-        self.world.set_tile_content(x, y, crate::world::TileContent::Station(station));
-        self.world.stations.push((x, y));
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+
+        if let Err(reason) = self.world.build_station(x, y, crate::world::StationType::Harbor, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
         self.player.stations.push((x, y));
         true
     }
 
+    /// Digs a canal ships can sail along.
+    fn build_canal(&mut self, x: usize, y: usize) -> bool {
+        let cost = match self.world.construction_cost(x, y, crate::world::CANAL_BUILD_COST) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        };
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+        if let Err(reason) = self.world.build_canal(x, y, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
+        true
+    }
+
+    /// Places a buoy ships can be ordered to pass on the way.
+    fn build_buoy(&mut self, x: usize, y: usize) -> bool {
+        if !self.player.can_afford(crate::world::BUOY_BUILD_COST) {
+            return false;
+        }
+        if let Err(reason) = self.world.build_buoy(x, y, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), crate::world::BUOY_BUILD_COST);
+        true
+    }
+
+    /// Lays out an airport with its terminal at the cursor.
+    fn build_airport(&mut self, x: usize, y: usize, size: crate::world::AirportSize) -> bool {
+        let cost = match self.world.construction_cost(x, y, size.build_cost()) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        };
+        if !self.player.can_afford(cost) {
+            return false;
+        }
+        if let Err(reason) = self.world.build_airport(x, y, size, self.player.company_id) {
+            self.add_notification(reason);
+            return false;
+        }
+        self.pay_for_building((x, y), cost);
+        self.player.stations.push((x, y));
+        true
+    }
+
+    fn terraform(&mut self, x: usize, y: usize, raise: bool) -> bool {
+        let budget = if self.player.infinite_money { i64::MAX } else { self.player.money };
+        let result = if raise {
+            self.world.raise_land(x, y, budget)
+        } else {
+            self.world.lower_land(x, y, budget)
+        };
+
+        match result {
+            Ok(cost) => self.player.spend_money(cost, crate::finance::LedgerCategory::Construction),
+            Err(reason) => {
+                self.add_notification(reason);
+                false
+            }
+        }
+    }
+
+    fn demolish(&mut self, x: usize, y: usize) -> bool {
+        // An airport goes as a whole, along with its terminal
+        let (x, y) = self.world.airport_at((x, y)).unwrap_or((x, y));
+        let Some(tile) = self.world.get_tile(x, y) else {
+            return false;
+        };
+        // Anything built before prices were kept refunds its list price
+        let list_price = match &tile.content {
+            crate::world::TileContent::Track(_) => crate::world::TRACK_BUILD_COST,
+            crate::world::TileContent::Road => crate::world::ROAD_BUILD_COST,
+            crate::world::TileContent::Station(_) if self.world.airports.contains_key(&(x, y)) => {
+                self.world.airports[&(x, y)].size.build_cost()
+            }
+            crate::world::TileContent::Station(station) => station.station_type.build_cost(),
+            _ if self.world.buoys.contains(&(x, y)) => crate::world::BUOY_BUILD_COST,
+            _ if self.world.canals.contains_key(&(x, y)) => crate::world::CANAL_BUILD_COST,
+            _ => {
+                self.add_notification("Nothing here can be demolished".to_string());
+                return false;
+            }
+        };
+        if !self.world.is_owned_by(x, y, self.player.company_id) {
+            self.add_notification(format!("Only the owner can demolish this: {}", self.owner_name(tile.owner)));
+            return false;
+        }
+        let paid = self.world.build_costs.get(&(x, y)).copied().unwrap_or(list_price);
+        let occupied = std::iter::once(&self.player)
+            .chain(self.ai_players.iter().map(|ai| &ai.player))
+            .flat_map(|player| &player.vehicles)
+            .any(|vehicle| vehicle.occupies((x, y)));
+        if occupied {
+            self.add_notification("A vehicle is in the way".to_string());
+            return false;
+        }
+
+        match self.world.demolish(x, y) {
+            Ok(crate::world::TileContent::Station(station)) => {
+                self.player.remove_station(&mut self.world, (x, y));
+                // Other companies may have been calling here through shared access
+                for ai_player in &mut self.ai_players {
+                    ai_player.player.remove_station(&mut self.world, (x, y));
+                }
+                self.add_notification(format!("{} demolished", station.name));
+            }
+            Ok(_) => {}
+            Err(reason) => {
+                self.add_notification(reason);
+                return false;
+            }
+        }
+        self.player.refund(paid * DEMOLISH_REFUND_PERCENT / 100, crate::finance::LedgerCategory::Construction);
+        true
+    }
+
+    /// Pays for something just built on `pos` and notes the price, so
+    /// demolishing it refunds part of what was actually paid.
+    fn pay_for_building(&mut self, pos: (usize, usize), cost: i64) {
+        self.player.spend_money(cost, crate::finance::LedgerCategory::Construction);
+        self.world.record_build_cost(pos, cost);
+    }
+
     fn buy_vehicle_at_location(&mut self, x: usize, y: usize) -> bool {
         // For now, just buy a basic bus
         let vehicle_type = crate::vehicle::VehicleType::Road {
@@ -710,19 +1367,19 @@ impl Game {
             },
             crate::ui::VehicleOrder::StartRoute => {
                 // For now, start simple movement
-                if let Some(vehicle) = self.player.vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-                    if !vehicle.route.is_empty() {
-                        vehicle.state = crate::vehicle::VehicleState::Moving {
-                            from: (vehicle.x, vehicle.y),
-                            to: vehicle.route[0],
-                            progress: 0.0,
-                        };
-                    }
+                if let Some(vehicle) = self.player.vehicles.iter_mut().find(|v| v.id == vehicle_id)
+                    && !vehicle.route.is_empty()
+                {
+                    vehicle.state = crate::vehicle::VehicleState::Moving {
+                        from: (vehicle.x, vehicle.y),
+                        to: vehicle.route[0],
+                        progress: 0.0,
+                    };
                 }
             },
             crate::ui::VehicleOrder::Stop => {
                 if let Some(vehicle) = self.player.vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-                    vehicle.state = crate::vehicle::VehicleState::Idle;
+                    vehicle.stop(&mut self.world);
                 }
             },
             crate::ui::VehicleOrder::SendToDepot => {
@@ -737,15 +1394,21 @@ impl Game {
 
         match order {
             crate::ui::VehicleOrder::GoToLocation => {
-                if let Some(vehicle) = self.player.vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-                    // Set simple route to destination
-                    vehicle.route = vec![(x, y)];
-                    vehicle.route_index = 0;
-                    vehicle.state = crate::vehicle::VehicleState::Moving {
-                        from: (vehicle.x, vehicle.y),
-                        to: (x, y),
-                        progress: 0.0,
-                    };
+                let world = &self.world;
+                let sent = self.player.vehicles.iter_mut()
+                    .find(|v| v.id == vehicle_id)
+                    .map(|vehicle| -> Result<(), String> {
+                        // Set simple route to destination
+                        vehicle.assign_route(world, vec![(x, y)])?;
+                        vehicle.state = crate::vehicle::VehicleState::Moving {
+                            from: (vehicle.x, vehicle.y),
+                            to: (x, y),
+                            progress: 0.0,
+                        };
+                        Ok(())
+                    });
+                if let Some(Err(reason)) = sent {
+                    self.add_notification(reason);
                 }
                 // Clear the order mode
                 self.vehicle_order_mode = None;
@@ -788,9 +1451,14 @@ impl Game {
         if let Some((vehicle_id, waypoints)) = self.route_creation_mode.clone() {
             if waypoints.len() >= 2 {
                 // Assign route to vehicle
-                if let Some(vehicle) = self.player.vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-                    vehicle.assign_route(waypoints.clone());
-                    self.add_notification(format!("Route created for vehicle {} with {} stations", vehicle_id, waypoints.len()));
+                let world = &self.world;
+                let assigned = self.player.vehicles.iter_mut()
+                    .find(|v| v.id == vehicle_id)
+                    .map(|vehicle| vehicle.assign_route(world, waypoints.clone()));
+                match assigned {
+                    Some(Ok(())) => self.add_notification(format!("Route created for vehicle {} with {} stations", vehicle_id, waypoints.len())),
+                    Some(Err(reason)) => self.add_notification(reason),
+                    None => {}
                 }
             } else {
                 self.add_notification(format!("Route needs at least 2 stations! Currently have: {}", waypoints.len()));
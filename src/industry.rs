@@ -0,0 +1,99 @@
+use crate::world::{CargoType, IndustryType};
+
+/// What an industry type consumes and produces. Primary industries have no
+/// inputs and produce every tick; secondary industries run one batch of
+/// their recipe for each full set of inputs in the stockpile.
+pub struct IndustrySpec {
+    pub industry_type: IndustryType,
+    pub name: &'static str,
+    /// Cargo consumed by one batch
+    pub inputs: &'static [(CargoType, u32)],
+    /// Cargo produced by one batch
+    pub outputs: &'static [(CargoType, u32)],
+    /// Only placed on water tiles rather than on land
+    pub offshore: bool,
+}
+
+pub const INDUSTRY_SPECS: &[IndustrySpec] = &[
+    IndustrySpec {
+        industry_type: IndustryType::CoalMine,
+        name: "Coal Mine",
+        inputs: &[],
+        outputs: &[(CargoType::Coal, 1)],
+        offshore: false,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::IronOreMine,
+        name: "Iron Ore Mine",
+        inputs: &[],
+        outputs: &[(CargoType::IronOre, 1)],
+        offshore: false,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::SteelMill,
+        name: "Steel Mill",
+        inputs: &[(CargoType::Coal, 1), (CargoType::IronOre, 1)],
+        outputs: &[(CargoType::Steel, 1)],
+        offshore: false,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::Factory,
+        name: "Factory",
+        inputs: &[(CargoType::Steel, 1)],
+        outputs: &[(CargoType::Goods, 2)],
+        offshore: false,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::Farm,
+        name: "Farm",
+        inputs: &[],
+        outputs: &[(CargoType::Food, 1)],
+        offshore: false,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::Sawmill,
+        name: "Sawmill",
+        inputs: &[],
+        outputs: &[(CargoType::Wood, 1)],
+        offshore: false,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::OilRig,
+        name: "Oil Rig",
+        inputs: &[],
+        outputs: &[(CargoType::Oil, 1)],
+        offshore: true,
+    },
+    IndustrySpec {
+        industry_type: IndustryType::Refinery,
+        name: "Oil Refinery",
+        inputs: &[(CargoType::Oil, 2)],
+        outputs: &[(CargoType::Goods, 1)],
+        offshore: false,
+    },
+];
+
+impl IndustrySpec {
+    pub fn for_type(industry_type: &IndustryType) -> &'static IndustrySpec {
+        INDUSTRY_SPECS
+            .iter()
+            .find(|spec| spec.industry_type == *industry_type)
+            .expect("every industry type has a spec")
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn accepts(&self, cargo_type: CargoType) -> bool {
+        self.inputs.iter().any(|&(input, _)| input == cargo_type)
+    }
+
+    pub fn input_cargo(&self) -> Vec<CargoType> {
+        self.inputs.iter().map(|&(cargo_type, _)| cargo_type).collect()
+    }
+
+    pub fn output_cargo(&self) -> Vec<CargoType> {
+        self.outputs.iter().map(|&(cargo_type, _)| cargo_type).collect()
+    }
+}
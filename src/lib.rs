@@ -1,9 +1,15 @@
 pub mod game;
 pub mod world;
+pub mod terrain;
+pub mod industry;
+pub mod cargo;
 pub mod ui;
 pub mod player;
 pub mod vehicle;
+pub mod pathfinding;
+pub mod network;
 pub mod economy;
+pub mod finance;
 pub mod ai;
 pub mod save;
 pub mod server;
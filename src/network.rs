@@ -0,0 +1,482 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::pathfinding::{Heuristic, PathKind};
+use crate::world::{CompanyId, Direction, TileContent, World};
+
+/// One leg of a route over the network graph: leave `from` by `exit` and
+/// follow the track or road up to `to`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hop {
+    pub from: (usize, usize),
+    pub exit: Direction,
+    pub to: (usize, usize),
+}
+
+/// A run of track or road leaving a node.
+#[derive(Clone, Debug, PartialEq)]
+struct Edge {
+    exit: Direction,
+    to: (usize, usize),
+    cost: u32,
+    /// Companies whose permission is needed to use the run
+    owners: Vec<CompanyId>,
+}
+
+impl Edge {
+    fn usable_by(&self, world: &World, company: CompanyId) -> bool {
+        world.shared_infrastructure || self.owners.iter().all(|&owner| owner == company)
+    }
+}
+
+/// Rail or road collapsed into nodes (junctions, stations and dead ends)
+/// and the runs between them, so routes are planned a run at a time
+/// rather than a tile at a time.
+#[derive(Clone, Debug)]
+pub struct NetworkGraph {
+    kind: PathKind,
+    edges: HashMap<(usize, usize), Vec<Edge>>,
+    /// Tiles changed since the graph was last brought up to date
+    dirty: Vec<(usize, usize)>,
+    /// Network version at which each node's runs were last traced, and at
+    /// which each changed tile was taken into account
+    traced: HashMap<(usize, usize), u64>,
+    /// Bumped whenever a refresh finds the runs really did change, unlike
+    /// the network version, which moves with every tile built on
+    revision: u64,
+}
+
+impl NetworkGraph {
+    pub fn new(kind: PathKind) -> Self {
+        Self {
+            kind,
+            edges: HashMap::new(),
+            dirty: Vec::new(),
+            traced: HashMap::new(),
+            revision: 0,
+        }
+    }
+
+    /// Changes each time the runs of the graph change, so vehicles that
+    /// found no route know when another search might turn one up.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Counts as a change to every run, e.g. when access to other
+    /// companies' infrastructure was granted or withdrawn.
+    pub fn touch(&mut self) {
+        self.revision += 1;
+    }
+
+    /// Notes a changed tile, to be taken into account on the next refresh.
+    pub fn mark_changed(&mut self, pos: (usize, usize)) {
+        self.dirty.push(pos);
+    }
+
+    /// Traces the whole network again, e.g. after the path costs changed.
+    pub fn rebuild(&mut self, world: &World) {
+        for pos in std::mem::take(&mut self.dirty) {
+            self.traced.insert(pos, world.network_version);
+        }
+        self.edges.clear();
+        self.revision += 1;
+        for y in 0..world.height {
+            for x in 0..world.width {
+                if is_node(world, self.kind, (x, y)) {
+                    self.edges.insert((x, y), self.trace(world, (x, y)));
+                }
+            }
+        }
+    }
+
+    /// Retraces only the runs that pass by the tiles changed since the
+    /// last call.
+    pub fn refresh(&mut self, world: &World) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        // Every node whose runs may have passed by a changed tile
+        let mut affected = HashSet::new();
+        for pos in std::mem::take(&mut self.dirty) {
+            self.traced.insert(pos, world.network_version);
+            affected.insert(pos);
+            for direction in Direction::ALL {
+                let Some(next) = direction.step(pos) else {
+                    continue;
+                };
+                // Whether or not it is still a node, so that one that just
+                // stopped being one loses its runs
+                affected.insert(next);
+                if is_node(world, self.kind, next) {
+                    continue;
+                }
+                for exit in links(world, self.kind, next) {
+                    if let Some(tiles) = walk(world, self.kind, next, exit, None) {
+                        affected.extend(tiles.last());
+                    }
+                }
+            }
+        }
+
+        // The far ends of their new runs must link back to them
+        let mut traced = HashMap::new();
+        for &node in &affected {
+            if is_node(world, self.kind, node) {
+                traced.insert(node, self.trace(world, node));
+            }
+        }
+        let far_ends: Vec<(usize, usize)> = traced.values()
+            .flatten()
+            .map(|edge| edge.to)
+            .filter(|to| !traced.contains_key(to))
+            .collect();
+        for node in far_ends {
+            traced.insert(node, self.trace(world, node));
+        }
+
+        let mut changed = false;
+        for node in affected {
+            if !traced.contains_key(&node) {
+                changed |= self.edges.remove(&node).is_some();
+            }
+        }
+        for (node, edges) in traced {
+            self.traced.insert(node, world.network_version);
+            changed |= self.edges.get(&node) != Some(&edges);
+            self.edges.insert(node, edges);
+        }
+        if changed {
+            self.revision += 1;
+        }
+    }
+
+    /// Whether none of the hops has been retraced since `version`.
+    pub fn hops_are_current(&self, hops: &[Hop], version: u64) -> bool {
+        hops.iter()
+            .flat_map(|hop| [hop.from, hop.to])
+            .all(|pos| self.traced.get(&pos).is_none_or(|&traced| traced <= version))
+    }
+
+    /// The cheapest hops from `start` to `goal` for `owner`'s vehicles. None
+    /// if either end is off the network or the network doesn't link them.
+    pub fn find_route(
+        &self,
+        world: &World,
+        start: (usize, usize),
+        goal: (usize, usize),
+        owner: CompanyId,
+        heuristic: Heuristic,
+    ) -> Option<Vec<Hop>> {
+        if start == goal {
+            return Some(Vec::new());
+        }
+        let start_hops = self.hops_from(world, start, Some(goal))?;
+
+        // Hops into the goal from the nodes at either end of its run
+        let mut goal_hops: HashMap<(usize, usize), Vec<(Hop, u32)>> = HashMap::new();
+        if !is_node(world, self.kind, goal) {
+            let exits = links(world, self.kind, goal);
+            if exits.len() != 2 {
+                return None;
+            }
+            for exit in exits {
+                let Some(mut tiles) = walk(world, self.kind, goal, exit, None) else {
+                    continue;
+                };
+                tiles.reverse();
+                let end = tiles.remove(0);
+                tiles.push(goal);
+                let Some(entry) = Direction::between(end, tiles[0]) else {
+                    continue;
+                };
+                if run_owners(world, self.kind, &tiles).iter().all(|&company| world.shared_infrastructure || company == owner) {
+                    let hop = Hop { from: end, exit: entry, to: goal };
+                    goal_hops.entry(end).or_default().push((hop, run_cost(world, end, &tiles)));
+                }
+            }
+        }
+
+        let step = world.path_costs.step;
+        let mut open_set = BinaryHeap::new();
+        let mut came_from: HashMap<(usize, usize), Hop> = HashMap::new();
+        let mut g_score = HashMap::new();
+
+        open_set.push(Reverse((heuristic.estimate(start, goal, step), 0, start)));
+        g_score.insert(start, 0);
+
+        while let Some(Reverse((_, cost, current))) = open_set.pop() {
+            if current == goal {
+                let mut hops = Vec::new();
+                let mut current_pos = current;
+                while let Some(&hop) = came_from.get(&current_pos) {
+                    hops.push(hop);
+                    current_pos = hop.from;
+                }
+                hops.reverse();
+                return Some(hops);
+            }
+
+            // A cheaper way here was found after this entry was queued
+            if cost > g_score[&current] {
+                continue;
+            }
+
+            let mut hops = if current == start {
+                start_hops.iter()
+                    .filter(|(_, edge)| edge.usable_by(world, owner))
+                    .map(|(hop, edge)| (*hop, edge.cost))
+                    .collect()
+            } else {
+                self.edges.get(&current)
+                    .into_iter()
+                    .flatten()
+                    .filter(|edge| edge.usable_by(world, owner))
+                    .map(|edge| (Hop { from: current, exit: edge.exit, to: edge.to }, edge.cost))
+                    .collect::<Vec<_>>()
+            };
+            hops.extend(goal_hops.get(&current).into_iter().flatten().copied());
+
+            for (hop, hop_cost) in hops {
+                let tentative_g_score = cost + hop_cost;
+                if tentative_g_score < *g_score.get(&hop.to).unwrap_or(&u32::MAX) {
+                    came_from.insert(hop.to, hop);
+                    g_score.insert(hop.to, tentative_g_score);
+                    let estimate = tentative_g_score + heuristic.estimate(hop.to, goal, step);
+                    open_set.push(Reverse((estimate, tentative_g_score, hop.to)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The runs leaving `pos`, whether it is a node or part of a run. None
+    /// if it isn't on the network.
+    fn hops_from(&self, world: &World, pos: (usize, usize), stop: Option<(usize, usize)>) -> Option<Vec<(Hop, Edge)>> {
+        let edges = if is_node(world, self.kind, pos) {
+            self.edges.get(&pos).cloned().unwrap_or_default()
+        } else {
+            let exits = links(world, self.kind, pos);
+            if exits.len() != 2 {
+                return None;
+            }
+            exits.into_iter()
+                .map(|exit| self.edge(world, pos, exit, stop))
+                .collect::<Option<Vec<_>>>()?
+        };
+        Some(edges.into_iter().map(|edge| (Hop { from: pos, exit: edge.exit, to: edge.to }, edge)).collect())
+    }
+
+    /// Every run leaving a node.
+    fn trace(&self, world: &World, node: (usize, usize)) -> Vec<Edge> {
+        links(world, self.kind, node).into_iter()
+            .filter_map(|exit| self.edge(world, node, exit, None))
+            .collect()
+    }
+
+    fn edge(&self, world: &World, from: (usize, usize), exit: Direction, stop: Option<(usize, usize)>) -> Option<Edge> {
+        let tiles = walk(world, self.kind, from, exit, stop)?;
+        Some(Edge {
+            exit,
+            to: *tiles.last()?,
+            cost: run_cost(world, from, &tiles),
+            owners: run_owners(world, self.kind, &tiles),
+        })
+    }
+}
+
+/// The tiles a hop passes over, `hop.to` included but not `hop.from`. None
+/// if the track or road no longer leads there.
+pub fn hop_tiles(world: &World, kind: PathKind, hop: Hop) -> Option<Vec<(usize, usize)>> {
+    walk(world, kind, hop.from, hop.exit, Some(hop.to))
+        .filter(|tiles| tiles.last() == Some(&hop.to))
+}
+
+/// Directions from `pos` in which the network carries on, whoever owns it.
+fn links(world: &World, kind: PathKind, pos: (usize, usize)) -> Vec<Direction> {
+    match kind {
+        PathKind::Rail => world.rail_connections(pos).into_iter()
+            .filter(|&direction| world.rail_linked(pos, direction))
+            .collect(),
+        PathKind::Road => Direction::ORTHOGONAL.into_iter()
+            .filter(|&direction| {
+                road_side(world, pos, direction)
+                    && direction.step(pos).is_some_and(|next| road_side(world, next, direction.opposite()))
+            })
+            .collect(),
+        PathKind::Water => Vec::new(),
+    }
+}
+
+/// Whether road traffic can leave `pos` by `direction`.
+fn road_side(world: &World, pos: (usize, usize), direction: Direction) -> bool {
+    match world.get_tile(pos.0, pos.1).map(|tile| &tile.content) {
+        Some(TileContent::Road | TileContent::Station(_)) => true,
+        Some(TileContent::Track(_)) => world.crossing_road_sides(pos).is_some_and(|sides| sides.contains(&direction)),
+        _ => false,
+    }
+}
+
+/// Stations, junctions and dead ends; everything else on the network is
+/// part of a run between two of them.
+fn is_node(world: &World, kind: PathKind, pos: (usize, usize)) -> bool {
+    let station = matches!(world.get_tile(pos.0, pos.1).map(|tile| &tile.content), Some(TileContent::Station(_)));
+    let exits = links(world, kind, pos).len();
+    station || (exits > 0 && exits != 2)
+}
+
+/// Follows the run leaving `from` by `exit` until it reaches a node or
+/// `stop`, and returns the tiles passed, the last one included. None if it
+/// circles back to `from` without meeting a node.
+fn walk(
+    world: &World,
+    kind: PathKind,
+    from: (usize, usize),
+    exit: Direction,
+    stop: Option<(usize, usize)>,
+) -> Option<Vec<(usize, usize)>> {
+    let mut tiles = Vec::new();
+    let (mut pos, mut direction) = (from, exit);
+    loop {
+        let next = direction.step(pos)?;
+        tiles.push(next);
+        if Some(next) == stop || is_node(world, kind, next) {
+            return Some(tiles);
+        }
+        if next == from {
+            return None;
+        }
+        direction = links(world, kind, next).into_iter().find(|&onward| onward != direction.opposite())?;
+        pos = next;
+    }
+}
+
+fn run_cost(world: &World, from: (usize, usize), tiles: &[(usize, usize)]) -> u32 {
+    std::iter::once(&from).chain(tiles)
+        .zip(tiles)
+        .map(|(&step_from, &step_to)| world.path_costs.step_cost(world, step_from, step_to))
+        .sum()
+}
+
+/// Owners of the tiles on a run that vehicles need permission to use: all
+/// of them on rail, only stations on road.
+fn run_owners(world: &World, kind: PathKind, tiles: &[(usize, usize)]) -> Vec<CompanyId> {
+    let mut owners = Vec::new();
+    for &(x, y) in tiles {
+        let Some(tile) = world.get_tile(x, y) else {
+            continue;
+        };
+        let needs_access = kind == PathKind::Rail || matches!(tile.content, TileContent::Station(_));
+        if let Some(owner) = tile.owner.filter(|owner| needs_access && !owners.contains(owner)) {
+            owners.push(owner);
+        }
+    }
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::TrackType;
+
+    /// Brings the graphs up to date the incremental way and checks the rail
+    /// graph against one traced from scratch.
+    fn assert_matches_rebuild(world: &mut World) {
+        world.refresh_network_graphs();
+        let mut rebuilt = NetworkGraph::new(PathKind::Rail);
+        rebuilt.rebuild(world);
+        assert_eq!(world.rail_graph.edges, rebuilt.edges);
+    }
+
+    #[test]
+    fn refresh_keeps_up_with_building_and_demolishing() {
+        let mut world = World::flat(16, 16);
+        for x in 2..10 {
+            world.build_track(x, 4, None, 0).unwrap();
+            assert_matches_rebuild(&mut world);
+        }
+        // A branch joined into the middle of the line makes a junction
+        world.join_track(5, 4, &[Direction::South], 0).unwrap();
+        assert_matches_rebuild(&mut world);
+        for y in 5..9 {
+            world.build_track(5, y, None, 0).unwrap();
+            assert_matches_rebuild(&mut world);
+        }
+        assert_eq!(world.rail_graph.edges[&(5, 4)].len(), 3);
+
+        world.demolish(7, 4).unwrap();
+        assert_matches_rebuild(&mut world);
+        world.demolish(5, 6).unwrap();
+        assert_matches_rebuild(&mut world);
+    }
+
+    /// The tiles `hops` pass over, in order.
+    fn follow(world: &World, hops: &[Hop]) -> Vec<(usize, usize)> {
+        hops.iter().flat_map(|&hop| hop_tiles(world, PathKind::Rail, hop).expect("hop no longer leads anywhere")).collect()
+    }
+
+    #[test]
+    fn routes_run_from_mid_run_and_keep_to_usable_track() {
+        let straight = TrackType::Straight { horizontal: true };
+        let mut world = World::flat(16, 8);
+        for x in 2..14 {
+            world.build_track(x, 4, Some(straight.clone()), if x < 10 { 0 } else { 1 }).unwrap();
+        }
+        world.refresh_network_graphs();
+
+        let hops = world.rail_graph.find_route(&world, (4, 4), (7, 4), 0, Heuristic::Chebyshev).unwrap();
+        assert_eq!(follow(&world, &hops), vec![(5, 4), (6, 4), (7, 4)]);
+        assert_eq!(world.rail_graph.find_route(&world, (4, 4), (4, 4), 0, Heuristic::Chebyshev), Some(Vec::new()));
+
+        // The far end is another company's track
+        assert!(world.rail_graph.find_route(&world, (4, 4), (12, 4), 0, Heuristic::Chebyshev).is_none());
+
+        // Once that company has gone bankrupt its track is open to anyone
+        world.release_company_tiles(1);
+        world.refresh_network_graphs();
+        let hops = world.rail_graph.find_route(&world, (4, 4), (12, 4), 0, Heuristic::Chebyshev).unwrap();
+        assert_eq!(follow(&world, &hops).last(), Some(&(12, 4)));
+        assert!(world.rail_graph.find_route(&world, (4, 4), (12, 6), 0, Heuristic::Chebyshev).is_none());
+    }
+
+    #[test]
+    fn a_loop_without_nodes_has_no_runs_but_can_be_travelled() {
+        let mut world = World::flat(8, 8);
+        let corners = [
+            ((2, 2), Direction::East, Direction::South),
+            ((3, 2), Direction::West, Direction::South),
+            ((3, 3), Direction::North, Direction::West),
+            ((2, 3), Direction::North, Direction::East),
+        ];
+        for ((x, y), from_dir, to_dir) in corners {
+            world.build_track(x, y, Some(TrackType::Curve { from_dir, to_dir }), 0).unwrap();
+        }
+        assert_matches_rebuild(&mut world);
+        assert!(world.rail_graph.edges.is_empty());
+        // A route around it still stops at the goal
+        let hops = world.rail_graph.find_route(&world, (2, 2), (3, 3), 0, Heuristic::Chebyshev).unwrap();
+        assert_eq!(follow(&world, &hops).len(), 2);
+        assert_eq!(follow(&world, &hops).last(), Some(&(3, 3)));
+    }
+
+    #[test]
+    fn revision_moves_only_when_runs_change() {
+        let mut world = World::flat(16, 16);
+        for x in 2..10 {
+            world.build_track(x, 4, None, 0).unwrap();
+        }
+        let rail = world.network_revision(PathKind::Rail);
+        let road = world.network_revision(PathKind::Road);
+
+        // A road well away from the track leaves the rail runs as they were
+        world.set_tile_content(3, 12, TileContent::Road);
+        world.set_tile_content(4, 12, TileContent::Road);
+        assert_eq!(world.network_revision(PathKind::Rail), rail);
+        assert!(world.network_revision(PathKind::Road) > road);
+
+        world.demolish(6, 4).unwrap();
+        assert!(world.network_revision(PathKind::Rail) > rail);
+    }
+}
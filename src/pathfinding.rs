@@ -0,0 +1,489 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::network::Hop;
+use crate::world::{CompanyId, Direction, StationType, TerrainType, TileContent, TrackType, World};
+
+/// What each step of a route costs. Every step costs at least `step`, so
+/// the distance heuristics never overestimate.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathCosts {
+    pub step: u32,
+    /// Extra per level climbed; going downhill is free
+    pub climb: u32,
+    /// Extra for entering a curved piece of track
+    pub curve: u32,
+    /// Extra for entering a junction
+    pub junction: u32,
+    /// Extra for driving off road across each kind of open land
+    pub grass: u32,
+    pub desert: u32,
+    pub forest: u32,
+}
+
+impl Default for PathCosts {
+    fn default() -> Self {
+        Self {
+            step: 10,
+            climb: 20,
+            curve: 5,
+            junction: 5,
+            grass: 20,
+            desert: 30,
+            forest: 40,
+        }
+    }
+}
+
+impl PathCosts {
+    /// Cost of moving from one tile onto its neighbour.
+    pub fn step_cost(&self, world: &World, from: (usize, usize), to: (usize, usize)) -> u32 {
+        let Some(tile) = world.get_tile(to.0, to.1) else {
+            return self.step;
+        };
+        let surcharge = match &tile.content {
+            TileContent::Track(TrackType::Curve { .. }) => self.curve,
+            TileContent::Track(TrackType::Junction { .. }) => self.junction,
+            TileContent::Empty => match tile.terrain {
+                TerrainType::Grass => self.grass,
+                TerrainType::Desert => self.desert,
+                TerrainType::Forest => self.forest,
+                TerrainType::Water | TerrainType::Mountain => 0,
+            },
+            _ => 0,
+        };
+        self.step + self.climb * world.climb_between(from, to) as u32 + surcharge
+    }
+}
+
+/// Lower bound on the remaining cost, used to steer the search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Heuristic {
+    /// For vehicles that only move along the four sides of a tile
+    Manhattan,
+    /// For trains, which can also run diagonally and so cover two
+    /// Manhattan steps in one
+    Chebyshev,
+    /// Plain Dijkstra
+    None,
+}
+
+impl Heuristic {
+    pub fn estimate(self, from: (usize, usize), to: (usize, usize), step: u32) -> u32 {
+        let dx = from.0.abs_diff(to.0) as u32;
+        let dy = from.1.abs_diff(to.1) as u32;
+        match self {
+            Heuristic::Manhattan => (dx + dy) * step,
+            Heuristic::Chebyshev => dx.max(dy) * step,
+            Heuristic::None => 0,
+        }
+    }
+}
+
+/// Which network a route was found on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PathKind {
+    Rail,
+    Road,
+    Water,
+}
+
+/// How to get from one tile to another.
+#[derive(Clone, Debug)]
+pub enum Route {
+    /// Tile by tile, for journeys that leave the network
+    Tiles(Vec<(usize, usize)>),
+    /// Run by run over the network graph
+    Hops(Vec<Hop>),
+}
+
+/// A route found earlier and the network version it was found at.
+#[derive(Clone, Debug)]
+pub struct CachedPath {
+    pub route: Route,
+    pub version: u64,
+    /// Day the route was last asked for
+    pub last_used: u32,
+}
+
+/// Past this many routes the least recently used half is forgotten.
+const MAX_CACHED_PATHS: usize = 4096;
+
+/// Network, the company whose infrastructure may be used, and both ends.
+type PathKey = (PathKind, CompanyId, (usize, usize), (usize, usize));
+
+/// Routes shared by every vehicle.
+#[derive(Clone, Debug, Default)]
+pub struct PathCache {
+    entries: HashMap<PathKey, CachedPath>,
+}
+
+impl PathCache {
+    /// Forgets every route, e.g. after the path costs changed.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Keeps only the routes `keep` accepts.
+    pub fn retain(&mut self, mut keep: impl FnMut(PathKind, &CachedPath) -> bool) {
+        self.entries.retain(|&(kind, ..), cached| keep(kind, cached));
+    }
+
+    fn insert(&mut self, key: PathKey, cached: CachedPath) {
+        if self.entries.len() >= MAX_CACHED_PATHS {
+            let mut by_age: Vec<(u32, PathKey)> = self.entries.iter().map(|(&key, cached)| (cached.last_used, key)).collect();
+            by_age.sort_unstable_by_key(|&(last_used, _)| last_used);
+            for (_, key) in &by_age[..by_age.len() / 2] {
+                self.entries.remove(key);
+            }
+        }
+        self.entries.insert(key, cached);
+    }
+}
+
+/// The route from `start` to `goal`, from the world's cache while none of
+/// it has changed since it was found, otherwise from `search`.
+pub fn cached_path(
+    world: &mut World,
+    kind: PathKind,
+    owner: CompanyId,
+    start: (usize, usize),
+    goal: (usize, usize),
+    search: impl FnOnce(&World) -> Option<Route>,
+) -> Option<Route> {
+    world.refresh_network_graphs();
+    let key = (kind, owner, start, goal);
+    let today = world.ticks;
+    if let Some(cached) = world.path_cache.entries.get(&key) {
+        if world.route_is_current(kind, &cached.route, cached.version) {
+            let route = cached.route.clone();
+            if let Some(cached) = world.path_cache.entries.get_mut(&key) {
+                cached.last_used = today;
+            }
+            return Some(route);
+        }
+        world.path_cache.entries.remove(&key);
+    }
+
+    let route = search(world)?;
+    let version = world.network_version;
+    world.path_cache.insert(key, CachedPath { route: route.clone(), version, last_used: today });
+    Some(route)
+}
+
+/// A* over the tiles `neighbors` allows. Returns the tiles from `start` to
+/// `goal`, both included.
+pub fn find_path(
+    world: &World,
+    start: (usize, usize),
+    goal: (usize, usize),
+    costs: &PathCosts,
+    heuristic: Heuristic,
+    neighbors: impl Fn((usize, usize)) -> Vec<(usize, usize)>,
+) -> Option<Vec<(usize, usize)>> {
+    let mut open_set = BinaryHeap::new();
+    let mut came_from = HashMap::new();
+    let mut g_score = HashMap::new();
+
+    open_set.push(Reverse((heuristic.estimate(start, goal, costs.step), 0, start)));
+    g_score.insert(start, 0);
+
+    while let Some(Reverse((_, cost, current))) = open_set.pop() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut current_pos = current;
+            while let Some(&previous) = came_from.get(&current_pos) {
+                path.push(previous);
+                current_pos = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        // A cheaper way here was found after this entry was queued
+        if cost > g_score[&current] {
+            continue;
+        }
+
+        for neighbor in neighbors(current) {
+            let tentative_g_score = cost + costs.step_cost(world, current, neighbor);
+            if tentative_g_score < *g_score.get(&neighbor).unwrap_or(&u32::MAX) {
+                came_from.insert(neighbor, current);
+                g_score.insert(neighbor, tentative_g_score);
+                let estimate = tentative_g_score + heuristic.estimate(neighbor, goal, costs.step);
+                open_set.push(Reverse((estimate, tentative_g_score, neighbor)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Tiles a train belonging to `owner` can run to from `pos`.
+pub fn rail_neighbors(world: &World, pos: (usize, usize), owner: CompanyId) -> Vec<(usize, usize)> {
+    // Off the rails, e.g. just bought, a train may join track on any side
+    let exits = match world.rail_connections(pos) {
+        exits if exits.is_empty() => Direction::ORTHOGONAL.to_vec(),
+        exits => exits,
+    };
+
+    // Only along pieces that link up with each other
+    exits.into_iter()
+        .filter_map(|direction| {
+            let next = direction.step(pos)?;
+            let enters = world.rail_connections(next).contains(&direction.opposite());
+            (enters && world.is_usable_by(next.0, next.1, owner)).then_some(next)
+        })
+        .collect()
+}
+
+/// Tiles a road vehicle belonging to `owner` can drive to from `pos`.
+pub fn road_neighbors(world: &World, pos: (usize, usize), owner: CompanyId) -> Vec<(usize, usize)> {
+    let mut neighbors = Vec::new();
+
+    for direction in Direction::ORTHOGONAL {
+        let Some(next) = direction.step(pos) else {
+            continue;
+        };
+        // Off a level crossing only the way the road runs
+        if world.crossing_road_sides(pos).is_some_and(|sides| !sides.contains(&direction)) {
+            continue;
+        }
+        if let Some(tile) = world.get_tile(next.0, next.1) {
+            match &tile.content {
+                // Road vehicles can use roads, stations, and empty terrain
+                // short of airfields; roads are open to everyone, stops only
+                // to their owner
+                TileContent::Road if !matches!(tile.terrain, TerrainType::Water | TerrainType::Mountain) => {
+                    neighbors.push(next);
+                },
+                TileContent::Empty
+                    if !matches!(tile.terrain, TerrainType::Water | TerrainType::Mountain) && world.is_clear(next.0, next.1) => {
+                    neighbors.push(next);
+                },
+                TileContent::Station(_) if world.is_usable_by(next.0, next.1, owner) => {
+                    neighbors.push(next);
+                },
+                // Level crossings only straight across the rail
+                TileContent::Track(_) if world.crossing_road_sides(next).is_some_and(|sides| sides.contains(&direction)) => {
+                    neighbors.push(next);
+                },
+                _ => {}
+            }
+        }
+    }
+
+    neighbors
+}
+
+/// Tiles a ship belonging to `owner` can sail to from `pos`: open water,
+/// canals included, and the company's harbors.
+pub fn water_neighbors(world: &World, pos: (usize, usize), owner: CompanyId) -> Vec<(usize, usize)> {
+    Direction::ORTHOGONAL.into_iter()
+        .filter_map(|direction| direction.step(pos))
+        .filter(|&(x, y)| match world.get_tile(x, y) {
+            Some(tile) => match &tile.content {
+                TileContent::Empty => matches!(tile.terrain, TerrainType::Water),
+                TileContent::Station(station) => {
+                    matches!(station.station_type, StationType::Harbor) && world.is_usable_by(x, y, owner)
+                }
+                _ => false,
+            },
+            None => false,
+        })
+        .collect()
+}
+
+/// The tiles an aircraft passes over flying straight from `start` to
+/// `goal`, both included. Steps may be diagonal.
+pub fn flight_path(start: (usize, usize), goal: (usize, usize)) -> Vec<(usize, usize)> {
+    let (dx, dy) = (goal.0.abs_diff(start.0), goal.1.abs_diff(start.1));
+    let steps = dx.max(dy);
+    // Spread the shorter axis evenly along the longer one
+    (0..=steps)
+        .map(|step| {
+            let along = |from: usize, to: usize, distance: usize| {
+                let moved = (distance * step * 2 + steps) / (steps * 2).max(1);
+                if to >= from { from + moved } else { from - moved }
+            };
+            (along(start.0, goal.0, dx), along(start.1, goal.1, dy))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What driving along `path` costs.
+    fn cost_of(world: &World, path: &[(usize, usize)]) -> u32 {
+        path.windows(2).map(|step| world.path_costs.step_cost(world, step[0], step[1])).sum()
+    }
+
+    fn drive(world: &World, start: (usize, usize), goal: (usize, usize), heuristic: Heuristic) -> Option<Vec<(usize, usize)>> {
+        find_path(world, start, goal, &world.path_costs, heuristic, |pos| road_neighbors(world, pos, 0))
+    }
+
+    #[test]
+    fn heuristics_estimate_by_axis() {
+        assert_eq!(Heuristic::Manhattan.estimate((1, 2), (4, 6), 10), 70);
+        assert_eq!(Heuristic::Chebyshev.estimate((4, 6), (1, 2), 10), 40);
+        assert_eq!(Heuristic::None.estimate((1, 2), (4, 6), 10), 0);
+    }
+
+    #[test]
+    fn search_goes_around_what_it_cannot_cross() {
+        let mut world = World::flat(10, 10);
+        for y in 1..10 {
+            world.tiles[y][5].terrain = TerrainType::Mountain;
+        }
+        let path = drive(&world, (0, 5), (9, 5), Heuristic::Manhattan).unwrap();
+        assert_eq!(path.first(), Some(&(0, 5)));
+        assert_eq!(path.last(), Some(&(9, 5)));
+        assert!(path.contains(&(5, 0)));
+        assert!(path.windows(2).all(|step| step[0].0.abs_diff(step[1].0) + step[0].1.abs_diff(step[1].1) == 1));
+        assert_eq!(path.len(), 20);
+
+        world.tiles[0][5].terrain = TerrainType::Mountain;
+        assert!(drive(&world, (0, 5), (9, 5), Heuristic::Manhattan).is_none());
+    }
+
+    #[test]
+    fn roads_beat_the_straight_line_across_open_land() {
+        let mut world = World::flat(10, 4);
+        for x in 0..10 {
+            world.set_tile_content(x, 0, TileContent::Road);
+        }
+        let path = drive(&world, (0, 1), (9, 1), Heuristic::Manhattan).unwrap();
+        assert!(path[1..path.len() - 1].iter().all(|&(_, y)| y == 0));
+
+        // With open land as cheap as road the detour no longer pays
+        world.path_costs.grass = 0;
+        assert_eq!(drive(&world, (0, 1), (9, 1), Heuristic::Manhattan).unwrap().len(), 10);
+        assert_eq!(drive(&world, (4, 2), (4, 2), Heuristic::Manhattan), Some(vec![(4, 2)]));
+    }
+
+    #[test]
+    fn trains_keep_to_their_own_track() {
+        let mut world = World::flat(10, 4);
+        for x in 1..9 {
+            world.build_track(x, 1, None, if x == 5 { 1 } else { 0 }).unwrap();
+        }
+        let search = |world: &World| find_path(world, (1, 1), (8, 1), &world.path_costs, Heuristic::Chebyshev, |pos| {
+            rail_neighbors(world, pos, 0)
+        });
+        assert!(search(&world).is_none());
+
+        world.set_shared_infrastructure(true);
+        assert_eq!(search(&world).map(|path| path.len()), Some(8));
+    }
+
+    #[test]
+    fn heuristic_search_finds_routes_as_cheap_as_dijkstra() {
+        let mut world = World::flat(20, 20);
+        for y in 0..20 {
+            for x in 0..20 {
+                let tile = &mut world.tiles[y][x];
+                tile.terrain = match (x * 7 + y * 3) % 6 {
+                    0 => TerrainType::Forest,
+                    1 => TerrainType::Desert,
+                    2 if x % 4 != 0 => TerrainType::Mountain,
+                    _ => TerrainType::Grass,
+                };
+                tile.height = ((x / 3 + y / 5) % 3) as u8 + 1;
+            }
+        }
+        for x in 0..20 {
+            world.set_tile_content(x, 10, TileContent::Road);
+        }
+        for (start, goal) in [((0, 0), (19, 19)), ((3, 17), (16, 2)), ((0, 10), (19, 11)), ((8, 1), (8, 18))] {
+            let dijkstra = drive(&world, start, goal, Heuristic::None).unwrap();
+            let a_star = drive(&world, start, goal, Heuristic::Manhattan).unwrap();
+            assert_eq!(cost_of(&world, &a_star), cost_of(&world, &dijkstra), "{start:?} to {goal:?}");
+        }
+    }
+
+    #[test]
+    fn flights_go_straight_one_tile_at_a_time() {
+        assert_eq!(flight_path((3, 3), (3, 3)), vec![(3, 3)]);
+        assert_eq!(flight_path((2, 5), (6, 5)), vec![(2, 5), (3, 5), (4, 5), (5, 5), (6, 5)]);
+        assert_eq!(flight_path((4, 4), (1, 1)), vec![(4, 4), (3, 3), (2, 2), (1, 1)]);
+
+        for (start, goal) in [((0, 0), (9, 3)), ((20, 2), (3, 11)), ((5, 30), (7, 0))] {
+            let path = flight_path(start, goal);
+            assert_eq!(path.first(), Some(&start));
+            assert_eq!(path.last(), Some(&goal));
+            assert_eq!(path.len(), start.0.abs_diff(goal.0).max(start.1.abs_diff(goal.1)) + 1);
+            assert!(path.windows(2).all(|step| step[0].0.abs_diff(step[1].0) <= 1 && step[0].1.abs_diff(step[1].1) <= 1));
+            assert_eq!(flight_path(goal, start).len(), path.len());
+        }
+    }
+
+    #[test]
+    fn cache_forgets_least_recently_used_routes_when_full() {
+        let mut cache = PathCache::default();
+        for day in 0..MAX_CACHED_PATHS as u32 {
+            let key = (PathKind::Road, 0, (day as usize, 0), (0, 0));
+            cache.insert(key, CachedPath { route: Route::Tiles(Vec::new()), version: 0, last_used: day });
+        }
+        assert_eq!(cache.len(), MAX_CACHED_PATHS);
+
+        let newest = (PathKind::Road, 0, (0, 1), (0, 0));
+        cache.insert(newest, CachedPath { route: Route::Tiles(Vec::new()), version: 0, last_used: u32::MAX });
+        assert_eq!(cache.len(), MAX_CACHED_PATHS / 2 + 1);
+        assert!(cache.entries.contains_key(&newest));
+        assert!(cache.entries.contains_key(&(PathKind::Road, 0, (MAX_CACHED_PATHS - 1, 0), (0, 0))));
+        assert!(!cache.entries.contains_key(&(PathKind::Road, 0, (0, 0), (0, 0))));
+    }
+
+    #[test]
+    fn cache_keeps_half_its_routes_when_all_were_used_the_same_day() {
+        let mut cache = PathCache::default();
+        for n in 0..MAX_CACHED_PATHS {
+            let key = (PathKind::Rail, 1, (n, 0), (0, 0));
+            cache.insert(key, CachedPath { route: Route::Tiles(Vec::new()), version: 0, last_used: 7 });
+        }
+        let key = (PathKind::Rail, 1, (0, 1), (0, 0));
+        cache.insert(key, CachedPath { route: Route::Tiles(Vec::new()), version: 0, last_used: 7 });
+        assert_eq!(cache.len(), MAX_CACHED_PATHS / 2 + 1);
+    }
+
+    #[test]
+    fn month_end_drops_routes_over_changed_tiles() {
+        let mut world = World::flat(16, 16);
+        for x in 2..10 {
+            world.build_track(x, 4, None, 0).unwrap();
+        }
+        let search = |world: &World| {
+            find_path(world, (2, 4), (9, 4), &world.path_costs, Heuristic::Chebyshev, |pos| rail_neighbors(world, pos, 0))
+                .map(Route::Tiles)
+        };
+        assert!(cached_path(&mut world, PathKind::Rail, 0, (2, 4), (9, 4), search).is_some());
+        assert_eq!(world.path_cache.len(), 1);
+
+        world.demolish(5, 4).unwrap();
+        for _ in 0..30 {
+            world.update();
+        }
+        assert!(world.path_cache.is_empty());
+    }
+
+    #[test]
+    fn road_vehicles_keep_off_airfields() {
+        let mut world = World::flat(12, 12);
+        world.build_airport(5, 5, crate::world::AirportSize::Small, 0).unwrap();
+        let neighbors = road_neighbors(&world, (7, 6), 0);
+        assert!(!neighbors.contains(&(6, 6)));
+        assert!(neighbors.contains(&(8, 6)));
+        assert!(neighbors.contains(&(7, 5)));
+    }
+}
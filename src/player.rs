@@ -1,8 +1,22 @@
-use crate::world::{CargoType, World};
+use crate::world::{CargoType, CompanyId, World};
 use crate::economy::Economy;
+use crate::finance::{Ledger, LedgerCategory};
 use crate::vehicle::{Vehicle, VehicleType};
 
+/// Largest loan at the start of the game; it grows with inflation
+pub const MAX_LOAN: i64 = 500_000;
+/// Loans are taken out and paid back in steps of this size
+pub const LOAN_STEP: i64 = 10_000;
+/// Yearly interest charged on top of the inflation rate
+const LOAN_INTEREST_MARGIN: f32 = 0.03;
+/// Months in a row with a negative balance before the company goes bankrupt
+pub const BANKRUPTCY_MONTHS: u32 = 6;
+/// The human player's company
+pub const PLAYER_COMPANY: CompanyId = 0;
+
 pub struct Player {
+    /// Marks the tiles this company owns
+    pub company_id: CompanyId,
     pub name: String,
     pub money: i64,
     pub vehicles: Vec<Vehicle>,
@@ -10,6 +24,12 @@ pub struct Player {
     pub routes: Vec<Route>,
     pub reputation: f32,
     pub game_time: u32,
+    pub loan: i64,
+    /// Sandbox mode: spending costs nothing and the company can't go bankrupt
+    pub infinite_money: bool,
+    /// Consecutive months that ended with a negative balance
+    pub months_in_debt: u32,
+    pub ledger: Ledger,
 }
 
 #[derive(Clone, Debug)]
@@ -25,6 +45,7 @@ pub struct Route {
 impl Player {
     pub fn new(name: String, starting_money: i64) -> Self {
         Self {
+            company_id: PLAYER_COMPANY,
             name,
             money: starting_money,
             vehicles: Vec::new(),
@@ -32,6 +53,10 @@ impl Player {
             routes: Vec::new(),
             reputation: 50.0,
             game_time: 0,
+            loan: 0,
+            infinite_money: false,
+            months_in_debt: 0,
+            ledger: Ledger::new(),
         }
     }
 
@@ -41,9 +66,9 @@ impl Player {
         for vehicle in &mut self.vehicles {
             vehicle.update(world, economy);
             
-            if vehicle.has_delivered_cargo() {
-                let profit = vehicle.calculate_delivery_profit();
+            for (cargo_type, profit) in vehicle.take_delivery_income() {
                 self.money += profit;
+                self.ledger.record(LedgerCategory::Income(cargo_type), profit);
                 
                 if let Some(route) = self.routes.iter_mut().find(|r| r.vehicle_ids.contains(&vehicle.id)) {
                     route.profit += profit;
@@ -51,30 +76,114 @@ impl Player {
             }
         }
 
-        for vehicle in &self.vehicles {
-            self.money -= vehicle.get_running_costs() as i64;
-        }
+        let running_costs: i64 = self.vehicles.iter().map(|v| v.get_running_costs() as i64).sum();
+        self.charge(running_costs, LedgerCategory::RunningCosts);
 
-        if self.game_time % 30 == 0 {
+        if self.game_time.is_multiple_of(30) {
             self.update_reputation();
+            self.charge_maintenance(world, economy);
+            self.update_loan(economy);
+            self.ledger.close_month();
         }
     }
 
-    pub fn can_afford(&self, _amount: i64) -> bool {
-        true // Infinite money for now
+    pub fn can_afford(&self, amount: i64) -> bool {
+        self.infinite_money || self.money >= amount
     }
 
-    pub fn spend_money(&mut self, _amount: i64) -> bool {
-        // Don't actually spend money - infinite money mode
+    /// Pays `amount` if the company can afford it and books it under
+    /// `category`. Nothing is deducted in infinite money mode.
+    pub fn spend_money(&mut self, amount: i64, category: LedgerCategory) -> bool {
+        if !self.can_afford(amount) {
+            return false;
+        }
+        self.charge(amount, category);
         true
     }
 
+    /// Books a bill that has to be paid whether or not the company can
+    /// afford it. In infinite money mode it goes in the ledger but leaves
+    /// the balance alone.
+    fn charge(&mut self, amount: i64, category: LedgerCategory) {
+        if !self.infinite_money {
+            self.money -= amount;
+        }
+        self.ledger.record(category, amount);
+    }
+
+    /// Pays back part of an earlier expense. In infinite money mode, where
+    /// nothing was paid, only the ledger changes.
+    pub fn refund(&mut self, amount: i64, category: LedgerCategory) {
+        if !self.infinite_money {
+            self.money += amount;
+        }
+        self.ledger.record(category, -amount);
+    }
+
+    /// The most the company may owe the bank, rounded down to a loan step.
+    pub fn max_loan(&self, economy: &Economy) -> i64 {
+        (MAX_LOAN as f32 * economy.price_level) as i64 / LOAN_STEP * LOAN_STEP
+    }
+
+    /// Yearly interest rate on the loan.
+    pub fn interest_rate(&self, economy: &Economy) -> f32 {
+        economy.inflation_rate + LOAN_INTEREST_MARGIN
+    }
+
+    /// Borrows one more loan step. Returns the amount borrowed.
+    pub fn borrow(&mut self, economy: &Economy) -> Result<i64, String> {
+        let max_loan = self.max_loan(economy);
+        if self.loan >= max_loan {
+            return Err(format!("Maximum loan of ${} already reached", max_loan));
+        }
+        let amount = LOAN_STEP.min(max_loan - self.loan);
+        self.loan += amount;
+        self.money += amount;
+        Ok(amount)
+    }
+
+    /// Pays back one loan step. Returns the amount repaid.
+    pub fn repay(&mut self) -> Result<i64, String> {
+        if self.loan == 0 {
+            return Err("There is no loan to repay".to_string());
+        }
+        let amount = LOAN_STEP.min(self.loan);
+        if self.money < amount {
+            return Err(format!("Need ${} to repay the loan", amount));
+        }
+        self.loan -= amount;
+        self.money -= amount;
+        Ok(amount)
+    }
+
+    /// A company goes bankrupt after BANKRUPTCY_MONTHS months in the red.
+    pub fn is_bankrupt(&self) -> bool {
+        !self.infinite_money && self.months_in_debt >= BANKRUPTCY_MONTHS
+    }
+
+    /// Monthly upkeep of every track, road and station the company owns.
+    fn charge_maintenance(&mut self, world: &World, economy: &Economy) {
+        let upkeep = economy.inflate(world.maintenance_cost(self.company_id));
+        self.charge(upkeep, LedgerCategory::PropertyMaintenance);
+    }
+
+    fn update_loan(&mut self, economy: &Economy) {
+        let interest = (self.loan as f32 * self.interest_rate(economy) / 12.0).round() as i64;
+        self.charge(interest, LedgerCategory::LoanInterest);
+
+        if self.money < 0 && !self.infinite_money {
+            self.months_in_debt += 1;
+        } else {
+            self.months_in_debt = 0;
+        }
+    }
+
     pub fn add_vehicle(&mut self, vehicle_type: VehicleType, x: usize, y: usize) -> Option<u32> {
         let cost = Vehicle::get_purchase_cost(&vehicle_type);
         
-        if self.spend_money(cost) {
-            let vehicle_id = self.vehicles.len() as u32;
-            let vehicle = Vehicle::new(vehicle_id, vehicle_type, x, y);
+        if self.spend_money(cost, LedgerCategory::VehiclePurchase) {
+            let vehicle_id = self.vehicles.iter().map(|v| v.id + 1).max().unwrap_or(0);
+            let vehicle = Vehicle::new(vehicle_id, self.company_id, vehicle_type, x, y);
             self.vehicles.push(vehicle);
             Some(vehicle_id)
         } else {
@@ -97,30 +206,59 @@ impl Player {
         route_id
     }
 
-    pub fn assign_vehicle_to_route(&mut self, vehicle_id: u32, route_id: u32) -> bool {
-        if let Some(route) = self.routes.iter_mut().find(|r| r.id == route_id) {
-            if !route.vehicle_ids.contains(&vehicle_id) {
-                route.vehicle_ids.push(vehicle_id);
-                
-                if let Some(vehicle) = self.vehicles.iter_mut().find(|v| v.id == vehicle_id) {
-                    vehicle.assign_route(route.stations.clone());
-                }
-                return true;
-            }
+    /// Puts a vehicle on a route. Returns false if it is already on it or
+    /// can't be sent round its stations.
+    pub fn assign_vehicle_to_route(&mut self, world: &World, vehicle_id: u32, route_id: u32) -> bool {
+        let Some(route) = self.routes.iter_mut().find(|r| r.id == route_id) else {
+            return false;
+        };
+        if route.vehicle_ids.contains(&vehicle_id) {
+            return false;
+        }
+        let Some(vehicle) = self.vehicles.iter_mut().find(|v| v.id == vehicle_id) else {
+            return false;
+        };
+        if vehicle.assign_route(world, route.stations.clone()).is_err() {
+            return false;
+        }
+        route.vehicle_ids.push(vehicle_id);
+        true
+    }
+
+    /// Forgets a station that has been demolished: it leaves the company's
+    /// list, every route and every vehicle's orders.
+    pub fn remove_station(&mut self, world: &mut World, station: (usize, usize)) {
+        self.stations.retain(|&own| own != station);
+        for route in &mut self.routes {
+            route.stations.retain(|&stop| stop != station);
+        }
+        for vehicle in &mut self.vehicles {
+            vehicle.remove_stop(world, station);
+        }
+    }
+
+    /// Writes off a vehicle that has been destroyed, freeing whatever it
+    /// held in the world.
+    pub fn remove_vehicle(&mut self, world: &mut World, vehicle_id: u32) {
+        world.release_vehicle((self.company_id, vehicle_id), None);
+        self.vehicles.retain(|vehicle| vehicle.id != vehicle_id);
+        for route in &mut self.routes {
+            route.vehicle_ids.retain(|&id| id != vehicle_id);
         }
-        false
     }
 
     pub fn get_total_vehicle_value(&self) -> i64 {
         self.vehicles.iter().map(|v| v.get_current_value()).sum()
     }
 
+    /// Net profit over the last closed month.
     pub fn get_monthly_profit(&self) -> i64 {
-        self.routes.iter().map(|r| r.profit).sum()
+        self.ledger.months.last().map_or(0, |month| month.net_profit())
     }
 
+    /// Expenses over the last closed month.
     pub fn get_monthly_expenses(&self) -> i64 {
-        self.vehicles.iter().map(|v| v.get_running_costs() as i64 * 30).sum()
+        self.ledger.months.last().map_or(0, |month| month.total_expenses())
     }
 
     fn update_reputation(&mut self) {
@@ -145,11 +283,12 @@ impl Player {
         CompanyStats {
             name: self.name.clone(),
             money: self.money,
+            loan: self.loan,
             vehicle_count: self.vehicles.len(),
             station_count: self.stations.len(),
             route_count: self.routes.len(),
             reputation: self.reputation,
-            total_value: self.money + self.get_total_vehicle_value(),
+            total_value: self.money - self.loan + self.get_total_vehicle_value(),
             monthly_profit: self.get_monthly_profit(),
             monthly_expenses: self.get_monthly_expenses(),
         }
@@ -159,6 +298,7 @@ impl Player {
 pub struct CompanyStats {
     pub name: String,
     pub money: i64,
+    pub loan: i64,
     pub vehicle_count: usize,
     pub station_count: usize,
     pub route_count: usize,
@@ -166,4 +306,110 @@ pub struct CompanyStats {
     pub total_value: i64,
     pub monthly_profit: i64,
     pub monthly_expenses: i64,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vehicle::TruckType;
+
+    fn truck() -> VehicleType {
+        VehicleType::Road { truck_type: TruckType::SmallTruck { capacity: 20 } }
+    }
+
+    #[test]
+    fn loans_come_in_steps_up_to_the_cap() {
+        let economy = Economy::new();
+        let mut company = Player::new("Test".to_string(), 0);
+        let steps = MAX_LOAN / LOAN_STEP;
+        for _ in 0..steps {
+            assert_eq!(company.borrow(&economy), Ok(LOAN_STEP));
+        }
+        assert_eq!(company.loan, MAX_LOAN);
+        assert_eq!(company.money, MAX_LOAN);
+        assert!(company.borrow(&economy).is_err());
+        assert_eq!(company.loan, MAX_LOAN);
+    }
+
+    #[test]
+    fn the_loan_cap_grows_with_prices_in_whole_steps() {
+        let mut economy = Economy::new();
+        let company = Player::new("Test".to_string(), 0);
+        economy.price_level = 1.5;
+        assert_eq!(company.max_loan(&economy), 750_000);
+        economy.price_level = 1.234;
+        assert_eq!(company.max_loan(&economy), 610_000);
+    }
+
+    #[test]
+    fn repaying_takes_no_more_than_is_owed_or_can_be_paid() {
+        let mut company = Player::new("Test".to_string(), 100_000);
+        assert!(company.repay().is_err());
+
+        company.loan = 4_000;
+        assert_eq!(company.repay(), Ok(4_000));
+        assert_eq!((company.loan, company.money), (0, 96_000));
+
+        company.loan = LOAN_STEP;
+        company.money = LOAN_STEP - 1;
+        assert!(company.repay().is_err());
+        assert_eq!(company.loan, LOAN_STEP);
+    }
+
+    #[test]
+    fn interest_is_inflation_plus_a_margin_charged_monthly() {
+        let mut economy = Economy::new();
+        economy.inflation_rate = 0.05;
+        let mut company = Player::new("Test".to_string(), 10_000);
+        assert!((company.interest_rate(&economy) - 0.08).abs() < 1e-6);
+
+        company.loan = 150_000;
+        company.update_loan(&economy);
+        assert_eq!(company.money, 9_000);
+        assert_eq!(company.ledger.current_month.loan_interest, 1_000);
+    }
+
+    #[test]
+    fn six_months_in_debt_bankrupts_the_company() {
+        let economy = Economy::new();
+        let mut company = Player::new("Test".to_string(), -1);
+        for _ in 1..BANKRUPTCY_MONTHS {
+            company.update_loan(&economy);
+            assert!(!company.is_bankrupt());
+        }
+        company.update_loan(&economy);
+        assert!(company.is_bankrupt());
+
+        // Back in the black the count starts over
+        company.money = 1;
+        company.update_loan(&economy);
+        assert_eq!(company.months_in_debt, 0);
+        assert!(!company.is_bankrupt());
+    }
+
+    #[test]
+    fn sandbox_spending_still_shows_in_the_ledger() {
+        let mut company = Player::new("Test".to_string(), 0);
+        company.infinite_money = true;
+        assert!(company.spend_money(30_000, LedgerCategory::Construction));
+        company.refund(10_000, LedgerCategory::Construction);
+        company.charge(500, LedgerCategory::RunningCosts);
+        assert_eq!(company.money, 0);
+        assert_eq!(company.ledger.current_month.construction, 20_000);
+        assert_eq!(company.ledger.current_month.total_expenses(), 20_500);
+    }
+
+    #[test]
+    fn purchases_need_the_money_unless_it_is_infinite() {
+        let cost = Vehicle::get_purchase_cost(&truck());
+        let mut company = Player::new("Test".to_string(), cost - 1);
+        assert_eq!(company.add_vehicle(truck(), 0, 0), None);
+        assert_eq!(company.money, cost - 1);
+        assert!(company.vehicles.is_empty());
+
+        company.infinite_money = true;
+        company.months_in_debt = BANKRUPTCY_MONTHS;
+        assert_eq!(company.add_vehicle(truck(), 0, 0), Some(0));
+        assert_eq!(company.money, cost - 1);
+        assert!(!company.is_bankrupt());
+    }
+}
@@ -1,12 +1,19 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
-use crate::game::Game;
-use crate::world::{World, Tile, TerrainType, TileContent, Town, Industry, IndustryType, Station, StationType, TrackType, CargoType};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::game::{Game, GameSettings};
+use crate::cargo::CargoList;
+use crate::finance::Ledger;
+use crate::world::{World, WorldGenConfig, Airport, AirportSize, CompanyId, VehicleId, Direction, Tile, TerrainType, TileContent, Town, House, Industry, IndustryType, Signal, Station, StationType, CargoRating, TrackType, CargoType};
 use crate::player::{Player, Route};
 use crate::vehicle::{Vehicle, VehicleType, VehicleState};
+use crate::network::Hop;
 use crate::economy::{Economy, EconomicState};
 use crate::ai::{AIPlayer, AIDifficulty, AIStrategy};
+use crate::server::NewsItem;
 
 #[derive(Serialize, Deserialize)]
 pub struct GameSave {
@@ -15,16 +22,49 @@ pub struct GameSave {
     pub ai_players: Vec<AIPlayerSave>,
     pub economy: EconomySave,
     pub game_time: u32,
+    #[serde(default)]
+    pub settings: GameSettings,
+    #[serde(default)]
+    pub news: Vec<NewsItem>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct WorldSave {
     pub width: usize,
     pub height: usize,
+    // Older saves predate seeded generation and carry no config
+    #[serde(default)]
+    pub gen_config: Option<WorldGenConfig>,
     pub tiles: Vec<Vec<TileSave>>,
     pub towns: Vec<(usize, usize)>,
     pub industries: Vec<(usize, usize)>,
     pub stations: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub ticks: u32,
+    /// Where the world's generator carries on from; older saves restart
+    /// it from the map seed and date
+    #[serde(default)]
+    pub rng_seed: Option<u64>,
+    #[serde(default)]
+    pub signals: Vec<((usize, usize), Signal)>,
+    #[serde(default)]
+    pub level_crossings: Vec<(usize, usize)>,
+    /// Canal tiles and the terrain they were dug out of
+    #[serde(default)]
+    pub canals: Vec<((usize, usize), TerrainType)>,
+    #[serde(default)]
+    pub buoys: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub airports: Vec<((usize, usize), AirportSave)>,
+    #[serde(default)]
+    pub build_costs: Vec<((usize, usize), i64)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AirportSave {
+    pub size: AirportSize,
+    #[serde(default)]
+    pub runway_users: Vec<VehicleId>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -32,6 +72,8 @@ pub struct TileSave {
     pub terrain: TerrainType,
     pub content: TileContentSave,
     pub height: u8,
+    #[serde(default)]
+    pub owner: Option<CompanyId>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -42,6 +84,7 @@ pub enum TileContentSave {
     Station(StationSave),
     Track(TrackTypeSave),
     Road,
+    House(HouseSave),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,6 +92,21 @@ pub struct TownSave {
     pub name: String,
     pub population: u32,
     pub growth_rate: f32,
+    // Towns were a single tile before they grew houses and roads
+    #[serde(default)]
+    pub houses: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub roads: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub passengers_moved: u32,
+    #[serde(default)]
+    pub goods_delivered: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HouseSave {
+    pub town: (usize, usize),
+    pub residents: u32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -57,6 +115,20 @@ pub struct IndustrySave {
     pub production_rate: u32,
     pub cargo_input: Vec<CargoType>,
     pub cargo_output: Vec<CargoType>,
+    #[serde(default)]
+    pub stockpile: HashMap<CargoType, u32>,
+    #[serde(default)]
+    pub input_stockpile: HashMap<CargoType, u32>,
+    #[serde(default)]
+    pub produced_this_month: u32,
+    #[serde(default)]
+    pub transported_this_month: u32,
+    #[serde(default)]
+    pub delivered_this_month: u32,
+    #[serde(default)]
+    pub last_month_transported: u32,
+    #[serde(default)]
+    pub months_unserviced: u32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -64,18 +136,27 @@ pub struct StationSave {
     pub name: String,
     pub station_type: StationType,
     pub connections: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub ratings: HashMap<CargoType, CargoRating>,
+    #[serde(default)]
+    pub cargo_waiting: CargoList,
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum TrackTypeSave {
     Straight { horizontal: bool },
     Curve { from_dir: u8, to_dir: u8 },
+    Diagonal { rising: bool },
+    /// Older saves only had four-way junctions
     Junction,
+    JunctionOf { connections: Vec<Direction> },
 }
 
 
 #[derive(Serialize, Deserialize)]
 pub struct PlayerSave {
+    #[serde(default)]
+    pub company_id: CompanyId,
     pub name: String,
     pub money: i64,
     pub vehicles: Vec<VehicleSave>,
@@ -83,6 +164,12 @@ pub struct PlayerSave {
     pub routes: Vec<RouteSave>,
     pub reputation: f32,
     pub game_time: u32,
+    #[serde(default)]
+    pub loan: i64,
+    #[serde(default)]
+    pub months_in_debt: u32,
+    #[serde(default)]
+    pub ledger: Ledger,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -106,6 +193,8 @@ pub struct VehicleSave {
     pub route_index: usize,
     pub current_path: Vec<(usize, usize)>,
     pub path_index: usize,
+    #[serde(default)]
+    pub pending_hops: Vec<Hop>,
     pub age: u32,
     pub reliability: u8,
     pub speed: u32,
@@ -113,6 +202,8 @@ pub struct VehicleSave {
     pub profit: i64,
     pub on_time_deliveries: u32,
     pub total_deliveries: u32,
+    #[serde(default)]
+    pub cargo: CargoList,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -122,6 +213,8 @@ pub enum VehicleStateSave {
     Loading,
     Unloading,
     Broken,
+    Lost,
+    Holding,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -129,6 +222,12 @@ pub struct EconomySave {
     pub inflation_rate: f32,
     pub economic_state: EconomicState,
     pub month: u32,
+    #[serde(default = "default_price_level")]
+    pub price_level: f32,
+}
+
+fn default_price_level() -> f32 {
+    1.0
 }
 
 #[derive(Serialize, Deserialize)]
@@ -141,13 +240,17 @@ pub struct AIPlayerSave {
 }
 
 impl GameSave {
-    pub fn from_game(game: &Game) -> Self {
+    /// Taking the game mutably lets the running game and the save carry on
+    /// from the same generator state.
+    pub fn from_game(game: &mut Game) -> Self {
         Self {
-            world: WorldSave::from_world(&game.world),
+            world: WorldSave::from_world(&mut game.world),
             player: PlayerSave::from_player(&game.player),
             ai_players: game.ai_players.iter().map(AIPlayerSave::from_ai_player).collect(),
             economy: EconomySave::from_economy(&game.economy),
             game_time: game.player.game_time,
+            settings: game.settings,
+            news: game.news.clone(),
         }
     }
 
@@ -167,38 +270,72 @@ impl GameSave {
         Ok(save)
     }
 
-    pub fn to_game(self) -> Game {
+    pub fn into_game(self) -> Game {
         let mut game = Game::new();
-        game.world = self.world.to_world();
-        game.player = self.player.to_player();
-        game.ai_players = self.ai_players.into_iter().map(|ai| ai.to_ai_player()).collect();
-        game.economy = self.economy.to_economy();
+        game.world = self.world.into_world();
+        game.player = self.player.into_player();
+        game.ai_players = self.ai_players.into_iter().map(|ai| ai.into_ai_player()).collect();
+        game.economy = self.economy.into_economy();
+        game.apply_settings(self.settings);
+        game.news = self.news;
         game
     }
 }
 
 impl WorldSave {
-    fn from_world(world: &World) -> Self {
+    fn from_world(world: &mut World) -> Self {
+        // The generator's state can't be written out, so both the game and
+        // the save switch to a fresh seed drawn from it
+        let rng_seed = world.rng.r#gen();
+        world.rng = StdRng::seed_from_u64(rng_seed);
         Self {
             width: world.width,
             height: world.height,
+            gen_config: Some(world.config.clone()),
             tiles: world.tiles.iter().map(|row| {
                 row.iter().map(TileSave::from_tile).collect()
             }).collect(),
             towns: world.towns.clone(),
             industries: world.industries.clone(),
             stations: world.stations.clone(),
+            ticks: world.ticks,
+            rng_seed: Some(rng_seed),
+            signals: world.signals.iter().map(|(&pos, &signal)| (pos, signal)).collect(),
+            level_crossings: world.level_crossings.iter().copied().collect(),
+            canals: world.canals.iter().map(|(&pos, terrain)| (pos, terrain.clone())).collect(),
+            buoys: world.buoys.iter().copied().collect(),
+            airports: world.airports.iter().map(|(&pos, airport)| (pos, AirportSave {
+                size: airport.size,
+                runway_users: airport.runway_users.clone(),
+            })).collect(),
+            build_costs: world.build_costs.iter().map(|(&pos, &cost)| (pos, cost)).collect(),
         }
     }
 
-    fn to_world(self) -> World {
-        let mut world = World::new(self.width, self.height);
+    fn into_world(self) -> World {
+        let config = self.gen_config
+            .unwrap_or_else(|| WorldGenConfig::with_random_seed(self.width, self.height));
+        let mut world = World::from_config(config);
         world.tiles = self.tiles.into_iter().map(|row| {
-            row.into_iter().map(|tile| tile.to_tile()).collect()
+            row.into_iter().map(|tile| tile.into_tile()).collect()
         }).collect();
         world.towns = self.towns;
         world.industries = self.industries;
         world.stations = self.stations;
+        world.ticks = self.ticks;
+        let rng_seed = self.rng_seed.unwrap_or(world.config.seed ^ world.ticks as u64);
+        world.rng = StdRng::seed_from_u64(rng_seed);
+        world.signals = self.signals.into_iter().collect();
+        world.level_crossings = self.level_crossings.into_iter().collect();
+        world.canals = self.canals.into_iter().collect();
+        world.buoys = self.buoys.into_iter().collect();
+        world.airports = self.airports.into_iter()
+            .map(|(pos, airport)| (pos, Airport { size: airport.size, runway_users: airport.runway_users }))
+            .collect();
+        world.build_costs = self.build_costs.into_iter().collect();
+        world.index_airports();
+        world.rebuild_network_graphs();
+        world.recount_upkeep();
         world
     }
 }
@@ -209,14 +346,16 @@ impl TileSave {
             terrain: tile.terrain.clone(),
             content: TileContentSave::from_tile_content(&tile.content),
             height: tile.height,
+            owner: tile.owner,
         }
     }
 
-    fn to_tile(self) -> Tile {
+    fn into_tile(self) -> Tile {
         Tile {
             terrain: self.terrain,
-            content: self.content.to_tile_content(),
+            content: self.content.into_tile_content(),
             height: self.height,
+            owner: self.owner,
         }
     }
 }
@@ -230,17 +369,19 @@ impl TileContentSave {
             TileContent::Station(station) => TileContentSave::Station(StationSave::from_station(station)),
             TileContent::Track(track) => TileContentSave::Track(TrackTypeSave::from_track_type(track)),
             TileContent::Road => TileContentSave::Road,
+            TileContent::House(house) => TileContentSave::House(HouseSave::from_house(house)),
         }
     }
 
-    fn to_tile_content(self) -> TileContent {
+    fn into_tile_content(self) -> TileContent {
         match self {
             TileContentSave::Empty => TileContent::Empty,
-            TileContentSave::Town(town) => TileContent::Town(town.to_town()),
-            TileContentSave::Industry(industry) => TileContent::Industry(industry.to_industry()),
-            TileContentSave::Station(station) => TileContent::Station(station.to_station()),
-            TileContentSave::Track(track) => TileContent::Track(track.to_track_type()),
+            TileContentSave::Town(town) => TileContent::Town(town.into_town()),
+            TileContentSave::Industry(industry) => TileContent::Industry(industry.into_industry()),
+            TileContentSave::Station(station) => TileContent::Station(station.into_station()),
+            TileContentSave::Track(track) => TileContent::Track(track.into_track_type()),
             TileContentSave::Road => TileContent::Road,
+            TileContentSave::House(house) => TileContent::House(house.into_house()),
         }
     }
 }
@@ -251,16 +392,40 @@ impl TownSave {
             name: town.name.clone(),
             population: town.population,
             growth_rate: town.growth_rate,
+            houses: town.houses.clone(),
+            roads: town.roads.clone(),
+            passengers_moved: town.passengers_moved,
+            goods_delivered: town.goods_delivered,
         }
     }
 
-    fn to_town(self) -> Town {
+    fn into_town(self) -> Town {
         Town {
             name: self.name,
             population: self.population,
             growth_rate: self.growth_rate,
             cargo_demand: std::collections::HashMap::new(),
             cargo_supply: std::collections::HashMap::new(),
+            houses: self.houses,
+            roads: self.roads,
+            passengers_moved: self.passengers_moved,
+            goods_delivered: self.goods_delivered,
+        }
+    }
+}
+
+impl HouseSave {
+    fn from_house(house: &House) -> Self {
+        Self {
+            town: house.town,
+            residents: house.residents,
+        }
+    }
+
+    fn into_house(self) -> House {
+        House {
+            town: self.town,
+            residents: self.residents,
         }
     }
 }
@@ -272,16 +437,29 @@ impl IndustrySave {
             production_rate: industry.production_rate,
             cargo_input: industry.cargo_input.clone(),
             cargo_output: industry.cargo_output.clone(),
+            stockpile: industry.stockpile.clone(),
+            input_stockpile: industry.input_stockpile.clone(),
+            produced_this_month: industry.produced_this_month,
+            transported_this_month: industry.transported_this_month,
+            delivered_this_month: industry.delivered_this_month,
+            last_month_transported: industry.last_month_transported,
+            months_unserviced: industry.months_unserviced,
         }
     }
 
-    fn to_industry(self) -> Industry {
+    fn into_industry(self) -> Industry {
         Industry {
             industry_type: self.industry_type,
             production_rate: self.production_rate,
             cargo_input: self.cargo_input,
             cargo_output: self.cargo_output,
-            stockpile: std::collections::HashMap::new(),
+            stockpile: self.stockpile,
+            input_stockpile: self.input_stockpile,
+            produced_this_month: self.produced_this_month,
+            transported_this_month: self.transported_this_month,
+            delivered_this_month: self.delivered_this_month,
+            last_month_transported: self.last_month_transported,
+            months_unserviced: self.months_unserviced,
         }
     }
 }
@@ -292,15 +470,19 @@ impl StationSave {
             name: station.name.clone(),
             station_type: station.station_type.clone(),
             connections: station.connections.clone(),
+            ratings: station.ratings.clone(),
+            cargo_waiting: station.cargo_waiting.clone(),
         }
     }
 
-    fn to_station(self) -> Station {
+    fn into_station(self) -> Station {
         Station {
             name: self.name,
             station_type: self.station_type,
-            cargo_waiting: std::collections::HashMap::new(),
+            cargo_waiting: self.cargo_waiting,
             connections: self.connections,
+            acceptance: Vec::new(),
+            ratings: self.ratings,
         }
     }
 }
@@ -309,31 +491,42 @@ impl TrackTypeSave {
     fn from_track_type(track: &TrackType) -> Self {
         match track {
             TrackType::Straight { horizontal } => TrackTypeSave::Straight { horizontal: *horizontal },
-            TrackType::Curve { from_dir: _, to_dir: _ } => TrackTypeSave::Curve {
-                from_dir: 0,
-                to_dir: 1,
+            TrackType::Curve { from_dir, to_dir } => TrackTypeSave::Curve {
+                from_dir: Self::direction_index(*from_dir),
+                to_dir: Self::direction_index(*to_dir),
             },
-            TrackType::Junction => TrackTypeSave::Junction,
+            TrackType::Diagonal { rising } => TrackTypeSave::Diagonal { rising: *rising },
+            TrackType::Junction { connections } => TrackTypeSave::JunctionOf { connections: connections.clone() },
         }
     }
 
-    fn to_track_type(self) -> TrackType {
-        use crate::world::Direction;
+    fn into_track_type(self) -> TrackType {
         match self {
             TrackTypeSave::Straight { horizontal } => TrackType::Straight { horizontal },
-            TrackTypeSave::Curve { from_dir: _, to_dir: _ } => TrackType::Curve {
-                from_dir: Direction::North,
-                to_dir: Direction::East,
-            },
-            TrackTypeSave::Junction => TrackType::Junction,
+            TrackTypeSave::Curve { from_dir, to_dir } => TrackType::from_connections(&[
+                Self::index_direction(from_dir),
+                Self::index_direction(to_dir),
+            ]),
+            TrackTypeSave::Diagonal { rising } => TrackType::Diagonal { rising },
+            TrackTypeSave::Junction => TrackType::Junction { connections: Direction::ORTHOGONAL.to_vec() },
+            TrackTypeSave::JunctionOf { connections } => TrackType::Junction { connections },
         }
     }
+
+    fn direction_index(direction: Direction) -> u8 {
+        Direction::ALL.iter().position(|&d| d == direction).unwrap_or(0) as u8
+    }
+
+    fn index_direction(index: u8) -> Direction {
+        Direction::ALL.get(index as usize).copied().unwrap_or(Direction::North)
+    }
 }
 
 
 impl PlayerSave {
     fn from_player(player: &Player) -> Self {
         Self {
+            company_id: player.company_id,
             name: player.name.clone(),
             money: player.money,
             vehicles: player.vehicles.iter().map(VehicleSave::from_vehicle).collect(),
@@ -341,16 +534,26 @@ impl PlayerSave {
             routes: player.routes.iter().map(RouteSave::from_route).collect(),
             reputation: player.reputation,
             game_time: player.game_time,
+            loan: player.loan,
+            months_in_debt: player.months_in_debt,
+            ledger: player.ledger.clone(),
         }
     }
 
-    fn to_player(self) -> Player {
+    fn into_player(self) -> Player {
         let mut player = Player::new(self.name, self.money);
-        player.vehicles = self.vehicles.into_iter().map(|v| v.to_vehicle()).collect();
+        player.company_id = self.company_id;
+        player.vehicles = self.vehicles.into_iter().map(|v| v.into_vehicle()).collect();
+        for vehicle in &mut player.vehicles {
+            vehicle.owner = self.company_id;
+        }
         player.stations = self.stations;
-        player.routes = self.routes.into_iter().map(|r| r.to_route()).collect();
+        player.routes = self.routes.into_iter().map(|r| r.into_route()).collect();
         player.reputation = self.reputation;
         player.game_time = self.game_time;
+        player.loan = self.loan;
+        player.months_in_debt = self.months_in_debt;
+        player.ledger = self.ledger;
         player
     }
 }
@@ -367,7 +570,7 @@ impl RouteSave {
         }
     }
 
-    fn to_route(self) -> Route {
+    fn into_route(self) -> Route {
         Route {
             id: self.id,
             name: self.name,
@@ -391,6 +594,7 @@ impl VehicleSave {
             route_index: vehicle.route_index,
             current_path: vehicle.current_path.clone(),
             path_index: vehicle.path_index,
+            pending_hops: vehicle.pending_hops.clone(),
             age: vehicle.age,
             reliability: vehicle.reliability,
             speed: vehicle.speed,
@@ -398,21 +602,25 @@ impl VehicleSave {
             profit: vehicle.profit,
             on_time_deliveries: vehicle.on_time_deliveries,
             total_deliveries: vehicle.total_deliveries,
+            cargo: vehicle.cargo.clone(),
         }
     }
 
-    fn to_vehicle(self) -> Vehicle {
+    fn into_vehicle(self) -> Vehicle {
         Vehicle {
             id: self.id,
+            owner: 0,
             vehicle_type: self.vehicle_type,
             x: self.x,
             y: self.y,
-            state: self.state.to_vehicle_state(),
-            cargo: std::collections::HashMap::new(),
+            state: self.state.into_vehicle_state(),
+            cargo: self.cargo,
             route: self.route,
             route_index: self.route_index,
             current_path: self.current_path,
             path_index: self.path_index,
+            pending_hops: self.pending_hops,
+            path_version: 0,
             age: self.age,
             reliability: self.reliability,
             speed: self.speed,
@@ -420,6 +628,7 @@ impl VehicleSave {
             profit: self.profit,
             on_time_deliveries: self.on_time_deliveries,
             total_deliveries: self.total_deliveries,
+            uncollected_income: Vec::new(),
         }
     }
 }
@@ -436,16 +645,21 @@ impl VehicleStateSave {
             VehicleState::Loading => VehicleStateSave::Loading,
             VehicleState::Unloading => VehicleStateSave::Unloading,
             VehicleState::Broken => VehicleStateSave::Broken,
+            VehicleState::Lost { .. } => VehicleStateSave::Lost,
+            VehicleState::Holding => VehicleStateSave::Holding,
         }
     }
 
-    fn to_vehicle_state(self) -> VehicleState {
+    fn into_vehicle_state(self) -> VehicleState {
         match self {
             VehicleStateSave::Idle => VehicleState::Idle,
             VehicleStateSave::Moving { from, to, progress } => VehicleState::Moving { from, to, progress },
             VehicleStateSave::Loading => VehicleState::Loading,
             VehicleStateSave::Unloading => VehicleState::Unloading,
             VehicleStateSave::Broken => VehicleState::Broken,
+            // Look for a route again straight away
+            VehicleStateSave::Lost => VehicleState::Lost { revision: u64::MAX, retry_at: 0 },
+            VehicleStateSave::Holding => VehicleState::Holding,
         }
     }
 }
@@ -456,14 +670,16 @@ impl EconomySave {
             inflation_rate: economy.inflation_rate,
             economic_state: economy.economic_state.clone(),
             month: economy.month,
+            price_level: economy.price_level,
         }
     }
 
-    fn to_economy(self) -> Economy {
+    fn into_economy(self) -> Economy {
         let mut economy = Economy::new();
         economy.inflation_rate = self.inflation_rate;
         economy.economic_state = self.economic_state;
         economy.month = self.month;
+        economy.price_level = self.price_level;
         economy
     }
 }
@@ -479,9 +695,9 @@ impl AIPlayerSave {
         }
     }
 
-    fn to_ai_player(self) -> AIPlayer {
+    fn into_ai_player(self) -> AIPlayer {
         AIPlayer {
-            player: self.player.to_player(),
+            player: self.player.into_player(),
             difficulty: self.difficulty,
             strategy: self.strategy,
             decision_timer: self.decision_timer,
@@ -491,12 +707,78 @@ impl AIPlayerSave {
     }
 }
 
-pub fn save_game(game: &Game, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn save_game(game: &mut Game, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
     let save = GameSave::from_game(game);
     save.save_to_file(filename)
 }
 
 pub fn load_game(filename: &str) -> Result<Game, Box<dyn std::error::Error>> {
     let save = GameSave::load_from_file(filename)?;
-    Ok(save.to_game())
-}
\ No newline at end of file
+    Ok(save.into_game())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::TerrainConfig;
+    use crate::world::{SignalType, TrackType};
+
+    #[test]
+    fn a_saved_game_loads_back_the_same() {
+        let config = WorldGenConfig {
+            terrain: TerrainConfig { sea_level: 0.4, forest_share: 0.3, ..TerrainConfig::default() },
+            ..WorldGenConfig::new(48, 40, 1234)
+        };
+        let mut game = Game::new_headless();
+        game.world = World::from_config(config.clone());
+        game.apply_settings(GameSettings { infinite_money: true, level_crossing_crashes: true, ..GameSettings::default() });
+        game.news.push(NewsItem { day: 12, message: "Railway opens".to_string(), x: 3, y: 4 });
+
+        let world = &mut game.world;
+        world.set_tile_content(2, 2, TileContent::Track(TrackType::Straight { horizontal: true }));
+        world.signals.insert((2, 2), Signal { signal_type: SignalType::Path, facing: Direction::East });
+        world.level_crossings.insert((5, 5));
+        world.canals.insert((6, 6), TerrainType::Desert);
+        world.buoys.insert((7, 7));
+        world.airports.insert((10, 10), Airport { size: AirportSize::Large, runway_users: vec![(1, 3)] });
+        world.build_costs.insert((2, 2), 1_500);
+
+        let json = serde_json::to_string(&GameSave::from_game(&mut game)).unwrap();
+        let save: GameSave = serde_json::from_str(&json).unwrap();
+
+        let gen_config = save.world.gen_config.clone().unwrap();
+        assert_eq!((gen_config.width, gen_config.height, gen_config.seed), (48, 40, 1234));
+        assert_eq!(gen_config.terrain.sea_level, 0.4);
+        assert_eq!(gen_config.terrain.forest_share, 0.3);
+        // The config alone regrows the map the game started on
+        let regrown = World::from_config(gen_config);
+        let fresh = World::from_config(config);
+        for (a, b) in regrown.tiles.iter().flatten().zip(fresh.tiles.iter().flatten()) {
+            assert_eq!((&a.terrain, a.height), (&b.terrain, b.height));
+        }
+        assert_eq!(regrown.towns, fresh.towns);
+        assert_eq!(regrown.industries, fresh.industries);
+
+        let mut loaded = save.into_game();
+        assert!(loaded.settings.infinite_money && loaded.player.infinite_money);
+        assert!(loaded.settings.level_crossing_crashes);
+        assert_eq!(loaded.world.config.seed, 1234);
+        assert_eq!((loaded.world.width, loaded.world.height), (48, 40));
+        assert_eq!(loaded.news.len(), 1);
+        assert_eq!((loaded.news[0].day, loaded.news[0].message.as_str()), (12, "Railway opens"));
+
+        let world = &loaded.world;
+        assert_eq!(world.signals.get(&(2, 2)), Some(&Signal { signal_type: SignalType::Path, facing: Direction::East }));
+        assert!(world.level_crossings.contains(&(5, 5)));
+        assert_eq!(world.canals.get(&(6, 6)), Some(&TerrainType::Desert));
+        assert!(world.buoys.contains(&(7, 7)));
+        let airport = &world.airports[&(10, 10)];
+        assert_eq!((airport.size, airport.runway_users.as_slice()), (AirportSize::Large, &[(1, 3)][..]));
+        assert_eq!(world.airport_at((11, 11)), Some((10, 10)));
+        assert_eq!(world.build_costs.get(&(2, 2)), Some(&1_500));
+
+        // The loaded game rolls the same dice the saved one goes on to
+        let ahead: Vec<u64> = (0..4).map(|_| game.world.rng.r#gen()).collect();
+        let loaded_ahead: Vec<u64> = (0..4).map(|_| loaded.world.rng.r#gen()).collect();
+        assert_eq!(ahead, loaded_ahead);
+    }
+}
@@ -7,15 +7,34 @@ pub struct GameRenderData {
     pub player_data: PlayerRenderData,
     pub ui_state: UIRenderState,
     pub notifications: Vec<String>,
+    pub news: Vec<NewsItem>,
     pub game_time: u32,
 }
 
+/// A dated headline for the news feed, pointing at where it happened.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewsItem {
+    pub day: u32,
+    pub message: String,
+    pub x: usize,
+    pub y: usize,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WorldRenderData {
     pub width: usize,
     pub height: usize,
+    pub seed: u64,
     pub tiles: Vec<Vec<TileRenderData>>,
     pub vehicles: Vec<VehicleRenderData>,
+    pub companies: Vec<CompanyRenderData>,
+}
+
+/// A company the client can colour owned infrastructure by.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompanyRenderData {
+    pub id: crate::world::CompanyId,
+    pub name: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -23,7 +42,9 @@ pub struct TileRenderData {
     pub x: usize,
     pub y: usize,
     pub terrain: crate::world::TerrainType,
+    pub height: u8,
     pub content: TileContentRenderData,
+    pub owner: Option<crate::world::CompanyId>,
     pub ascii_char: char,
     pub style_color: String,
 }
@@ -34,21 +55,40 @@ pub enum TileContentRenderData {
     Town {
         name: String,
         population: u32,
+        houses: usize,
+        growth_rate: f32,
     },
     Industry {
         industry_type: String,
         production_rate: u32,
         stockpile: HashMap<String, u32>,
+        input_stockpile: HashMap<String, u32>,
+        last_month_transported: u32,
     },
     Station {
         name: String,
         station_type: String,
         cargo_waiting: HashMap<String, u32>,
+        accepts: Vec<String>,
+        catchment_radius: usize,
+        /// Rating per cargo, in percent
+        ratings: HashMap<String, u32>,
     },
     Track {
         track_type: String,
+        signal: Option<String>,
+        signal_state: Option<crate::world::SignalState>,
+        level_crossing: bool,
     },
     Road,
+    House {
+        town: String,
+        residents: u32,
+    },
+    Canal,
+    Buoy,
+    /// Runways and aprons around an airport's terminal
+    Airfield,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -66,11 +106,49 @@ pub struct VehicleRenderData {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlayerRenderData {
     pub name: String,
+    pub company_id: crate::world::CompanyId,
     pub money: i64,
+    pub loan: i64,
+    pub max_loan: i64,
+    /// Yearly interest rate on the loan
+    pub interest_rate: f32,
+    pub infinite_money: bool,
     pub vehicle_count: usize,
     pub reputation: f32,
 }
 
+/// A company's income statements, for the finances view.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompanyFinances {
+    pub name: String,
+    pub money: i64,
+    pub loan: i64,
+    pub current_month: crate::finance::Statement,
+    /// Closed periods, oldest first
+    pub monthly: Vec<crate::finance::Statement>,
+    pub quarterly: Vec<crate::finance::Statement>,
+    pub yearly: Vec<crate::finance::Statement>,
+}
+
+/// What building a line of track or road would involve.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinePlan {
+    /// Every tile along the line, in building order
+    pub path: Vec<(usize, usize)>,
+    /// Tiles that need new track or road; the rest are already in place
+    pub new_tiles: Vec<(usize, usize)>,
+    pub cost: i64,
+    /// Tiles that can't be built on
+    pub blocked: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LineBuildResponse {
+    pub success: bool,
+    pub message: String,
+    pub plan: Option<LinePlan>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UIRenderState {
     pub cursor_x: usize,
@@ -121,10 +199,24 @@ impl InputCommand {
             "pause" => Some(crate::ui::InputEvent::Pause),
             "build_menu" => Some(crate::ui::InputEvent::BuildMenu),
             "show_controls" => Some(crate::ui::InputEvent::ShowControls),
+            "show_finances" => Some(crate::ui::InputEvent::ShowFinances),
             "build_rail" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildRailTrack)),
             "build_station" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildTrainStation)),
             "build_road" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildRoad)),
             "build_bus_stop" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildBusStop)),
+            "build_raise_land" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::RaiseLand)),
+            "build_lower_land" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::LowerLand)),
+            "build_demolish" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::Demolish)),
+            "build_rail_line" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::RailLine)),
+            "build_road_line" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::RoadLine)),
+            "build_signal" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildSignal)),
+            "build_harbor" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildHarbor)),
+            "build_canal" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildCanal)),
+            "build_buoy" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildBuoy)),
+            "build_small_airport" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildAirport { size: crate::world::AirportSize::Small })),
+            "build_large_airport" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuildAirport { size: crate::world::AirportSize::Large })),
+            "borrow" => Some(crate::ui::InputEvent::BorrowMoney),
+            "repay" => Some(crate::ui::InputEvent::RepayLoan),
             "buy_vehicle" => Some(crate::ui::InputEvent::BuildAction(crate::ui::BuildAction::BuyVehicle)),
             "vehicle_go_to" => Some(crate::ui::InputEvent::VehicleOrder(crate::ui::VehicleOrder::GoToLocation)),
             "vehicle_create_route" => Some(crate::ui::InputEvent::VehicleOrder(crate::ui::VehicleOrder::CreateRoute)),
@@ -0,0 +1,390 @@
+use std::collections::HashSet;
+use rand::Rng;
+use rand::rngs::StdRng;
+use crate::world::TerrainType;
+
+/// Highest elevation a tile can have. Sea tiles sit at 0.
+pub const MAX_HEIGHT: u8 = 15;
+
+/// Tunables for the heightmap generator. Shares are fractions of the whole map.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TerrainConfig {
+    /// Fraction of the map that lies below sea level
+    pub sea_level: f32,
+    /// Amplitude kept per noise octave (0.3 = smooth hills, 0.7 = jagged)
+    pub roughness: f32,
+    /// Wavelength in tiles of the largest landforms
+    pub feature_size: f32,
+    pub mountain_share: f32,
+    pub forest_share: f32,
+    pub desert_share: f32,
+    /// Rivers per 100,000 tiles
+    pub river_density: f32,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        Self {
+            sea_level: 0.25,
+            roughness: 0.5,
+            feature_size: 128.0,
+            mountain_share: 0.08,
+            forest_share: 0.15,
+            desert_share: 0.10,
+            river_density: 4.0,
+        }
+    }
+}
+
+pub struct Heightmap {
+    pub width: usize,
+    pub height: usize,
+    pub elevation: Vec<u8>,
+    pub terrain: Vec<TerrainType>,
+}
+
+impl Heightmap {
+    pub fn generate(width: usize, height: usize, config: &TerrainConfig, rng: &mut StdRng) -> Self {
+        let height_noise = FractalNoise::new(rng.r#gen(), 6, config.roughness, config.feature_size);
+        let forest_noise = FractalNoise::new(rng.r#gen(), 4, 0.5, config.feature_size / 4.0);
+        let desert_noise = FractalNoise::new(rng.r#gen(), 3, 0.5, config.feature_size / 2.0);
+
+        let mut raw: Vec<f32> = (0..width * height)
+            .map(|i| height_noise.sample((i % width) as f32, (i / width) as f32))
+            .collect();
+        normalize(&mut raw);
+        let sea = quantile(&raw, config.sea_level);
+        let snow_line = quantile(&raw, 1.0 - config.mountain_share);
+
+        let mut map = Self {
+            width,
+            height,
+            elevation: raw.iter().map(|&h| Self::elevation_for(h, sea)).collect(),
+            terrain: raw.iter().map(|&h| {
+                if h < sea {
+                    TerrainType::Water
+                } else if h >= snow_line {
+                    TerrainType::Mountain
+                } else {
+                    TerrainType::Grass
+                }
+            }).collect(),
+        };
+
+        map.limit_slopes();
+        map.carve_rivers(config, rng);
+        map.scatter_vegetation(config, &forest_noise, &desert_noise);
+        map
+    }
+
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    fn elevation_for(h: f32, sea: f32) -> u8 {
+        if h < sea {
+            0
+        } else {
+            let land = (h - sea) / (1.0 - sea).max(f32::EPSILON);
+            1 + (land * (MAX_HEIGHT - 1) as f32).round() as u8
+        }
+    }
+
+    /// Lowers peaks until no tile is more than one level above a neighbour,
+    /// so generated land obeys the same rule as terraforming.
+    fn limit_slopes(&mut self) {
+        let (w, h) = (self.width, self.height);
+        loop {
+            let mut changed = false;
+            for i in (0..w * h).chain((0..w * h).rev()) {
+                let (x, y) = (i % w, i / w);
+                let mut lowest = self.elevation[i];
+                if x > 0 { lowest = lowest.min(self.elevation[i - 1] + 1); }
+                if x + 1 < w { lowest = lowest.min(self.elevation[i + 1] + 1); }
+                if y > 0 { lowest = lowest.min(self.elevation[i - w] + 1); }
+                if y + 1 < h { lowest = lowest.min(self.elevation[i + w] + 1); }
+                if lowest < self.elevation[i] {
+                    self.elevation[i] = lowest;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Rivers start on high ground and follow the steepest way down until they
+    /// reach existing water. A river that gets trapped in a hollow becomes a lake.
+    fn carve_rivers(&mut self, config: &TerrainConfig, rng: &mut StdRng) {
+        let river_count = ((self.width * self.height) as f32 / 100_000.0 * config.river_density).round() as usize;
+        for _ in 0..river_count {
+            // A handful of attempts to find a spring above the lowlands
+            let spring = (0..20)
+                .map(|_| (rng.gen_range(0..self.width), rng.gen_range(0..self.height)))
+                .find(|&(x, y)| {
+                    let i = self.index(x, y);
+                    self.elevation[i] >= MAX_HEIGHT / 2 && !matches!(self.terrain[i], TerrainType::Water)
+                });
+            let Some((x, y)) = spring else { continue };
+
+            let (course, trapped) = self.river_course(x, y);
+            if trapped {
+                let &(lx, ly) = course.last().unwrap();
+                self.fill_lake(lx, ly);
+            }
+            for (cx, cy) in course {
+                let i = self.index(cx, cy);
+                self.terrain[i] = TerrainType::Water;
+            }
+        }
+    }
+
+    /// Follows the steepest way down from a spring. The flag is set when the
+    /// river ends in a hollow instead of reaching water.
+    fn river_course(&self, mut x: usize, mut y: usize) -> (Vec<(usize, usize)>, bool) {
+        let max_length = self.width + self.height;
+        let mut course = vec![(x, y)];
+        let mut visited = HashSet::from([(x, y)]);
+        loop {
+            let here = self.elevation[self.index(x, y)];
+            let next = self.neighbours(x, y)
+                .filter(|pos| !visited.contains(pos))
+                .min_by_key(|&(nx, ny)| self.elevation[self.index(nx, ny)]);
+
+            match next {
+                Some((nx, ny)) if self.elevation[self.index(nx, ny)] <= here && course.len() < max_length => {
+                    if matches!(self.terrain[self.index(nx, ny)], TerrainType::Water) {
+                        return (course, false);
+                    }
+                    course.push((nx, ny));
+                    visited.insert((nx, ny));
+                    x = nx;
+                    y = ny;
+                }
+                _ => return (course, true),
+            }
+        }
+    }
+
+    fn fill_lake(&mut self, x: usize, y: usize) {
+        let level = self.elevation[self.index(x, y)];
+        let mut frontier = vec![(x, y)];
+        let mut size = 0;
+        while let Some((cx, cy)) = frontier.pop() {
+            let i = self.index(cx, cy);
+            if size >= 40 || matches!(self.terrain[i], TerrainType::Water) || self.elevation[i] > level {
+                continue;
+            }
+            self.terrain[i] = TerrainType::Water;
+            size += 1;
+            frontier.extend(self.neighbours(cx, cy));
+        }
+    }
+
+    /// Forests grow in noise-shaped clusters; deserts favour the middle
+    /// latitudes of the map and avoid high ground.
+    fn scatter_vegetation(&mut self, config: &TerrainConfig, forest_noise: &FractalNoise, desert_noise: &FractalNoise) {
+        let (w, h) = (self.width, self.height);
+        let desert_score = |x: usize, y: usize| {
+            let latitude = 1.0 - ((y as f32 / h as f32) - 0.5).abs() * 2.0;
+            latitude * 0.6 + desert_noise.sample(x as f32, y as f32) * 0.4
+        };
+        let mut desert_scores: Vec<f32> = (0..w * h).map(|i| desert_score(i % w, i / w)).collect();
+        normalize(&mut desert_scores);
+        let desert_line = quantile(&desert_scores, 1.0 - config.desert_share);
+
+        let mut forest_scores: Vec<f32> = (0..w * h)
+            .map(|i| forest_noise.sample((i % w) as f32, (i / w) as f32))
+            .collect();
+        normalize(&mut forest_scores);
+        let forest_line = quantile(&forest_scores, 1.0 - config.forest_share);
+
+        for i in 0..w * h {
+            if !matches!(self.terrain[i], TerrainType::Grass) {
+                continue;
+            }
+            if desert_scores[i] >= desert_line && self.elevation[i] < MAX_HEIGHT * 2 / 3 {
+                self.terrain[i] = TerrainType::Desert;
+            } else if forest_scores[i] >= forest_line {
+                self.terrain[i] = TerrainType::Forest;
+            }
+        }
+    }
+
+    fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().filter_map(move |(dx, dy)| {
+            let nx = x.checked_add_signed(dx)?;
+            let ny = y.checked_add_signed(dy)?;
+            (nx < self.width && ny < self.height).then_some((nx, ny))
+        })
+    }
+}
+
+/// Value noise summed over several octaves. Lattice values come from hashing
+/// the coordinates with the seed, so sampling needs no stored state.
+pub struct FractalNoise {
+    seed: u64,
+    octaves: u32,
+    persistence: f32,
+    base_frequency: f32,
+}
+
+impl FractalNoise {
+    pub fn new(seed: u64, octaves: u32, persistence: f32, feature_size: f32) -> Self {
+        Self {
+            seed,
+            octaves,
+            persistence,
+            base_frequency: 1.0 / feature_size.max(1.0),
+        }
+    }
+
+    /// Returns a value in `[0, 1]`.
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut max_amplitude = 0.0;
+        let mut frequency = self.base_frequency;
+
+        for octave in 0..self.octaves {
+            total += self.value_noise(x * frequency, y * frequency, octave) * amplitude;
+            max_amplitude += amplitude;
+            amplitude *= self.persistence;
+            frequency *= 2.0;
+        }
+
+        total / max_amplitude
+    }
+
+    fn value_noise(&self, x: f32, y: f32, octave: u32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+        let (ix, iy) = (x0 as i64, y0 as i64);
+
+        let a = self.lattice(ix, iy, octave);
+        let b = self.lattice(ix + 1, iy, octave);
+        let c = self.lattice(ix, iy + 1, octave);
+        let d = self.lattice(ix + 1, iy + 1, octave);
+
+        let top = a + (b - a) * tx;
+        let bottom = c + (d - c) * tx;
+        top + (bottom - top) * ty
+    }
+
+    fn lattice(&self, x: i64, y: i64, octave: u32) -> f32 {
+        // SplitMix64 finaliser over the packed coordinates
+        let mut h = self.seed
+            ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+            ^ (octave as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+        (h >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Stretches samples to cover `[0, 1]`; summed octaves bunch up around 0.5.
+fn normalize(values: &mut [f32]) {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = (max - min).max(f32::EPSILON);
+    for v in values.iter_mut() {
+        *v = (*v - min) / range;
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Approximate quantile of values in `[0, 1]` using a fixed histogram, which
+/// avoids sorting a million samples per terrain layer.
+fn quantile(values: &[f32], fraction: f32) -> f32 {
+    const BINS: usize = 1024;
+    let mut histogram = [0usize; BINS];
+    for &v in values {
+        histogram[((v.clamp(0.0, 1.0)) * (BINS - 1) as f32) as usize] += 1;
+    }
+
+    let target = (values.len() as f32 * fraction.clamp(0.0, 1.0)) as usize;
+    let mut seen = 0;
+    for (bin, &count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen > target {
+            return bin as f32 / (BINS - 1) as f32;
+        }
+    }
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn generate(config: &TerrainConfig, seed: u64) -> Heightmap {
+        Heightmap::generate(96, 96, config, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn share(map: &Heightmap, terrain: TerrainType) -> f32 {
+        map.terrain.iter().filter(|&t| *t == terrain).count() as f32 / map.terrain.len() as f32
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_map() {
+        let config = TerrainConfig::default();
+        let (a, b) = (generate(&config, 7), generate(&config, 7));
+        assert_eq!(a.elevation, b.elevation);
+        assert_eq!(a.terrain, b.terrain);
+        assert_ne!(a.elevation, generate(&config, 8).elevation);
+    }
+
+    #[test]
+    fn neighbouring_tiles_differ_by_at_most_one_level() {
+        let map = generate(&TerrainConfig { roughness: 0.7, ..TerrainConfig::default() }, 3);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let here = map.elevation[map.index(x, y)];
+                for (nx, ny) in map.neighbours(x, y) {
+                    assert!(here.abs_diff(map.elevation[map.index(nx, ny)]) <= 1, "cliff at ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rivers_never_flow_uphill() {
+        let map = generate(&TerrainConfig::default(), 11);
+        let springs: Vec<_> = (0..map.width * map.height)
+            .filter(|&i| map.elevation[i] >= MAX_HEIGHT / 2 && !matches!(map.terrain[i], TerrainType::Water))
+            .map(|i| (i % map.width, i / map.width))
+            .collect();
+        assert!(!springs.is_empty());
+
+        for (x, y) in springs {
+            let (course, _) = map.river_course(x, y);
+            for step in course.windows(2) {
+                let from = map.elevation[map.index(step[0].0, step[0].1)];
+                let to = map.elevation[map.index(step[1].0, step[1].1)];
+                assert!(to <= from, "river from ({x}, {y}) climbs at {:?}", step[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn sea_level_and_shares_move_the_terrain_mix() {
+        let base = TerrainConfig { river_density: 0.0, ..TerrainConfig::default() };
+        let mix = |config: TerrainConfig, terrain: TerrainType| share(&generate(&config, 5), terrain);
+
+        assert!(mix(TerrainConfig { sea_level: 0.1, ..base.clone() }, TerrainType::Water)
+            < mix(TerrainConfig { sea_level: 0.4, ..base.clone() }, TerrainType::Water));
+        assert!(mix(TerrainConfig { mountain_share: 0.02, ..base.clone() }, TerrainType::Mountain)
+            < mix(TerrainConfig { mountain_share: 0.2, ..base.clone() }, TerrainType::Mountain));
+        assert!(mix(TerrainConfig { forest_share: 0.05, ..base.clone() }, TerrainType::Forest)
+            < mix(TerrainConfig { forest_share: 0.3, ..base.clone() }, TerrainType::Forest));
+        assert!(mix(TerrainConfig { desert_share: 0.02, ..base.clone() }, TerrainType::Desert)
+            < mix(TerrainConfig { desert_share: 0.2, ..base }, TerrainType::Desert));
+    }
+}
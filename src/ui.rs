@@ -30,6 +30,9 @@ pub enum InputEvent {
     VehicleOrder(VehicleOrder),
     VehiclePurchase(VehiclePurchaseType),
     FinishRouteCreation,
+    BorrowMoney,
+    RepayLoan,
+    ShowFinances,
 }
 
 #[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
@@ -39,6 +42,16 @@ pub enum BuildAction {
     BuildRoad,
     BuildBusStop,
     BuyVehicle,
+    RaiseLand,
+    LowerLand,
+    Demolish,
+    RailLine,
+    RoadLine,
+    BuildSignal,
+    BuildHarbor,
+    BuildCanal,
+    BuildBuoy,
+    BuildAirport { size: crate::world::AirportSize },
 }
 
 #[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
@@ -81,6 +94,7 @@ pub struct UI {
     selected_tile: Option<Tile>,
     pub show_build_menu: bool,
     pub show_controls: bool,
+    pub show_finances: bool,
     show_vehicle_menu: bool,
     selected_vehicle_id: Option<u32>,
     build_mode: Option<BuildAction>,
@@ -93,6 +107,12 @@ pub struct UI {
     paused: bool,
 }
 
+impl Default for UI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UI {
     pub fn new() -> Self {
         Self {
@@ -106,6 +126,7 @@ impl UI {
             selected_tile: None,
             show_build_menu: false,
             show_controls: false,
+            show_finances: false,
             show_vehicle_menu: false,
             selected_vehicle_id: None,
             build_mode: None,
@@ -139,129 +160,176 @@ impl UI {
     }
 
     pub fn get_input(&mut self) -> Result<Option<InputEvent>, Box<dyn std::error::Error>> {
-        if event::poll(std::time::Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    return Ok(match key.code {
-                        KeyCode::Char('q') => Some(InputEvent::Quit),
-                        KeyCode::Up | KeyCode::Char('w') => Some(InputEvent::Move(CursorDirection::Up)),
-                        KeyCode::Down | KeyCode::Char('s') => Some(InputEvent::Move(CursorDirection::Down)),
-                        KeyCode::Left | KeyCode::Char('a') => Some(InputEvent::Move(CursorDirection::Left)),
-                        KeyCode::Right | KeyCode::Char('d') => Some(InputEvent::Move(CursorDirection::Right)),
-                        KeyCode::Enter => {
-                            if self.route_creation_mode.is_some() {
-                                Some(InputEvent::FinishRouteCreation)
-                            } else {
-                                Some(InputEvent::Select)
-                            }
-                        },
-                        KeyCode::Char(' ') => Some(InputEvent::Select),
-                        KeyCode::Char('b') => Some(InputEvent::BuildMenu),
-                        KeyCode::Char('p') => Some(InputEvent::Pause),
-                        KeyCode::F(5) => Some(InputEvent::Save),
-                        KeyCode::F(9) => Some(InputEvent::Load),
-                        KeyCode::Char('?') => Some(InputEvent::ShowControls),
-                        KeyCode::Esc => {
-                            if self.show_controls || self.show_build_menu || self.show_vehicle_menu || self.show_vehicle_purchase_menu {
-                                self.show_controls = false;
-                                self.show_build_menu = false;
-                                self.show_vehicle_menu = false;
-                                self.show_vehicle_purchase_menu = false;
-                                self.build_mode = None;
-                                self.vehicle_order_mode = None;
-                                self.route_creation_mode = None;
-                                self.vehicle_purchase_mode = None;
-                                None
-                            } else if self.build_mode.is_some() || self.vehicle_order_mode.is_some() || self.route_creation_mode.is_some() || self.vehicle_purchase_mode.is_some() {
-                                self.build_mode = None;
-                                self.vehicle_order_mode = None;
-                                self.route_creation_mode = None;
-                                self.vehicle_purchase_mode = None;
-                                None
-                            } else {
-                                None
-                            }
-                        },
-                        // Build menu number keys
-                        KeyCode::Char('1') if self.show_build_menu => {
-                            self.show_build_menu = false;
-                            Some(InputEvent::BuildAction(BuildAction::BuildRailTrack))
-                        },
-                        KeyCode::Char('2') if self.show_build_menu => {
-                            self.show_build_menu = false;
-                            Some(InputEvent::BuildAction(BuildAction::BuildTrainStation))
-                        },
-                        KeyCode::Char('3') if self.show_build_menu => {
-                            self.show_build_menu = false;
-                            Some(InputEvent::BuildAction(BuildAction::BuildRoad))
-                        },
-                        KeyCode::Char('4') if self.show_build_menu => {
-                            self.show_build_menu = false;
-                            Some(InputEvent::BuildAction(BuildAction::BuildBusStop))
-                        },
-                        KeyCode::Char('5') if self.show_build_menu => {
-                            self.show_build_menu = false;
-                            self.show_vehicle_purchase_menu = true;
-                            None
-                        },
-                        // Vehicle menu number keys
-                        KeyCode::Char('1') if self.show_vehicle_menu => {
-                            self.show_vehicle_menu = false;
-                            Some(InputEvent::VehicleOrder(VehicleOrder::GoToLocation))
-                        },
-                        KeyCode::Char('2') if self.show_vehicle_menu => {
-                            self.show_vehicle_menu = false;
-                            Some(InputEvent::VehicleOrder(VehicleOrder::CreateRoute))
-                        },
-                        KeyCode::Char('3') if self.show_vehicle_menu => {
-                            self.show_vehicle_menu = false;
-                            Some(InputEvent::VehicleOrder(VehicleOrder::StartRoute))
-                        },
-                        KeyCode::Char('4') if self.show_vehicle_menu => {
-                            self.show_vehicle_menu = false;
-                            Some(InputEvent::VehicleOrder(VehicleOrder::Stop))
-                        },
-                        KeyCode::Char('5') if self.show_vehicle_menu => {
-                            self.show_vehicle_menu = false;
-                            Some(InputEvent::VehicleOrder(VehicleOrder::SendToDepot))
-                        },
-                        // Vehicle purchase menu number keys
-                        KeyCode::Char('1') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Train))
-                        },
-                        KeyCode::Char('2') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Bus))
-                        },
-                        KeyCode::Char('3') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::SmallTruck))
-                        },
-                        KeyCode::Char('4') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::LargeTruck))
-                        },
-                        KeyCode::Char('5') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Ship))
-                        },
-                        KeyCode::Char('6') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::SmallPlane))
-                        },
-                        KeyCode::Char('7') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::LargePlane))
-                        },
-                        KeyCode::Char('0') if self.show_vehicle_purchase_menu => {
-                            self.show_vehicle_purchase_menu = false;
-                            Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Auto))
-                        },
-                        _ => None,
-                    });
-                }
-            }
+        if event::poll(std::time::Duration::from_millis(50))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            return Ok(match key.code {
+                KeyCode::Char('q') => Some(InputEvent::Quit),
+                KeyCode::Up | KeyCode::Char('w') => Some(InputEvent::Move(CursorDirection::Up)),
+                KeyCode::Down | KeyCode::Char('s') => Some(InputEvent::Move(CursorDirection::Down)),
+                KeyCode::Left | KeyCode::Char('a') => Some(InputEvent::Move(CursorDirection::Left)),
+                KeyCode::Right | KeyCode::Char('d') => Some(InputEvent::Move(CursorDirection::Right)),
+                KeyCode::Enter => {
+                    if self.route_creation_mode.is_some() {
+                        Some(InputEvent::FinishRouteCreation)
+                    } else {
+                        Some(InputEvent::Select)
+                    }
+                },
+                KeyCode::Char(' ') => Some(InputEvent::Select),
+                KeyCode::Char('b') => Some(InputEvent::BuildMenu),
+                KeyCode::Char('p') => Some(InputEvent::Pause),
+                KeyCode::Char('l') => Some(InputEvent::BorrowMoney),
+                KeyCode::Char('r') => Some(InputEvent::RepayLoan),
+                KeyCode::Char('f') => Some(InputEvent::ShowFinances),
+                KeyCode::F(5) => Some(InputEvent::Save),
+                KeyCode::F(9) => Some(InputEvent::Load),
+                KeyCode::Char('?') => Some(InputEvent::ShowControls),
+                KeyCode::Esc => {
+                    if self.show_controls || self.show_finances || self.show_build_menu || self.show_vehicle_menu || self.show_vehicle_purchase_menu {
+                        self.show_controls = false;
+                        self.show_finances = false;
+                        self.show_build_menu = false;
+                        self.show_vehicle_menu = false;
+                        self.show_vehicle_purchase_menu = false;
+                        self.build_mode = None;
+                        self.vehicle_order_mode = None;
+                        self.route_creation_mode = None;
+                        self.vehicle_purchase_mode = None;
+                        None
+                    } else if self.build_mode.is_some() || self.vehicle_order_mode.is_some() || self.route_creation_mode.is_some() || self.vehicle_purchase_mode.is_some() {
+                        self.build_mode = None;
+                        self.vehicle_order_mode = None;
+                        self.route_creation_mode = None;
+                        self.vehicle_purchase_mode = None;
+                        None
+                    } else {
+                        None
+                    }
+                },
+                // Build menu number keys
+                KeyCode::Char('1') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildRailTrack))
+                },
+                KeyCode::Char('2') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildTrainStation))
+                },
+                KeyCode::Char('3') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildRoad))
+                },
+                KeyCode::Char('4') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildBusStop))
+                },
+                KeyCode::Char('5') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    self.show_vehicle_purchase_menu = true;
+                    None
+                },
+                KeyCode::Char('6') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::RaiseLand))
+                },
+                KeyCode::Char('7') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::LowerLand))
+                },
+                KeyCode::Char('8') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::Demolish))
+                },
+                KeyCode::Char('9') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::RailLine))
+                },
+                KeyCode::Char('0') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::RoadLine))
+                },
+                KeyCode::Char('g') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildSignal))
+                },
+                KeyCode::Char('h') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildHarbor))
+                },
+                KeyCode::Char('c') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildCanal))
+                },
+                KeyCode::Char('o') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildBuoy))
+                },
+                KeyCode::Char('i') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildAirport { size: crate::world::AirportSize::Small }))
+                },
+                KeyCode::Char('j') if self.show_build_menu => {
+                    self.show_build_menu = false;
+                    Some(InputEvent::BuildAction(BuildAction::BuildAirport { size: crate::world::AirportSize::Large }))
+                },
+                // Vehicle menu number keys
+                KeyCode::Char('1') if self.show_vehicle_menu => {
+                    self.show_vehicle_menu = false;
+                    Some(InputEvent::VehicleOrder(VehicleOrder::GoToLocation))
+                },
+                KeyCode::Char('2') if self.show_vehicle_menu => {
+                    self.show_vehicle_menu = false;
+                    Some(InputEvent::VehicleOrder(VehicleOrder::CreateRoute))
+                },
+                KeyCode::Char('3') if self.show_vehicle_menu => {
+                    self.show_vehicle_menu = false;
+                    Some(InputEvent::VehicleOrder(VehicleOrder::StartRoute))
+                },
+                KeyCode::Char('4') if self.show_vehicle_menu => {
+                    self.show_vehicle_menu = false;
+                    Some(InputEvent::VehicleOrder(VehicleOrder::Stop))
+                },
+                KeyCode::Char('5') if self.show_vehicle_menu => {
+                    self.show_vehicle_menu = false;
+                    Some(InputEvent::VehicleOrder(VehicleOrder::SendToDepot))
+                },
+                // Vehicle purchase menu number keys
+                KeyCode::Char('1') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Train))
+                },
+                KeyCode::Char('2') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Bus))
+                },
+                KeyCode::Char('3') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::SmallTruck))
+                },
+                KeyCode::Char('4') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::LargeTruck))
+                },
+                KeyCode::Char('5') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Ship))
+                },
+                KeyCode::Char('6') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::SmallPlane))
+                },
+                KeyCode::Char('7') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::LargePlane))
+                },
+                KeyCode::Char('0') if self.show_vehicle_purchase_menu => {
+                    self.show_vehicle_purchase_menu = false;
+                    Some(InputEvent::VehiclePurchase(VehiclePurchaseType::Auto))
+                },
+                _ => None,
+            });
         }
         Ok(None)
     }
@@ -385,6 +453,7 @@ impl UI {
         let selected_tile = self.selected_tile.clone();
         let show_build_menu = self.show_build_menu;
         let show_controls = self.show_controls;
+        let show_finances = self.show_finances;
         let show_vehicle_menu = self.show_vehicle_menu;
         let show_vehicle_purchase_menu = self.show_vehicle_purchase_menu;
         let selected_vehicle_id = self.selected_vehicle_id;
@@ -421,10 +490,14 @@ impl UI {
                     Self::render_controls_popup(f, f.size());
                 }
 
-                if show_vehicle_menu {
-                    if let Some(vehicle_id) = selected_vehicle_id {
-                        Self::render_vehicle_menu_static(f, f.size(), vehicle_id, &player.vehicles);
-                    }
+                if show_finances {
+                    Self::render_finances_popup(f, f.size(), player);
+                }
+
+                if show_vehicle_menu
+                    && let Some(vehicle_id) = selected_vehicle_id
+                {
+                    Self::render_vehicle_menu_static(f, f.size(), vehicle_id, &player.vehicles);
                 }
                 if show_vehicle_purchase_menu {
                     Self::render_vehicle_purchase_menu_static(f, f.size());
@@ -442,6 +515,16 @@ impl UI {
                 BuildAction::BuildRoad => "BUILD: Road".to_string(),
                 BuildAction::BuildBusStop => "BUILD: Bus Stop".to_string(),
                 BuildAction::BuyVehicle => "PURCHASE: Select Vehicle Type".to_string(),
+                BuildAction::RaiseLand => "TERRAFORM: Raise Land".to_string(),
+                BuildAction::LowerLand => "TERRAFORM: Lower Land".to_string(),
+                BuildAction::Demolish => "DEMOLISH: Remove Track, Road or Station".to_string(),
+                BuildAction::RailLine => "BUILD: Railway Line".to_string(),
+                BuildAction::RoadLine => "BUILD: Road Line".to_string(),
+                BuildAction::BuildSignal => "BUILD: Signal".to_string(),
+                BuildAction::BuildHarbor => "BUILD: Harbor".to_string(),
+                BuildAction::BuildCanal => "BUILD: Canal".to_string(),
+                BuildAction::BuildBuoy => "BUILD: Buoy".to_string(),
+                BuildAction::BuildAirport { size } => format!("BUILD: {:?} Airport", size),
             }
         } else if let Some((vehicle_id, waypoints)) = route_creation_mode {
             format!("ROUTE: Vehicle {} ({} waypoints) - Move cursor to station, SPACE to add, ENTER to finish", vehicle_id, waypoints.len())
@@ -461,9 +544,10 @@ impl UI {
         };
 
         let header = Paragraph::new(format!(
-            "RusTTD - {} | Money: ${} | Year: {} | {}",
+            "RusTTD - {} | Money: ${} | Loan: ${} | Year: {} | {}",
             player.name,
             player.money,
+            player.loan,
             1950 + (player.game_time / 365),
             status_text
         ))
@@ -473,6 +557,7 @@ impl UI {
         f.render_widget(header, area);
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn render_game_area_static(f: &mut Frame, area: Rect, world: &World, vehicles: &[crate::vehicle::Vehicle], cursor_x: usize, cursor_y: usize, camera_x: usize, camera_y: usize, _view_width: usize, _view_height: usize) {
         let mut lines = Vec::new();
         
@@ -507,6 +592,17 @@ impl UI {
                 BuildAction::BuildRoad => ("Building Road", "Cost: $5,000"),
                 BuildAction::BuildBusStop => ("Building Bus Stop", "Cost: $25,000"),
                 BuildAction::BuyVehicle => ("Choose vehicle type menu", "Various costs"),
+                BuildAction::RaiseLand => ("Raising Land", "Cost: $2,500 per tile per level"),
+                BuildAction::LowerLand => ("Lowering Land", "Cost: $2,500 per tile per level"),
+                BuildAction::Demolish => ("Demolishing", "Refund: half the build price"),
+                BuildAction::RailLine => ("Building Railway Line", "Cost: $10,000 per tile"),
+                BuildAction::RoadLine => ("Building Road Line", "Cost: $5,000 per tile"),
+                BuildAction::BuildSignal => ("Building Signal", "Cost: $2,000, again to turn or change"),
+                BuildAction::BuildHarbor => ("Building Harbor", "Cost: $75,000, next to water"),
+                BuildAction::BuildCanal => ("Building Canal", "Cost: $20,000"),
+                BuildAction::BuildBuoy => ("Building Buoy", "Cost: $3,000, on water"),
+                BuildAction::BuildAirport { size: crate::world::AirportSize::Small } => ("Building Small Airport", "Cost: $200,000, 2x2 level tiles"),
+                BuildAction::BuildAirport { size: crate::world::AirportSize::Large } => ("Building Large Airport", "Cost: $500,000, 3x3 level tiles"),
             };
             format!(
                 "BUILD MODE: {}\n{}\nCursor: ({}, {})\nClick to build, ESC to cancel",
@@ -522,8 +618,8 @@ impl UI {
             } else if let Some(tile) = selected_tile {
                 // Show selected tile info
                 format!(
-                    "Selected Tile: ({}, {})\nTerrain: {:?}\nContent: {}",
-                    cursor_x, cursor_y, tile.terrain, Self::format_tile_content(&tile.content)
+                    "Selected Tile: ({}, {})\nTerrain: {:?} (height {})\nContent: {}",
+                    cursor_x, cursor_y, tile.terrain, tile.height, Self::format_tile_content(&tile.content)
                 )
             } else {
                 // Show cursor position and instructions
@@ -549,36 +645,45 @@ impl UI {
         match content {
             crate::world::TileContent::Empty => "Empty".to_string(),
             crate::world::TileContent::Town(town) => {
-                format!("Town: {}\nPopulation: {}\nGrowth: {:.1}%", 
-                    town.name, town.population, town.growth_rate)
+                format!("Town: {}\nPopulation: {}\nHouses: {}\nGrowth: {:.1}%/month",
+                    town.name, town.population, town.houses.len(), town.growth_rate)
             },
             crate::world::TileContent::Industry(industry) => {
-                format!("Industry: {:?}\nProduction: {}/month\nInputs: {}\nOutputs: {}", 
+                let mut stock_info = String::new();
+                if !industry.input_stockpile.is_empty() {
+                    stock_info = format!("\nInputs waiting: {}", Self::format_cargo_waiting(&industry.input_stockpile));
+                }
+                format!("Industry: {:?}\nProduction: {}/month\nTransported last month: {}%\nInputs: {}\nOutputs: {}{}",
                     industry.industry_type, 
                     industry.production_rate,
+                    industry.last_month_transported,
                     Self::format_cargo_list(&industry.cargo_input),
-                    Self::format_cargo_list(&industry.cargo_output))
+                    Self::format_cargo_list(&industry.cargo_output),
+                    stock_info)
             },
             crate::world::TileContent::Station(station) => {
                 let mut cargo_info = String::new();
                 if !station.cargo_waiting.is_empty() {
-                    cargo_info = format!("\nWaiting cargo: {}", Self::format_cargo_waiting(&station.cargo_waiting));
+                    cargo_info = format!("\nWaiting cargo: {}", Self::format_cargo_waiting(&station.cargo_waiting.by_type()));
                 }
-                format!("Station: {}\nType: {:?}\nConnections: {}{}", 
-                    station.name, station.station_type, station.connections.len(), cargo_info)
+                let mut ratings: Vec<String> = station.ratings.iter()
+                    .map(|(cargo_type, rating)| format!("{:?} {}%", cargo_type, rating.rating as u32 * 100 / 255))
+                    .collect();
+                ratings.sort();
+                if !ratings.is_empty() {
+                    cargo_info.push_str(&format!("\nRatings: {}", ratings.join(", ")));
+                }
+                format!("Station: {}\nType: {:?}\nCatchment: {} tiles\nConnections: {}\nAccepts: {}{}", 
+                    station.name, station.station_type, station.station_type.catchment_radius(), station.connections.len(),
+                    Self::format_cargo_list(&station.acceptance), cargo_info)
             },
             crate::world::TileContent::Track(track_type) => {
-                match track_type {
-                    crate::world::TrackType::Straight { horizontal } => {
-                        format!("Railway Track ({})", if *horizontal { "Horizontal" } else { "Vertical" })
-                    },
-                    crate::world::TrackType::Curve { from_dir: _, to_dir: _ } => {
-                        "Railway Track (Curve)".to_string()
-                    },
-                    crate::world::TrackType::Junction => "Railway Junction".to_string(),
-                }
+                format!("Railway Track ({})", track_type.name())
             },
             crate::world::TileContent::Road => "Road".to_string(),
+            crate::world::TileContent::House(house) => {
+                format!("House\nResidents: {}\nTown centre: ({}, {})", house.residents, house.town.0, house.town.1)
+            },
         }
     }
 
@@ -641,12 +746,14 @@ impl UI {
             crate::vehicle::VehicleState::Loading => "Loading",
             crate::vehicle::VehicleState::Unloading => "Unloading", 
             crate::vehicle::VehicleState::Broken => "Broken",
+            crate::vehicle::VehicleState::Lost { .. } => "Lost",
+            crate::vehicle::VehicleState::Holding => "Holding",
         };
 
         let cargo_info = if vehicle.cargo.is_empty() {
             "Empty".to_string()
         } else {
-            Self::format_cargo_waiting(&vehicle.cargo)
+            Self::format_cargo_waiting(&vehicle.cargo.by_type())
         };
 
         format!(
@@ -673,6 +780,17 @@ impl UI {
             ListItem::new("3. Build Road               $5,000"),
             ListItem::new("4. Build Bus Stop           $25,000"),
             ListItem::new("5. Buy Vehicle              $75,000"),
+            ListItem::new("6. Raise Land               $2,500/level"),
+            ListItem::new("7. Lower Land               $2,500/level"),
+            ListItem::new("8. Demolish                 50% refund"),
+            ListItem::new("9. Railway Line (start/end) $10,000/tile"),
+            ListItem::new("0. Road Line (start/end)    $5,000/tile"),
+            ListItem::new("G. Signal (again to cycle)  $2,000"),
+            ListItem::new("H. Harbor (by water)        $75,000"),
+            ListItem::new("C. Canal                    $20,000"),
+            ListItem::new("O. Buoy (on water)          $3,000"),
+            ListItem::new("I. Small Airport (2x2)      $200,000"),
+            ListItem::new("J. Large Airport (3x3)      $500,000"),
             ListItem::new(""),
             ListItem::new("ESC. Cancel"),
         ];
@@ -697,9 +815,14 @@ impl UI {
             Line::from(""),
             Line::from("🚂 Game Actions:"),
             Line::from("   B                    Open build menu"),
-            Line::from("   1-5 (in menu)        Select build option"),
+            Line::from("   0-9, G-J, C, O       Select build option"),
             Line::from("   P                    Pause game (planned)"),
             Line::from(""),
+            Line::from("🏦 Finances:"),
+            Line::from("   L                    Borrow from the bank"),
+            Line::from("   R                    Repay part of the loan"),
+            Line::from("   F                    Show/hide finances"),
+            Line::from(""),
             Line::from("💾 Save/Load:"),
             Line::from("   F5                   Quick save"),
             Line::from("   F9                   Quick load"),
@@ -722,13 +845,79 @@ impl UI {
         f.render_widget(controls, popup_area);
     }
 
+    fn render_finances_popup(f: &mut Frame, area: Rect, player: &Player) {
+        let popup_area = Self::centered_rect_static(70, 70, area);
+        f.render_widget(Clear, popup_area);
+
+        let empty = crate::finance::Statement::default();
+        let quarters = player.ledger.quarterly();
+        let years = player.ledger.yearly();
+        let columns = [
+            ("This month", &player.ledger.current_month),
+            ("Last month", player.ledger.months.last().unwrap_or(&empty)),
+            ("Last quarter", quarters.last().unwrap_or(&empty)),
+            ("Last year", years.last().unwrap_or(&empty)),
+        ];
+
+        let row = |label: &str, value: &dyn Fn(&crate::finance::Statement) -> i64| {
+            let mut text = format!("{:<22}", label);
+            for (_, statement) in &columns {
+                text.push_str(&format!("{:>14}", value(statement)));
+            }
+            Line::from(text)
+        };
+
+        let mut header = format!("{:<22}", "");
+        for (title, _) in &columns {
+            header.push_str(&format!("{:>14}", title));
+        }
+
+        let mut cargo_types: Vec<_> = columns.iter()
+            .flat_map(|(_, statement)| statement.income.keys().copied())
+            .collect();
+        cargo_types.sort_by_key(|cargo_type| format!("{:?}", cargo_type));
+        cargo_types.dedup();
+
+        let mut lines = vec![
+            Line::from(format!("Money: ${}   Loan: ${}", player.money, player.loan)),
+            Line::from(""),
+            Line::from(header),
+            Line::from("Income:"),
+        ];
+        for cargo_type in cargo_types {
+            lines.push(row(&format!("  {:?}", cargo_type), &|statement| {
+                statement.income.get(&cargo_type).copied().unwrap_or(0)
+            }));
+        }
+        lines.push(row("  Total income", &|statement| statement.total_income()));
+        lines.push(Line::from("Expenses:"));
+        lines.push(row("  Construction", &|statement| statement.construction));
+        lines.push(row("  New vehicles", &|statement| statement.vehicle_purchases));
+        lines.push(row("  Running costs", &|statement| statement.running_costs));
+        lines.push(row("  Property maintenance", &|statement| statement.property_maintenance));
+        lines.push(row("  Loan interest", &|statement| statement.loan_interest));
+        lines.push(row("  Total expenses", &|statement| statement.total_expenses()));
+        lines.push(Line::from(""));
+        lines.push(row("Net profit", &|statement| statement.net_profit()));
+
+        let finances = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(" 💰 Finances "))
+            .style(Style::default().fg(Color::White));
+
+        f.render_widget(finances, popup_area);
+    }
+
     fn get_tile_style_static(world: &World, x: usize, y: usize) -> Style {
         if let Some(tile) = world.get_tile(x, y) {
             match &tile.content {
-                crate::world::TileContent::Town(_) => Style::default().fg(Color::Blue),
+                crate::world::TileContent::Town(_) | crate::world::TileContent::House(_) => Style::default().fg(Color::Blue),
                 crate::world::TileContent::Industry(_) => Style::default().fg(Color::Red),
                 crate::world::TileContent::Station(_) => Style::default().fg(Color::Green),
-                crate::world::TileContent::Track(_) => Style::default().fg(Color::Yellow),
+                crate::world::TileContent::Track(_) => match world.signal_state((x, y)) {
+                    Some(crate::world::SignalState::Green) => Style::default().fg(Color::Green).add_modifier(ratatui::style::Modifier::BOLD),
+                    Some(crate::world::SignalState::Red) => Style::default().fg(Color::Red).add_modifier(ratatui::style::Modifier::BOLD),
+                    None => Style::default().fg(Color::Yellow),
+                },
                 crate::world::TileContent::Road => Style::default().fg(Color::Gray),
                 _ => match tile.terrain {
                     crate::world::TerrainType::Grass => Style::default().fg(Color::Green),
@@ -774,7 +963,7 @@ impl UI {
                 .title(format!("Vehicle {} Orders", vehicle.id))
                 .border_style(Style::default().fg(Color::Yellow));
 
-            let menu_items = vec![
+            let menu_items = [
                 "1. Go to Location",
                 "2. Create Route",
                 "3. Start Route",
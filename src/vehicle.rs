@@ -1,10 +1,16 @@
-use std::collections::{HashMap, VecDeque, HashSet};
-use crate::world::{CargoType, World, TileContent};
+use crate::cargo::CargoList;
+use crate::world::{CargoType, CompanyId, Direction, StationType, World, TileContent, VehicleId};
 use crate::economy::Economy;
+use crate::network::{self, Hop};
+use crate::pathfinding::{self, Heuristic, PathKind, Route};
 
 // Configuration: Set to false to disable vehicle breakdowns
 const BREAKDOWNS_ENABLED: bool = false;
 
+/// A lost vehicle waits at least this many days between searches for a
+/// route, however often the network changes.
+const LOST_RETRY_DAYS: u32 = 7;
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub enum VehicleType {
     Train { engine: TrainEngine, cars: Vec<TrainCar> },
@@ -46,6 +52,16 @@ pub enum PlaneType {
     LargePlane { capacity: u32, range: u32 },
 }
 
+impl PlaneType {
+    /// Furthest the plane can fly between stops, in tiles.
+    pub fn range(&self) -> u32 {
+        match self {
+            PlaneType::SmallPlane { range, .. } => *range,
+            PlaneType::LargePlane { range, .. } => *range,
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub enum VehicleState {
     Idle,
@@ -53,19 +69,30 @@ pub enum VehicleState {
     Loading,
     Unloading,
     Broken,
+    /// No way to the next stop; tries again no sooner than `retry_at` and
+    /// only once the network's revision has moved on from `revision`
+    Lost { revision: u64, retry_at: u32 },
+    /// Circling until a runway is free at the airport ahead
+    Holding,
 }
 
 pub struct Vehicle {
     pub id: u32,
+    /// Company running the vehicle; decides whose infrastructure it may use
+    pub owner: CompanyId,
     pub vehicle_type: VehicleType,
     pub x: usize,
     pub y: usize,
     pub state: VehicleState,
-    pub cargo: HashMap<CargoType, u32>,
+    pub cargo: CargoList,
     pub route: Vec<(usize, usize)>,
     pub route_index: usize,
     pub current_path: Vec<(usize, usize)>, // Step-by-step path to next station
     pub path_index: usize, // Current position in the path
+    /// Later runs of the route, expanded into `current_path` one at a time
+    pub pending_hops: Vec<Hop>,
+    /// Network version the route was last checked against
+    pub path_version: u64,
     pub age: u32,
     pub reliability: u8,
     pub speed: u32,
@@ -73,23 +100,28 @@ pub struct Vehicle {
     pub profit: i64,
     pub on_time_deliveries: u32,
     pub total_deliveries: u32,
+    /// Earned by deliveries but not yet paid to the owner, per cargo
+    pub uncollected_income: Vec<(CargoType, i64)>,
 }
 
 impl Vehicle {
-    pub fn new(id: u32, vehicle_type: VehicleType, x: usize, y: usize) -> Self {
+    pub fn new(id: u32, owner: CompanyId, vehicle_type: VehicleType, x: usize, y: usize) -> Self {
         let (speed, reliability) = Self::get_vehicle_stats(&vehicle_type);
         
         Self {
             id,
+            owner,
             vehicle_type,
             x,
             y,
             state: VehicleState::Idle,
-            cargo: HashMap::new(),
+            cargo: CargoList::new(),
             route: Vec::new(),
             route_index: 0,
             current_path: Vec::new(),
             path_index: 0,
+            pending_hops: Vec::new(),
+            path_version: 0,
             age: 0,
             reliability,
             speed,
@@ -97,48 +129,143 @@ impl Vehicle {
             profit: 0,
             on_time_deliveries: 0,
             total_deliveries: 0,
+            uncollected_income: Vec::new(),
         }
     }
 
     pub fn update(&mut self, world: &mut World, economy: &mut Economy) {
         self.age += 1;
+        self.cargo.age();
         
-        if self.age > 0 && self.age % 365 == 0 {
+        if self.age > 0 && self.age.is_multiple_of(365) {
             self.reliability = self.reliability.saturating_sub(5);
         }
 
-        if BREAKDOWNS_ENABLED && self.age - self.last_service > 180 {
-            if rand::random::<u8>() > self.reliability {
-                self.state = VehicleState::Broken;
-                return;
+        if BREAKDOWNS_ENABLED && self.age - self.last_service > 180
+            && rand::random::<u8>() > self.reliability
+        {
+            if matches!(self.state, VehicleState::Moving { .. }) {
+                world.release_vehicle((self.owner, self.id), Some((self.x, self.y)));
             }
+            self.state = VehicleState::Broken;
+            return;
+        }
+
+        let train = self.train_id();
+        let road_vehicle = matches!(self.vehicle_type, VehicleType::Road { .. }).then_some((self.owner, self.id));
+        let aircraft = matches!(self.vehicle_type, VehicleType::Aircraft { .. }).then_some((self.owner, self.id));
+        if let Some(train) = train {
+            // Trains always hold the tile they stand on
+            world.hold_rail_tile((self.x, self.y), train);
+        }
+
+        let kind = self.path_kind();
+        if train.is_some() && matches!(self.state, VehicleState::Moving { .. }) {
+            self.expand_past_signal(world);
         }
 
+        let mut replan = false;
         match &mut self.state {
             VehicleState::Idle => {
+                if let Some(train) = train {
+                    world.release_rail(train, (self.x, self.y));
+                }
                 if !self.route.is_empty() {
                     self.start_moving_to_next_station(world);
+                } else if let Some(plane) = aircraft {
+                    // Without orders it won't take off and free the runway
+                    world.release_vehicle(plane, None);
                 }
             }
             VehicleState::Moving { from: _, to, progress } => {
-                *progress += self.speed as f32 / 1000.0;
+                if let Some(train) = train
+                    && world.rail_reservations.get(to) != Some(&train)
+                    && !world.reserve_rail_ahead(train, (self.x, self.y), &self.current_path[self.path_index..])
+                {
+                    // Held at a red signal or behind another train
+                    return;
+                }
+                let lane = Direction::between((self.x, self.y), *to).map(|heading| (*to, heading));
+                if let (Some(vehicle), Some((pos, heading))) = (road_vehicle, lane)
+                    && !world.reserve_road(pos, heading, vehicle)
+                {
+                    // Queueing behind the vehicle in front, or waiting
+                    // for a train to clear the crossing
+                    return;
+                }
+                if let Some(plane) = aircraft {
+                    let landing = self.path_index + 1 == self.current_path.len() && self.pending_hops.is_empty();
+                    if landing && !world.claim_runway(*to, plane) {
+                        self.state = VehicleState::Holding;
+                        return;
+                    }
+                }
+
+                let mut step = self.speed as f32 / 1000.0;
+                if matches!(self.vehicle_type, VehicleType::Train { .. }) {
+                    // Trains lose half their speed for every level they climb
+                    step /= 1.0 + world.climb_between((self.x, self.y), *to) as f32;
+                }
+                if aircraft.is_some() && self.x != to.0 && self.y != to.1 {
+                    // Flying diagonally covers more ground per tile
+                    step /= std::f32::consts::SQRT_2;
+                }
+                *progress += step;
                 
                 if *progress >= 1.0 {
+                    if let Some(train) = train {
+                        world.release_rail_tile((self.x, self.y), train);
+                        world.train_arrives(*to);
+                    }
+                    if let Some(vehicle) = road_vehicle {
+                        world.release_road(vehicle, lane);
+                    }
+                    if let Some(plane) = aircraft {
+                        // Off the runway once in the air
+                        world.release_runway((self.x, self.y), plane);
+                    }
+
                     // Move to the next tile
                     self.x = to.0;
                     self.y = to.1;
                     
                     // Check if we've reached the final destination
-                    if self.path_index >= self.current_path.len() - 1 {
-                        // Reached the station
-                        self.state = VehicleState::Loading;
+                    let leg_done = self.path_index >= self.current_path.len() - 1;
+                    if leg_done && self.pending_hops.is_empty() {
+                        if let Some(train) = train {
+                            world.release_rail(train, (self.x, self.y));
+                        }
                         self.current_path.clear();
                         self.path_index = 0;
+                        if world.buoys.contains(&(self.x, self.y)) {
+                            // Buoys are only passed on the way
+                            self.state = VehicleState::Idle;
+                            self.route_index = (self.route_index + 1) % self.route.len();
+                        } else {
+                            // Reached the station: drop off cargo before loading
+                            self.state = VehicleState::Unloading;
+                        }
+                    } else if aircraft.is_none() && !Self::route_ahead_is_current(world, kind, &self.current_path[self.path_index..], &self.pending_hops, self.path_version) {
+                        // Something on the way was built over or torn up
+                        replan = true;
                     } else {
-                        // Move to next tile in path
-                        self.path_index += 1;
-                        *progress = 0.0;
-                        *to = self.current_path[self.path_index];
+                        self.path_version = world.network_version;
+                        if leg_done {
+                            // On to the next run of the route
+                            let hop = self.pending_hops.remove(0);
+                            self.current_path = vec![(self.x, self.y)];
+                            self.path_index = 0;
+                            match kind.and_then(|kind| network::hop_tiles(world, kind, hop)) {
+                                Some(tiles) => self.current_path.extend(tiles),
+                                None => replan = true,
+                            }
+                        }
+                        if !replan {
+                            // Move to next tile in path
+                            self.path_index += 1;
+                            *progress = 0.0;
+                            *to = self.current_path[self.path_index];
+                        }
                     }
                 }
             }
@@ -149,7 +276,7 @@ impl Vehicle {
             }
             VehicleState::Unloading => {
                 self.unload_cargo_at_station(world, economy);
-                self.state = VehicleState::Idle;
+                self.state = VehicleState::Loading;
             }
             VehicleState::Broken => {
                 if rand::random::<u8>() < 10 {
@@ -158,12 +285,124 @@ impl Vehicle {
                     self.reliability = (self.reliability + 20).min(100);
                 }
             }
+            VehicleState::Lost { revision, retry_at } => {
+                let (revision, retry_at) = (*revision, *retry_at);
+                if world.ticks >= retry_at && self.network_revision(world) != revision {
+                    self.start_moving_to_next_station(world);
+                }
+            }
+            VehicleState::Holding => {
+                let airport = self.current_path.last().copied();
+                if let (Some(plane), Some(airport)) = (aircraft, airport)
+                    && world.claim_runway(airport, plane)
+                {
+                    self.state = VehicleState::Moving {
+                        from: (self.x, self.y),
+                        to: self.current_path[self.path_index],
+                        progress: 0.0,
+                    };
+                }
+            }
+        }
+
+        if replan {
+            if let Some(train) = train {
+                world.release_rail(train, (self.x, self.y));
+            }
+            self.current_path.clear();
+            self.path_index = 0;
+            self.pending_hops.clear();
+            self.start_moving_to_next_station(world);
+        }
+    }
+
+    /// Whether nothing left on the vehicle's route has changed since
+    /// `version`.
+    fn route_ahead_is_current(
+        world: &mut World,
+        kind: Option<PathKind>,
+        tiles: &[(usize, usize)],
+        hops: &[Hop],
+        version: u64,
+    ) -> bool {
+        world.refresh_network_graphs();
+        world.path_is_current(tiles, version)
+            && kind.and_then(|kind| world.network_graph(kind)).is_none_or(|graph| graph.hops_are_current(hops, version))
+    }
+
+    /// A train about to pass a signal needs to know its path up to the
+    /// next one, so runs are expanded past the end of the current one.
+    fn expand_past_signal(&mut self, world: &World) {
+        let Some(&next) = self.current_path.get(self.path_index) else {
+            return;
+        };
+        if world.signal_facing((self.x, self.y), next).is_none() {
+            return;
+        }
+        while !self.pending_hops.is_empty()
+            && !self.current_path[self.path_index + 1..].iter().any(|pos| world.signals.contains_key(pos))
+        {
+            let hop = self.pending_hops.remove(0);
+            match network::hop_tiles(world, PathKind::Rail, hop) {
+                Some(tiles) => self.current_path.extend(tiles),
+                None => {
+                    // Re-planned on arrival at the end of the current run
+                    self.pending_hops.insert(0, hop);
+                    return;
+                }
+            }
         }
     }
 
-    pub fn assign_route(&mut self, stations: Vec<(usize, usize)>) {
+    /// Gives the vehicle new orders, unless `check_orders` refuses them. A
+    /// lost vehicle looks for a way to its new first stop straight away.
+    pub fn assign_route(&mut self, world: &World, stations: Vec<(usize, usize)>) -> Result<(), String> {
+        self.check_orders(world, &stations)?;
         self.route = stations;
         self.route_index = 0;
+        if matches!(self.state, VehicleState::Lost { .. }) {
+            self.state = VehicleState::Idle;
+            self.current_path.clear();
+            self.path_index = 0;
+            self.pending_hops.clear();
+        }
+        Ok(())
+    }
+
+    /// Drops a station that no longer exists from the vehicle's orders. A
+    /// vehicle on its way there stops, giving up whatever it had claimed
+    /// ahead, and heads for its next order instead.
+    pub fn remove_stop(&mut self, world: &mut World, station: (usize, usize)) {
+        if !self.route.contains(&station) {
+            return;
+        }
+        self.route.retain(|&stop| stop != station);
+        if self.route_index >= self.route.len() {
+            self.route_index = 0;
+        }
+        if self.route.is_empty() || self.current_path.last() == Some(&station) {
+            self.stop(world);
+        }
+    }
+
+    /// Halts the vehicle where it stands, giving up the track, lane or
+    /// runway it had claimed ahead and the path it was following.
+    pub fn stop(&mut self, world: &mut World) {
+        world.release_vehicle((self.owner, self.id), Some((self.x, self.y)));
+        self.state = VehicleState::Idle;
+        self.current_path.clear();
+        self.path_index = 0;
+        self.pending_hops.clear();
+    }
+
+    /// How the rail network knows this vehicle, if it is a train.
+    pub fn train_id(&self) -> Option<VehicleId> {
+        matches!(self.vehicle_type, VehicleType::Train { .. }).then_some((self.owner, self.id))
+    }
+
+    /// Whether the vehicle stands on or is moving onto the tile.
+    pub fn occupies(&self, pos: (usize, usize)) -> bool {
+        (self.x, self.y) == pos || matches!(self.state, VehicleState::Moving { to, .. } if to == pos)
     }
 
     pub fn get_capacity(&self) -> u32 {
@@ -242,18 +481,9 @@ impl Vehicle {
         (purchase_cost as f32 * (1.0 - depreciation)) as i64
     }
 
-    pub fn has_delivered_cargo(&self) -> bool {
-        self.total_deliveries > 0
-    }
-
-    pub fn calculate_delivery_profit(&self) -> i64 {
-        let base_profit = self.cargo.values().sum::<u32>() as i64 * 10;
-        let distance_bonus = if self.route.len() > 1 { 
-            self.calculate_route_distance() as i64 * 5 
-        } else { 
-            0 
-        };
-        base_profit + distance_bonus
+    /// Hands over what deliveries have earned since the last call.
+    pub fn take_delivery_income(&mut self) -> Vec<(CargoType, i64)> {
+        std::mem::take(&mut self.uncollected_income)
     }
 
     pub fn is_on_time(&self) -> bool {
@@ -284,10 +514,25 @@ impl Vehicle {
         }
     }
 
-    fn start_moving_to_next_station(&mut self, world: &World) {
+    fn start_moving_to_next_station(&mut self, world: &mut World) {
         if let Some(&next_station) = self.route.get(self.route_index) {
             // Find path to the next station
-            if let Some(path) = self.find_path_to_station(world, next_station) {
+            self.pending_hops.clear();
+            let path = match self.find_path_to_station(world, next_station) {
+                Some(Route::Tiles(path)) => Some(path),
+                Some(Route::Hops(mut hops)) if !hops.is_empty() => {
+                    // Only the first run is expanded into tiles for now
+                    let first = hops.remove(0);
+                    self.pending_hops = hops;
+                    let tiles = self.path_kind().and_then(|kind| network::hop_tiles(world, kind, first));
+                    tiles.map(|tiles| std::iter::once((self.x, self.y)).chain(tiles).collect())
+                }
+                Some(Route::Hops(_)) => Some(Vec::new()),
+                None => None,
+            };
+            self.path_version = world.network_version;
+
+            if let Some(path) = path {
                 if path.len() > 1 {
                     // Set up step-by-step movement
                     self.current_path = path;
@@ -303,74 +548,100 @@ impl Vehicle {
                     self.state = VehicleState::Loading;
                 }
             } else {
-                // No path found - can't move
-                self.state = VehicleState::Idle;
+                // No path found - wait for the network to change
+                self.state = VehicleState::Lost {
+                    revision: self.network_revision(world),
+                    retry_at: world.ticks + LOST_RETRY_DAYS,
+                };
             }
         }
     }
 
     fn load_cargo_at_station(&mut self, world: &mut World) {
-        let available_capacity = self.get_capacity() - self.cargo.values().sum::<u32>();
+        let available_capacity = self.get_capacity() - self.cargo.total();
         
         if available_capacity == 0 {
             return; // Vehicle is full
         }
         
-        // Get mutable access to the tile
-        if let Some(tile) = world.tiles.get_mut(self.y).and_then(|row| row.get_mut(self.x)) {
-            if let crate::world::TileContent::Station(ref mut station) = tile.content {
-                let mut remaining_capacity = available_capacity;
-                let mut cargo_to_remove = Vec::new();
-                
-                // Determine what cargo to load
-                for (cargo_type, &amount) in &station.cargo_waiting {
-                    if amount > 0 && remaining_capacity > 0 {
-                        let to_load = amount.min(remaining_capacity);
-                        *self.cargo.entry(cargo_type.clone()).or_insert(0) += to_load;
-                        cargo_to_remove.push((cargo_type.clone(), to_load));
-                        remaining_capacity -= to_load;
-                    }
-                }
-                
-                // Remove loaded cargo from station
-                for (cargo_type, amount) in cargo_to_remove {
-                    if let Some(waiting_amount) = station.cargo_waiting.get_mut(&cargo_type) {
-                        *waiting_amount = waiting_amount.saturating_sub(amount);
-                        if *waiting_amount == 0 {
-                            station.cargo_waiting.remove(&cargo_type);
-                        }
-                    }
-                }
+        let mut loaded = Vec::new();
+        if let Some(tile) = world.tiles.get_mut(self.y).and_then(|row| row.get_mut(self.x))
+            && let TileContent::Station(ref mut station) = tile.content
+        {
+            if !self.serves(&station.station_type) {
+                return;
+            }
+            // Oldest cargo waiting goes first
+            for packet in station.cargo_waiting.take(available_capacity) {
+                station.record_pickup(packet.cargo_type, self.speed, self.age);
+                loaded.push((packet.cargo_type, packet.amount));
+                self.cargo.push(packet);
+            }
+        }
+
+        // Passengers picked up count as service for the town they came from
+        for (cargo_type, amount) in loaded {
+            if matches!(cargo_type, CargoType::Passengers | CargoType::Mail) {
+                world.record_town_service((self.x, self.y), cargo_type, amount);
             }
         }
     }
 
-    fn unload_cargo_at_station(&mut self, world: &mut World, _economy: &mut Economy) {
-        let delivered_cargo: Vec<_> = self.cargo.drain().collect();
+    fn unload_cargo_at_station(&mut self, world: &mut World, economy: &mut Economy) {
+        let here = (self.x, self.y);
+        let Some(TileContent::Station(station)) = world.get_tile(self.x, self.y).map(|tile| &tile.content) else {
+            return;
+        };
+        if !self.serves(&station.station_type) {
+            return;
+        }
+        let radius = station.station_type.catchment_radius();
+
+        // Cargo the station doesn't accept, or that is bound elsewhere, stays on board
+        let delivered_cargo = self.cargo.take_where(|packet| {
+            station.acceptance.contains(&packet.cargo_type)
+                && packet.destination.is_none_or(|destination| destination == here)
+        });
         
         if !delivered_cargo.is_empty() {
-            // Try to deliver cargo to nearby towns
             let mut total_delivered = 0;
-            
-            // Check nearby tiles for towns that might want this cargo
-            for dx in -2i32..=2i32 {
-                for dy in -2i32..=2i32 {
-                    let check_x = (self.x as i32 + dx).max(0).min(world.width as i32 - 1) as usize;
-                    let check_y = (self.y as i32 + dy).max(0).min(world.height as i32 - 1) as usize;
-                    
-                    if let Some(tile) = world.tiles.get_mut(check_y).and_then(|row| row.get_mut(check_x)) {
-                        if let crate::world::TileContent::Town(ref mut town) = tile.content {
-                            // Deliver all cargo types to town (simplified)
-                            for (cargo_type, amount) in &delivered_cargo {
-                                *town.cargo_demand.entry(cargo_type.clone()).or_insert(0) = 
-                                    town.cargo_demand.get(cargo_type).unwrap_or(&0).saturating_sub(*amount);
-                                total_delivered += amount;
-                            }
-                        }
+            let mut income = 0;
+            let mut earnings = Vec::new();
+
+            for mut packet in delivered_cargo {
+                // Industries that use the cargo take it first
+                let mut delivered = world.deliver_to_industry(here, packet.cargo_type, packet.amount, radius);
+
+                // Whatever is left goes to a town near the station
+                let leftover = packet.amount - delivered;
+                if leftover > 0
+                    && let Some((town_x, town_y)) = world.town_near(self.x, self.y, radius)
+                {
+                    if let TileContent::Town(ref mut town) = world.tiles[town_y][town_x].content {
+                        let demand = town.cargo_demand.entry(packet.cargo_type).or_insert(0);
+                        *demand = demand.saturating_sub(leftover);
                     }
+                    world.record_town_service(here, packet.cargo_type, leftover);
+                    delivered += leftover;
+                }
+
+                let payment = economy.calculate_delivery_payment(
+                    &packet.cargo_type,
+                    delivered,
+                    packet.distance_to(here) as f32,
+                    packet.age,
+                );
+                income += payment;
+                earnings.push((packet.cargo_type, payment));
+                total_delivered += delivered;
+
+                // The catchment changed since acceptance was last worked out
+                if delivered < packet.amount {
+                    packet.amount -= delivered;
+                    self.cargo.push(packet);
                 }
             }
-            
+
             if total_delivered > 0 {
                 self.total_deliveries += 1;
                 
@@ -379,174 +650,295 @@ impl Vehicle {
                     self.on_time_deliveries += 1;
                 }
                 
-                let delivery_profit = total_delivered as i64 * 50; // $50 per unit delivered
-                self.profit += delivery_profit;
+                self.profit += income;
+                self.uncollected_income.extend(earnings);
             }
         }
     }
 
-    fn calculate_route_distance(&self) -> f32 {
-        if self.route.len() < 2 {
-            return 0.0;
-        }
-        
-        let mut total_distance = 0.0;
-        for i in 0..self.route.len() - 1 {
-            let (x1, y1) = self.route[i];
-            let (x2, y2) = self.route[i + 1];
-            let distance = ((x2 as f32 - x1 as f32).powi(2) + (y2 as f32 - y1 as f32).powi(2)).sqrt();
-            total_distance += distance;
+    /// Revision of the network the vehicle plans its routes over. Aircraft
+    /// need no network and go by every change.
+    fn network_revision(&self, world: &mut World) -> u64 {
+        match self.path_kind() {
+            Some(kind) => world.network_revision(kind),
+            None => world.network_version,
         }
-        total_distance
     }
 
-    // Pathfinding methods
-    fn find_path_to_station(&self, world: &World, target: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+    /// The network the vehicle routes over, if it is tied to one.
+    fn path_kind(&self) -> Option<PathKind> {
         match self.vehicle_type {
-            VehicleType::Train { .. } => self.find_train_path(world, (self.x, self.y), target),
-            VehicleType::Road { .. } => self.find_road_path(world, (self.x, self.y), target),
-            _ => {
-                // For ships and planes, use direct path for now
-                Some(vec![target])
-            }
+            VehicleType::Train { .. } => Some(PathKind::Rail),
+            VehicleType::Road { .. } => Some(PathKind::Road),
+            VehicleType::Ship { .. } => Some(PathKind::Water),
+            VehicleType::Aircraft { .. } => None,
         }
     }
 
-    fn find_train_path(&self, world: &World, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
-        // A* pathfinding for trains using only tracks and stations
-        let mut open_set = VecDeque::new();
-        let mut came_from = HashMap::new();
-        let mut g_score = HashMap::new();
-        let mut visited = HashSet::new();
-
-        open_set.push_back(start);
-        g_score.insert(start, 0);
-
-        while let Some(current) = open_set.pop_front() {
-            if current == goal {
-                // Reconstruct path
-                let mut path = Vec::new();
-                let mut current_pos = current;
-                
-                while let Some(&previous) = came_from.get(&current_pos) {
-                    path.push(current_pos);
-                    current_pos = previous;
-                }
-                path.push(start);
-                path.reverse();
-                return Some(path);
-            }
+    /// Whether the vehicle can load and unload at this kind of station.
+    pub fn serves(&self, station_type: &StationType) -> bool {
+        match self.vehicle_type {
+            VehicleType::Ship { .. } => matches!(station_type, StationType::Harbor),
+            VehicleType::Aircraft { .. } => matches!(station_type, StationType::Airport),
+            _ => true,
+        }
+    }
 
-            if visited.contains(&current) {
-                continue;
+    /// Why the vehicle can't be sent round these stops, if it can't. An
+    /// aircraft only flies between airports, and no further than its range
+    /// on any leg, counting the one from where it is now.
+    pub fn check_orders(&self, world: &World, stops: &[(usize, usize)]) -> Result<(), String> {
+        let VehicleType::Aircraft { plane_type } = &self.vehicle_type else {
+            return Ok(());
+        };
+        for &(x, y) in stops {
+            let airport = match world.get_tile(x, y).map(|tile| &tile.content) {
+                Some(TileContent::Station(station)) => matches!(station.station_type, StationType::Airport),
+                _ => false,
+            };
+            if !airport {
+                return Err(format!("Aircraft can only fly to airports, ({}, {}) is not one", x, y));
             }
-            visited.insert(current);
-
-            // Check all adjacent tiles
-            let neighbors = self.get_train_neighbors(world, current);
-            for neighbor in neighbors {
-                if visited.contains(&neighbor) {
-                    continue;
-                }
+        }
 
-                let tentative_g_score = g_score.get(&current).unwrap_or(&u32::MAX) + 1;
-                
-                if tentative_g_score < *g_score.get(&neighbor).unwrap_or(&u32::MAX) {
-                    came_from.insert(neighbor, current);
-                    g_score.insert(neighbor, tentative_g_score);
-                    
-                    if !open_set.contains(&neighbor) {
-                        open_set.push_back(neighbor);
-                    }
-                }
+        let legs = std::iter::once((self.x, self.y)).chain(stops.iter().copied())
+            .zip(stops.iter().copied().chain(stops.first().copied()));
+        for (from, to) in legs {
+            let distance = (from.0.abs_diff(to.0) as f64).hypot(from.1.abs_diff(to.1) as f64);
+            if distance > plane_type.range() as f64 {
+                return Err(format!(
+                    "({}, {}) to ({}, {}) is {:.0} tiles, beyond the aircraft's range of {}",
+                    from.0, from.1, to.0, to.1, distance, plane_type.range()
+                ));
             }
         }
+        Ok(())
+    }
 
-        None // No path found
+    fn find_path_to_station(&self, world: &mut World, target: (usize, usize)) -> Option<Route> {
+        let start = (self.x, self.y);
+        let Some(kind) = self.path_kind() else {
+            // Aircraft fly straight over whatever is below
+            return Some(Route::Tiles(pathfinding::flight_path(start, target)));
+        };
+        pathfinding::cached_path(world, kind, self.owner, start, target, |world| {
+            let heuristic = match kind {
+                PathKind::Rail => Heuristic::Chebyshev,
+                PathKind::Road | PathKind::Water => Heuristic::Manhattan,
+            };
+            // Run by run over the network, tile by tile where the journey
+            // leaves it
+            let hops = world.network_graph(kind)
+                .and_then(|graph| graph.find_route(world, start, target, self.owner, heuristic));
+            hops.map(Route::Hops).or_else(|| {
+                let path = match kind {
+                    PathKind::Rail => self.find_train_path(world, start, target),
+                    PathKind::Road => self.find_road_path(world, start, target),
+                    PathKind::Water => self.find_ship_path(world, start, target),
+                };
+                path.map(Route::Tiles)
+            })
+        })
+    }
+
+    fn find_train_path(&self, world: &World, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        // Trains may only use tracks and stations
+        pathfinding::find_path(world, start, goal, &world.path_costs, Heuristic::Chebyshev, |pos| {
+            pathfinding::rail_neighbors(world, pos, self.owner)
+        })
     }
 
     fn find_road_path(&self, world: &World, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
-        // Simple pathfinding for road vehicles - can use roads and empty terrain
-        let mut open_set = VecDeque::new();
-        let mut came_from = HashMap::new();
-        let mut visited = HashSet::new();
-
-        open_set.push_back(start);
-
-        while let Some(current) = open_set.pop_front() {
-            if current == goal {
-                // Reconstruct path
-                let mut path = Vec::new();
-                let mut current_pos = current;
-                
-                while let Some(&previous) = came_from.get(&current_pos) {
-                    path.push(current_pos);
-                    current_pos = previous;
-                }
-                path.push(start);
-                path.reverse();
-                return Some(path);
-            }
+        // Road vehicles can use roads and empty terrain
+        pathfinding::find_path(world, start, goal, &world.path_costs, Heuristic::Manhattan, |pos| {
+            pathfinding::road_neighbors(world, pos, self.owner)
+        })
+    }
 
-            if visited.contains(&current) {
-                continue;
-            }
-            visited.insert(current);
+    fn find_ship_path(&self, world: &World, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        // Ships keep to water, canals and harbors
+        pathfinding::find_path(world, start, goal, &world.path_costs, Heuristic::Manhattan, |pos| {
+            pathfinding::water_neighbors(world, pos, self.owner)
+        })
+    }
+}
 
-            let neighbors = self.get_road_neighbors(world, current);
-            for neighbor in neighbors {
-                if !visited.contains(&neighbor) && !came_from.contains_key(&neighbor) {
-                    came_from.insert(neighbor, current);
-                    open_set.push_back(neighbor);
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{AirportSize, TerrainType};
+
+    fn plane_at(x: usize, y: usize, range: u32) -> Vehicle {
+        let plane_type = PlaneType::SmallPlane { capacity: 30, range };
+        Vehicle::new(0, 0, VehicleType::Aircraft { plane_type }, x, y)
+    }
+
+    /// Airports with terminals at (2, 2) and (2, 42), and a bus stop at (6, 2).
+    fn airfields() -> World {
+        let mut world = World::flat(48, 48);
+        world.build_airport(2, 2, AirportSize::Small, 0).unwrap();
+        world.build_airport(2, 42, AirportSize::Small, 0).unwrap();
+        world.build_station(6, 2, StationType::Road, 0).unwrap();
+        world
+    }
+
+    #[test]
+    fn aircraft_only_fly_between_airports_in_range() {
+        let world = airfields();
+        assert!(plane_at(2, 2, 50).check_orders(&world, &[(2, 2), (2, 42)]).is_ok());
+        assert!(plane_at(2, 2, 50).check_orders(&world, &[(2, 2), (6, 2)]).is_err());
+        assert!(plane_at(2, 2, 30).check_orders(&world, &[(2, 2), (2, 42)]).is_err());
+        // The first leg starts from wherever the aircraft is now
+        assert!(plane_at(40, 40, 30).check_orders(&world, &[(2, 42)]).is_err());
+    }
+
+    #[test]
+    fn refused_orders_leave_the_old_ones_in_place() {
+        let world = airfields();
+        let mut plane = plane_at(2, 2, 50);
+        plane.assign_route(&world, vec![(2, 2), (2, 42)]).unwrap();
+        assert!(plane.assign_route(&world, vec![(2, 2), (6, 2)]).is_err());
+        assert_eq!(plane.route, vec![(2, 2), (2, 42)]);
+
+        // Other vehicles go anywhere
+        let bus = VehicleType::Road { truck_type: TruckType::Bus { capacity: 40 } };
+        assert!(Vehicle::new(1, 0, bus, 6, 2).assign_route(&world, vec![(6, 2), (2, 2)]).is_ok());
+    }
+
+    /// Flies the plane until it has come down at its first stop.
+    fn land(plane: &mut Vehicle, world: &mut World) {
+        let mut economy = Economy::new();
+        let airport = plane.route[0];
+        for _ in 0..1000 {
+            plane.update(world, &mut economy);
+            if (plane.x, plane.y) == airport {
+                return;
             }
         }
-
-        None // No path found
+        panic!("never landed at {:?}, {:?}", airport, plane.state);
     }
 
-    fn get_train_neighbors(&self, world: &World, pos: (usize, usize)) -> Vec<(usize, usize)> {
-        let mut neighbors = Vec::new();
-        let (x, y) = pos;
+    #[test]
+    fn bankruptcy_frees_the_runways_its_planes_held() {
+        let mut world = airfields();
+        let mut landed = plane_at(2, 30, 50);
+        landed.assign_route(&world, vec![(2, 42)]).unwrap();
+        land(&mut landed, &mut world);
+        assert_eq!(world.airports[&(2, 42)].runway_users, vec![(0, 0)]);
+
+        world.release_company_tiles(0);
+        assert!(world.airports[&(2, 42)].runway_users.is_empty());
+
+        // The company taking over starts its vehicle ids again
+        let mut arriving = plane_at(2, 30, 50);
+        arriving.owner = 1;
+        arriving.assign_route(&world, vec![(2, 42)]).unwrap();
+        land(&mut arriving, &mut world);
+        assert_eq!(world.airports[&(2, 42)].runway_users, vec![(1, 0)]);
+    }
 
-        // Check all 4 directions
-        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-            if let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
-                if let Some(tile) = world.get_tile(nx, ny) {
-                    match &tile.content {
-                        TileContent::Track(_) | TileContent::Station(_) => {
-                            neighbors.push((nx, ny));
-                        },
-                        _ => {} // Trains can't use other tile types
-                    }
-                }
+    #[test]
+    fn planes_hold_until_the_runway_is_free() {
+        let mut world = airfields();
+        let mut economy = Economy::new();
+        let mut first = plane_at(2, 30, 50);
+        first.assign_route(&world, vec![(2, 42)]).unwrap();
+        land(&mut first, &mut world);
+
+        let mut second = plane_at(2, 36, 50);
+        second.id = 1;
+        second.assign_route(&world, vec![(2, 42)]).unwrap();
+        for _ in 0..200 {
+            first.update(&mut world, &mut economy);
+            second.update(&mut world, &mut economy);
+        }
+        assert!(matches!(second.state, VehicleState::Holding));
+        assert_ne!((second.x, second.y), (2, 42));
+
+        // Comes in once the first plane has taken off
+        first.assign_route(&world, vec![(2, 2)]).unwrap();
+        for _ in 0..1000 {
+            first.update(&mut world, &mut economy);
+            second.update(&mut world, &mut economy);
+            if (second.x, second.y) == (2, 42) {
+                break;
             }
         }
+        assert_eq!((second.x, second.y), (2, 42));
+        assert!(world.airports[&(2, 42)].runway_users.contains(&(0, 1)));
+    }
 
-        neighbors
+    #[test]
+    fn planes_without_orders_give_up_their_runway() {
+        let mut world = airfields();
+        let mut economy = Economy::new();
+        let mut plane = plane_at(2, 30, 50);
+        plane.assign_route(&world, vec![(2, 42)]).unwrap();
+        land(&mut plane, &mut world);
+
+        plane.remove_stop(&mut world, (2, 42));
+        plane.update(&mut world, &mut economy);
+        assert!(world.airports[&(2, 42)].runway_users.is_empty());
     }
 
-    fn get_road_neighbors(&self, world: &World, pos: (usize, usize)) -> Vec<(usize, usize)> {
-        let mut neighbors = Vec::new();
-        let (x, y) = pos;
+    #[test]
+    fn demolishing_the_next_stop_frees_the_lane_ahead() {
+        let mut world = World::flat(12, 6);
+        let mut economy = Economy::new();
+        world.build_station(2, 2, StationType::Road, 0).unwrap();
+        world.build_station(8, 2, StationType::Road, 0).unwrap();
+        let bus = VehicleType::Road { truck_type: TruckType::Bus { capacity: 40 } };
+        let mut vehicle = Vehicle::new(0, 0, bus, 2, 2);
+        vehicle.assign_route(&world, vec![(8, 2)]).unwrap();
+
+        // Sets off, then claims the lane into the next tile
+        vehicle.update(&mut world, &mut economy);
+        vehicle.update(&mut world, &mut economy);
+        assert_eq!(world.road_occupancy.get(&((3, 2), Direction::East)), Some(&(0, 0)));
+
+        world.demolish(8, 2).unwrap();
+        vehicle.remove_stop(&mut world, (8, 2));
+        assert!(matches!(vehicle.state, VehicleState::Idle));
+        assert!(world.road_occupancy.values().all(|&holder| holder != (0, 0)));
+        assert!(world.reserve_road((3, 2), Direction::East, (0, 1)));
+    }
 
-        // Check all 4 directions  
-        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-            if let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
-                if let Some(tile) = world.get_tile(nx, ny) {
-                    match &tile.content {
-                        TileContent::Road | TileContent::Station(_) | TileContent::Empty => {
-                            // Road vehicles can use roads, stations, and empty terrain
-                            if !matches!(tile.terrain, crate::world::TerrainType::Water | crate::world::TerrainType::Mountain) {
-                                neighbors.push((nx, ny));
-                            }
-                        },
-                        _ => {}
-                    }
-                }
-            }
+    #[test]
+    fn lost_vehicles_set_off_as_soon_as_they_get_reachable_orders() {
+        let mut world = World::flat(12, 6);
+        let mut economy = Economy::new();
+        for y in 0..6 {
+            world.tiles[y][6].terrain = TerrainType::Mountain;
         }
+        world.build_station(9, 2, StationType::Road, 0).unwrap();
+        world.build_station(4, 2, StationType::Road, 0).unwrap();
+        let bus = VehicleType::Road { truck_type: TruckType::Bus { capacity: 40 } };
+        let mut vehicle = Vehicle::new(0, 0, bus, 1, 2);
+        vehicle.assign_route(&world, vec![(9, 2)]).unwrap();
+        vehicle.update(&mut world, &mut economy);
+        assert!(matches!(vehicle.state, VehicleState::Lost { .. }));
+
+        // Nothing on the map changes, only the orders
+        vehicle.assign_route(&world, vec![(4, 2)]).unwrap();
+        vehicle.update(&mut world, &mut economy);
+        assert!(matches!(vehicle.state, VehicleState::Moving { to: (2, 2), .. }));
+    }
 
-        neighbors
+    #[test]
+    fn stopping_a_moving_vehicle_frees_its_lane_and_path() {
+        let mut world = World::flat(12, 6);
+        let mut economy = Economy::new();
+        world.build_station(8, 2, StationType::Road, 0).unwrap();
+        let truck = VehicleType::Road { truck_type: TruckType::SmallTruck { capacity: 20 } };
+        let mut vehicle = Vehicle::new(3, 0, truck, 2, 2);
+        vehicle.assign_route(&world, vec![(8, 2)]).unwrap();
+        vehicle.update(&mut world, &mut economy);
+        vehicle.update(&mut world, &mut economy);
+        assert_eq!(world.road_occupancy.get(&((3, 2), Direction::East)), Some(&(0, 3)));
+
+        vehicle.stop(&mut world);
+        assert!(world.road_occupancy.is_empty());
+        assert!(vehicle.current_path.is_empty() && vehicle.pending_hops.is_empty());
+        assert_eq!(vehicle.route, vec![(8, 2)]);
     }
-}
\ No newline at end of file
+}
@@ -12,8 +12,8 @@ use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tracing::{info, error};
 
-use crate::game::Game;
-use crate::server::{InputCommand, CommandResponse, GameRenderData};
+use crate::game::{Game, GameSettings};
+use crate::server::{InputCommand, CommandResponse, GameRenderData, LineBuildResponse};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -33,6 +33,31 @@ pub struct ActionQuery {
     y: Option<usize>,
 }
 
+#[derive(Deserialize)]
+pub struct BuildQuery {
+    x: Option<usize>,
+    y: Option<usize>,
+    /// Sides a rail piece links, e.g. `north,east`; picked automatically
+    /// when left out
+    piece: Option<String>,
+    /// Signal to put up, `block` or `path`; cycles through the choices
+    /// when left out
+    signal: Option<String>,
+    /// Side a signal faces, e.g. `east`
+    facing: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LineQuery {
+    /// Start of the line as `x,y`
+    from: String,
+    /// End of the line as `x,y`
+    to: String,
+    /// Only report the cost and blocked tiles without building
+    #[serde(default)]
+    preview: bool,
+}
+
 pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
@@ -83,9 +108,16 @@ pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
         .route("/api/command", post(send_command))
         .route("/api/move", post(move_cursor))
         .route("/api/action", post(perform_action))
+        .route("/api/build/rail_line", post(build_rail_line))
+        .route("/api/build/road_line", post(build_road_line))
         .route("/api/build/:action", post(build_action))
         .route("/api/vehicle/:id/order", post(vehicle_order))
         .route("/api/purchase/:vehicle_type", post(purchase_vehicle))
+        .route("/api/economy/payment-rates", get(get_payment_rates))
+        .route("/api/finances", get(get_finances))
+        .route("/api/loan/borrow", post(borrow_money))
+        .route("/api/loan/repay", post(repay_loan))
+        .route("/api/settings", get(get_settings).post(update_settings))
         
         // Serve static files and main page
         .route("/", get(serve_index))
@@ -151,15 +183,15 @@ async fn websocket_connection(mut socket: WebSocket, state: AppState) {
             if let Ok(msg) = msg {
                 match msg {
                     Message::Text(text) => {
-                        if let Ok(command) = serde_json::from_str::<InputCommand>(&text) {
-                            if let Some(input_event) = command.to_input_event() {
-                                let mut game_guard = state_clone.game.lock().unwrap();
-                                let cursor_pos = (
-                                    command.x.unwrap_or(game_guard.cursor_x),
-                                    command.y.unwrap_or(game_guard.cursor_y)
-                                );
-                                game_guard.process_input_event(input_event, cursor_pos);
-                            }
+                        if let Ok(command) = serde_json::from_str::<InputCommand>(&text)
+                            && let Some(input_event) = command.to_input_event()
+                        {
+                            let mut game_guard = state_clone.game.lock().unwrap();
+                            let cursor_pos = (
+                                command.x.unwrap_or(game_guard.cursor_x),
+                                command.y.unwrap_or(game_guard.cursor_y)
+                            );
+                            game_guard.process_input_event(input_event, cursor_pos);
                         }
                     },
                     Message::Close(_) => break,
@@ -188,6 +220,46 @@ async fn get_full_world_data(State(state): State<AppState>) -> Json<crate::serve
     Json(game_guard.get_full_world_data())
 }
 
+async fn get_payment_rates(State(state): State<AppState>) -> Json<Vec<crate::economy::PaymentCurve>> {
+    let game_guard = state.game.lock().unwrap();
+    Json(game_guard.economy.get_payment_rates())
+}
+
+async fn get_finances(State(state): State<AppState>) -> Json<Vec<crate::server::CompanyFinances>> {
+    let game_guard = state.game.lock().unwrap();
+    Json(game_guard.get_finances())
+}
+
+async fn borrow_money(State(state): State<AppState>) -> Json<CommandResponse> {
+    let mut game_guard = state.game.lock().unwrap();
+    Json(match game_guard.borrow_money() {
+        Ok(message) => CommandResponse { success: true, message },
+        Err(message) => CommandResponse { success: false, message },
+    })
+}
+
+async fn repay_loan(State(state): State<AppState>) -> Json<CommandResponse> {
+    let mut game_guard = state.game.lock().unwrap();
+    Json(match game_guard.repay_loan() {
+        Ok(message) => CommandResponse { success: true, message },
+        Err(message) => CommandResponse { success: false, message },
+    })
+}
+
+async fn get_settings(State(state): State<AppState>) -> Json<GameSettings> {
+    let game_guard = state.game.lock().unwrap();
+    Json(game_guard.settings)
+}
+
+async fn update_settings(
+    State(state): State<AppState>,
+    Json(settings): Json<GameSettings>,
+) -> Json<GameSettings> {
+    let mut game_guard = state.game.lock().unwrap();
+    game_guard.apply_settings(settings);
+    Json(game_guard.settings)
+}
+
 async fn send_command(
     State(state): State<AppState>,
     Json(command): Json<InputCommand>,
@@ -268,14 +340,57 @@ async fn perform_action(
 async fn build_action(
     Path(action): Path<String>,
     State(state): State<AppState>,
-    Query(params): Query<ActionQuery>,
+    Query(params): Query<BuildQuery>,
 ) -> Json<CommandResponse> {
+    if let (Some(piece), "rail") = (&params.piece, action.as_str()) {
+        let Some(piece) = parse_track_piece(piece) else {
+            return Json(CommandResponse {
+                success: false,
+                message: format!("Invalid track piece '{}'", piece),
+            });
+        };
+        let mut game_guard = state.game.lock().unwrap();
+        let x = params.x.unwrap_or(game_guard.cursor_x);
+        let y = params.y.unwrap_or(game_guard.cursor_y);
+        let built = game_guard.build_rail_track(x, y, Some(piece));
+        return Json(CommandResponse {
+            success: built,
+            message: if built { "Track built".to_string() } else { "Cannot build track here".to_string() },
+        });
+    }
+
+    if let (Some(signal_type), "signal") = (&params.signal, action.as_str()) {
+        let Some(signal) = parse_signal(signal_type, params.facing.as_deref().unwrap_or("")) else {
+            return Json(CommandResponse {
+                success: false,
+                message: "Invalid signal, expected signal=block|path and a facing side".to_string(),
+            });
+        };
+        let mut game_guard = state.game.lock().unwrap();
+        let x = params.x.unwrap_or(game_guard.cursor_x);
+        let y = params.y.unwrap_or(game_guard.cursor_y);
+        let built = game_guard.build_signal(x, y, Some(signal));
+        return Json(CommandResponse {
+            success: built,
+            message: if built { signal.name() } else { "Cannot put that signal here".to_string() },
+        });
+    }
+
     let build_action = match action.as_str() {
         "rail" => crate::ui::BuildAction::BuildRailTrack,
         "station" => crate::ui::BuildAction::BuildTrainStation,
         "road" => crate::ui::BuildAction::BuildRoad,
         "bus_stop" => crate::ui::BuildAction::BuildBusStop,
         "vehicle" => crate::ui::BuildAction::BuyVehicle,
+        "raise_land" => crate::ui::BuildAction::RaiseLand,
+        "lower_land" => crate::ui::BuildAction::LowerLand,
+        "demolish" => crate::ui::BuildAction::Demolish,
+        "signal" => crate::ui::BuildAction::BuildSignal,
+        "harbor" => crate::ui::BuildAction::BuildHarbor,
+        "canal" => crate::ui::BuildAction::BuildCanal,
+        "buoy" => crate::ui::BuildAction::BuildBuoy,
+        "small_airport" => crate::ui::BuildAction::BuildAirport { size: crate::world::AirportSize::Small },
+        "large_airport" => crate::ui::BuildAction::BuildAirport { size: crate::world::AirportSize::Large },
         _ => return Json(CommandResponse {
             success: false,
             message: "Invalid build action".to_string(),
@@ -298,6 +413,97 @@ async fn build_action(
     })
 }
 
+async fn build_rail_line(
+    State(state): State<AppState>,
+    Query(params): Query<LineQuery>,
+) -> Json<LineBuildResponse> {
+    build_line(state, crate::game::LineKind::Rail, params)
+}
+
+async fn build_road_line(
+    State(state): State<AppState>,
+    Query(params): Query<LineQuery>,
+) -> Json<LineBuildResponse> {
+    build_line(state, crate::game::LineKind::Road, params)
+}
+
+fn build_line(state: AppState, kind: crate::game::LineKind, params: LineQuery) -> Json<LineBuildResponse> {
+    let (Some(from), Some(to)) = (parse_position(&params.from), parse_position(&params.to)) else {
+        return Json(LineBuildResponse {
+            success: false,
+            message: "Line ends must be given as x,y".to_string(),
+            plan: None,
+        });
+    };
+
+    let mut game_guard = state.game.lock().unwrap();
+    if [from, to].iter().any(|&(x, y)| game_guard.world.get_tile(x, y).is_none()) {
+        return Json(LineBuildResponse {
+            success: false,
+            message: "Line ends must be on the map".to_string(),
+            plan: None,
+        });
+    }
+    if params.preview {
+        let plan = game_guard.plan_line(kind, from, to);
+        let success = plan.blocked.is_empty() && game_guard.player.can_afford(plan.cost);
+        return Json(LineBuildResponse {
+            success,
+            message: format!("{} new tile(s) for ${}, {} blocked", plan.new_tiles.len(), plan.cost, plan.blocked.len()),
+            plan: Some(plan),
+        });
+    }
+
+    Json(match game_guard.build_line(kind, from, to) {
+        Ok(plan) => LineBuildResponse {
+            success: true,
+            message: format!("Built {} tile(s) for ${}", plan.new_tiles.len(), plan.cost),
+            plan: Some(plan),
+        },
+        Err(message) => LineBuildResponse {
+            success: false,
+            message,
+            plan: Some(game_guard.plan_line(kind, from, to)),
+        },
+    })
+}
+
+fn parse_position(position: &str) -> Option<(usize, usize)> {
+    let (x, y) = position.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn parse_track_piece(piece: &str) -> Option<crate::world::TrackType> {
+    let connections = piece.split(',')
+        .map(parse_direction)
+        .collect::<Option<Vec<_>>>()?;
+    Some(crate::world::TrackType::from_connections(&connections))
+}
+
+fn parse_signal(signal_type: &str, facing: &str) -> Option<crate::world::Signal> {
+    let signal_type = match signal_type {
+        "block" => crate::world::SignalType::Block,
+        "path" => crate::world::SignalType::Path,
+        _ => return None,
+    };
+    Some(crate::world::Signal { signal_type, facing: parse_direction(facing)? })
+}
+
+fn parse_direction(side: &str) -> Option<crate::world::Direction> {
+    use crate::world::Direction;
+    match side.trim() {
+        "north" => Some(Direction::North),
+        "south" => Some(Direction::South),
+        "east" => Some(Direction::East),
+        "west" => Some(Direction::West),
+        "north_east" => Some(Direction::NorthEast),
+        "north_west" => Some(Direction::NorthWest),
+        "south_east" => Some(Direction::SouthEast),
+        "south_west" => Some(Direction::SouthWest),
+        _ => None,
+    }
+}
+
 async fn vehicle_order(
     Path(id): Path<u32>,
     State(state): State<AppState>,
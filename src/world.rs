@@ -1,6 +1,53 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::cargo::{CargoList, CargoPacket};
+use crate::industry::{IndustrySpec, INDUSTRY_SPECS};
+use crate::network::NetworkGraph;
+use crate::pathfinding::{PathCache, PathCosts, PathKind, Route};
+use crate::terrain::{Heightmap, TerrainConfig, MAX_HEIGHT};
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+/// Cost of moving one tile up or down by one height level.
+pub const TERRAFORM_COST_PER_LEVEL: i64 = 2500;
+
+/// Price of one tile of track on flat ground.
+pub const TRACK_BUILD_COST: i64 = 10000;
+
+/// Price of one tile of road on flat ground.
+pub const ROAD_BUILD_COST: i64 = 5000;
+
+/// Monthly upkeep of one owned tile of track, before inflation.
+pub const TRACK_MAINTENANCE: i64 = 100;
+
+/// Cost of putting a signal on a track tile.
+pub const SIGNAL_BUILD_COST: i64 = 2000;
+
+/// Monthly upkeep of one owned tile of road, before inflation.
+pub const ROAD_MAINTENANCE: i64 = 50;
+
+/// Price of digging one tile of canal on flat ground.
+pub const CANAL_BUILD_COST: i64 = 20000;
+
+/// Monthly upkeep of one owned tile of canal, before inflation.
+pub const CANAL_MAINTENANCE: i64 = 75;
+
+/// Cost of putting a buoy on open water.
+pub const BUOY_BUILD_COST: i64 = 3000;
+
+/// Largest number of tiles a single terraform may touch, cascades included.
+const MAX_TERRAFORM_TILES: usize = 1024;
+
+/// An industry left without service this many months may close.
+const INDUSTRY_CLOSURE_MONTHS: u32 = 60;
+
+/// Something that happened in the world that the player should hear about.
+#[derive(Clone, Debug)]
+pub struct WorldEvent {
+    pub message: String,
+    pub location: (usize, usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum TerrainType {
     Grass,
     Water,
@@ -17,30 +64,252 @@ pub enum TileContent {
     Station(Station),
     Track(TrackType),
     Road,
+    House(House),
 }
 
-#[derive(Clone, Debug)]
+/// A piece of rail, described by the sides of the tile it links up.
+#[derive(Clone, Debug, PartialEq)]
 pub enum TrackType {
     Straight { horizontal: bool },
+    /// A quarter turn between two neighbouring sides
     Curve { from_dir: Direction, to_dir: Direction },
-    Junction,
+    /// Runs corner to corner: rising links north-east and south-west
+    Diagonal { rising: bool },
+    Junction { connections: Vec<Direction> },
+}
+
+impl TrackType {
+    /// Every direction a train can leave the tile in.
+    pub fn connections(&self) -> Vec<Direction> {
+        match self {
+            TrackType::Straight { horizontal: true } => vec![Direction::East, Direction::West],
+            TrackType::Straight { horizontal: false } => vec![Direction::North, Direction::South],
+            TrackType::Curve { from_dir, to_dir } => vec![*from_dir, *to_dir],
+            TrackType::Diagonal { rising: true } => vec![Direction::NorthEast, Direction::SouthWest],
+            TrackType::Diagonal { rising: false } => vec![Direction::NorthWest, Direction::SouthEast],
+            TrackType::Junction { connections } => connections.clone(),
+        }
+    }
+
+    pub fn connects(&self, direction: Direction) -> bool {
+        self.connections().contains(&direction)
+    }
+
+    /// The simplest piece linking exactly `connections`. A lone connection
+    /// gets a straight or diagonal piece through it; nothing at all gets an
+    /// east-west straight.
+    pub fn from_connections(connections: &[Direction]) -> Self {
+        let mut connections = connections.to_vec();
+        connections.sort_by_key(|direction| Direction::ALL.iter().position(|d| d == direction));
+        connections.dedup();
+
+        match connections.as_slice() {
+            [] => TrackType::Straight { horizontal: true },
+            [single] => Self::from_connections(&[*single, single.opposite()]),
+            [a, b] if *a == b.opposite() => match a {
+                Direction::North | Direction::South => TrackType::Straight { horizontal: false },
+                Direction::East | Direction::West => TrackType::Straight { horizontal: true },
+                Direction::NorthEast | Direction::SouthWest => TrackType::Diagonal { rising: true },
+                Direction::NorthWest | Direction::SouthEast => TrackType::Diagonal { rising: false },
+            },
+            [a, b] if !a.is_diagonal() && !b.is_diagonal() => TrackType::Curve { from_dir: *a, to_dir: *b },
+            _ => TrackType::Junction { connections },
+        }
+    }
+
+    /// Glyph for the ASCII map.
+    pub fn glyph(&self) -> char {
+        match self {
+            TrackType::Straight { horizontal: true } => '─',
+            TrackType::Straight { horizontal: false } => '│',
+            TrackType::Diagonal { rising: true } => '╱',
+            TrackType::Diagonal { rising: false } => '╲',
+            TrackType::Curve { .. } | TrackType::Junction { .. } => {
+                let connects = |direction| self.connects(direction);
+                match (connects(Direction::North), connects(Direction::East), connects(Direction::South), connects(Direction::West)) {
+                    (true, true, true, true) => '┼',
+                    (true, true, true, false) => '├',
+                    (true, false, true, true) => '┤',
+                    (false, true, true, true) => '┬',
+                    (true, true, false, true) => '┴',
+                    (true, true, false, false) => '└',
+                    (true, false, false, true) => '┘',
+                    (false, true, true, false) => '┌',
+                    (false, false, true, true) => '┐',
+                    (true, false, true, false) => '│',
+                    (false, true, false, true) => '─',
+                    _ => '╳',
+                }
+            }
+        }
+    }
+
+    /// Short description for tile info.
+    pub fn name(&self) -> String {
+        match self {
+            TrackType::Straight { horizontal: true } => "Horizontal".to_string(),
+            TrackType::Straight { horizontal: false } => "Vertical".to_string(),
+            TrackType::Curve { from_dir, to_dir } => format!("Curve {:?}-{:?}", from_dir, to_dir),
+            TrackType::Diagonal { rising: true } => "Diagonal NE-SW".to_string(),
+            TrackType::Diagonal { rising: false } => "Diagonal NW-SE".to_string(),
+            TrackType::Junction { connections } => {
+                let sides: Vec<String> = connections.iter().map(|direction| format!("{:?}", direction)).collect();
+                format!("Junction {}", sides.join("/"))
+            }
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Direction {
     North,
     South,
     East,
     West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+
+    /// The sides of a tile, without the corners.
+    pub const ORTHOGONAL: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+    /// Step to the neighbouring tile; north is up the map.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::NorthEast => (1, -1),
+            Direction::NorthWest => (-1, -1),
+            Direction::SouthEast => (1, 1),
+            Direction::SouthWest => (-1, 1),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::NorthWest => Direction::SouthEast,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::SouthWest => Direction::NorthEast,
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        !Self::ORTHOGONAL.contains(self)
+    }
+
+    /// The tile one step away, if it is on the map's side of zero.
+    pub fn step(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        let (dx, dy) = self.offset();
+        Some((x.checked_add_signed(dx)?, y.checked_add_signed(dy)?))
+    }
+
+    /// Direction of a neighbouring tile, if `to` is one.
+    pub fn between(from: (usize, usize), to: (usize, usize)) -> Option<Direction> {
+        Self::ALL.into_iter().find(|direction| direction.step(from) == Some(to))
+    }
+}
+
+/// How a signal decides whether a train may pass.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SignalType {
+    /// Clear only while the whole block behind it is empty
+    Block,
+    /// Clear when the train's own path through the block is free, so
+    /// trains on different routes can share a block
+    Path,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SignalState {
+    Green,
+    Red,
+}
+
+/// A signal on a track tile. It only applies to trains leaving the tile by
+/// its `facing` side; trains going the other way pass it like plain track.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Signal {
+    pub signal_type: SignalType,
+    pub facing: Direction,
+}
+
+impl Signal {
+    pub fn name(&self) -> String {
+        let kind = match self.signal_type {
+            SignalType::Block => "Block",
+            SignalType::Path => "Path",
+        };
+        format!("{} signal facing {:?}", kind, self.facing)
+    }
 }
 
+/// A vehicle anywhere on the map, by owner and the id its owner gave it.
+pub type VehicleId = (CompanyId, u32);
+
+/// Towns spread at most this many tiles from their centre.
+pub const TOWN_RADIUS: usize = 8;
+
+/// Rating every station starts with for each cargo, out of 255.
+pub const INITIAL_STATION_RATING: u8 = 175;
+
+/// Ratings drift towards their target by at most this much a day.
+const MAX_RATING_CHANGE: u8 = 2;
+
+/// Cargo left this many days without a pickup starts to go elsewhere.
+const CARGO_DECAY_DAYS: u32 = 15;
+
+/// No station holds more than this much of one cargo.
+const MAX_CARGO_WAITING: u32 = 4095;
+
+/// A station must reach the town centre or this many houses before the town
+/// takes goods and food there.
+const HOUSES_ACCEPTING_GOODS: usize = 4;
+
+/// Town roads run along every third row and column through the centre.
+const TOWN_BLOCK_SIZE: usize = 3;
+
 #[derive(Clone, Debug)]
 pub struct Town {
     pub name: String,
     pub population: u32,
+    /// Population change over the last month, in percent
     pub growth_rate: f32,
     pub cargo_demand: HashMap<CargoType, u32>,
     pub cargo_supply: HashMap<CargoType, u32>,
+    pub houses: Vec<(usize, usize)>,
+    pub roads: Vec<(usize, usize)>,
+    /// Service received so far this month; reset when the town grows
+    pub passengers_moved: u32,
+    pub goods_delivered: u32,
+}
+
+/// A building on a tile of its own. The town it belongs to lives on the
+/// centre tile, which also counts everyone living in its houses.
+#[derive(Clone, Debug)]
+pub struct House {
+    pub town: (usize, usize),
+    pub residents: u32,
 }
 
 #[derive(Clone, Debug)]
@@ -49,10 +318,21 @@ pub struct Industry {
     pub production_rate: u32,
     pub cargo_input: Vec<CargoType>,
     pub cargo_output: Vec<CargoType>,
+    /// Produced cargo waiting to be picked up
     pub stockpile: HashMap<CargoType, u32>,
+    /// Delivered cargo waiting to be processed
+    pub input_stockpile: HashMap<CargoType, u32>,
+    // Running totals for the current month, judged at month end
+    pub produced_this_month: u32,
+    pub transported_this_month: u32,
+    pub delivered_this_month: u32,
+    /// Share of last month's output that reached a station, in percent
+    pub last_month_transported: u32,
+    /// Months in a row with nothing picked up or delivered
+    pub months_unserviced: u32,
 }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum IndustryType {
     CoalMine,
     IronOreMine,
@@ -68,8 +348,109 @@ pub enum IndustryType {
 pub struct Station {
     pub name: String,
     pub station_type: StationType,
-    pub cargo_waiting: HashMap<CargoType, u32>,
+    pub cargo_waiting: CargoList,
     pub connections: Vec<(usize, usize)>,
+    /// Cargo the catchment will take, refreshed every tick
+    pub acceptance: Vec<CargoType>,
+    /// Service rating for each cargo the station has been offered
+    pub ratings: HashMap<CargoType, CargoRating>,
+}
+
+/// How well a station is served for one cargo, as in TTD. The rating drifts
+/// towards a target made up of how recently cargo was picked up, how much is
+/// left waiting, and the speed and age of the last vehicle to load it.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CargoRating {
+    /// Out of 255
+    pub rating: u8,
+    pub days_since_pickup: u32,
+    /// Zero until a vehicle has loaded this cargo
+    pub last_vehicle_speed: u32,
+    /// Age in days of the last vehicle to load this cargo
+    pub last_vehicle_age: u32,
+}
+
+impl Default for CargoRating {
+    fn default() -> Self {
+        Self {
+            rating: INITIAL_STATION_RATING,
+            days_since_pickup: 0,
+            last_vehicle_speed: 0,
+            last_vehicle_age: 0,
+        }
+    }
+}
+
+impl CargoRating {
+    fn target(&self, waiting: u32) -> u8 {
+        let mut target: i32 = match self.days_since_pickup {
+            days if days > 52 => 0,
+            days if days > 30 => 25,
+            days if days > 15 => 45,
+            days if days > 7 => 65,
+            _ => 95,
+        };
+
+        target += match waiting {
+            amount if amount > 1500 => -90,
+            amount if amount > 1000 => -35,
+            amount if amount > 600 => 0,
+            amount if amount > 300 => 10,
+            amount if amount > 100 => 30,
+            _ => 40,
+        };
+
+        if self.last_vehicle_speed > 0 {
+            target += (self.last_vehicle_speed.saturating_sub(85) / 4).min(42) as i32;
+            target += match self.last_vehicle_age / 365 {
+                0 => 33,
+                1 => 20,
+                2 => 10,
+                _ => 0,
+            };
+        }
+
+        target.clamp(0, 255) as u8
+    }
+}
+
+impl Station {
+    /// How well the station is served for a cargo, out of 255. Supply from
+    /// industries and towns is shared between competing stations by this.
+    pub fn rating(&self, cargo_type: CargoType) -> u8 {
+        self.ratings.get(&cargo_type).map_or(INITIAL_STATION_RATING, |rating| rating.rating)
+    }
+
+    /// Notes a vehicle loading cargo here, which is what keeps ratings up.
+    pub fn record_pickup(&mut self, cargo_type: CargoType, vehicle_speed: u32, vehicle_age: u32) {
+        let rating = self.ratings.entry(cargo_type).or_default();
+        rating.days_since_pickup = 0;
+        rating.last_vehicle_speed = vehicle_speed;
+        rating.last_vehicle_age = vehicle_age;
+    }
+
+    /// Moves each rating a day closer to its target. Cargo nobody has
+    /// collected for a while slowly gives up waiting, faster the worse the
+    /// rating.
+    pub fn update_ratings(&mut self) {
+        for (cargo_type, rating) in &mut self.ratings {
+            rating.days_since_pickup = rating.days_since_pickup.saturating_add(1);
+
+            let waiting = self.cargo_waiting.amount_of(*cargo_type);
+            let target = rating.target(waiting);
+            rating.rating = if target > rating.rating {
+                rating.rating.saturating_add(MAX_RATING_CHANGE).min(target)
+            } else {
+                rating.rating.saturating_sub(MAX_RATING_CHANGE).max(target)
+            };
+
+            let mut excess = waiting.saturating_sub(MAX_CARGO_WAITING);
+            if rating.days_since_pickup > CARGO_DECAY_DAYS {
+                excess = excess.max(waiting * (256 - rating.rating as u32) / 256 / 8);
+            }
+            self.cargo_waiting.discard(*cargo_type, excess);
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -80,6 +461,96 @@ pub enum StationType {
     Harbor,
 }
 
+impl StationType {
+    /// Tiles around the station whose buildings and industries it serves,
+    /// both for picking up cargo and for what it accepts.
+    pub fn catchment_radius(&self) -> usize {
+        match self {
+            StationType::Road => 3,
+            StationType::Train => 4,
+            StationType::Harbor => 5,
+            StationType::Airport => 8,
+        }
+    }
+
+    /// Price of building one on flat ground.
+    pub fn build_cost(&self) -> i64 {
+        match self {
+            StationType::Road => 25000,
+            StationType::Train => 50000,
+            StationType::Harbor => 75000,
+            StationType::Airport => 200000,
+        }
+    }
+
+    /// Monthly upkeep before inflation.
+    pub fn maintenance_cost(&self) -> i64 {
+        match self {
+            StationType::Road => 500,
+            StationType::Train => 1000,
+            StationType::Harbor => 1500,
+            StationType::Airport => 3000,
+        }
+    }
+}
+
+/// How big an airport is: the ground it covers and the aircraft it can
+/// handle at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AirportSize {
+    Small,
+    Large,
+}
+
+impl AirportSize {
+    /// Width and height in tiles, the terminal in the top left corner.
+    pub fn footprint(self) -> (usize, usize) {
+        match self {
+            AirportSize::Small => (2, 2),
+            AirportSize::Large => (3, 3),
+        }
+    }
+
+    /// The tiles an airport of this size covers with its terminal at
+    /// `terminal`, the terminal first.
+    pub fn tiles(self, terminal: (usize, usize)) -> Vec<(usize, usize)> {
+        let (width, depth) = self.footprint();
+        (terminal.1..terminal.1 + depth)
+            .flat_map(|y| (terminal.0..terminal.0 + width).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// Aircraft that can be landing or on the ground at once.
+    pub fn runways(self) -> usize {
+        match self {
+            AirportSize::Small => 1,
+            AirportSize::Large => 2,
+        }
+    }
+
+    /// Price of building one on flat ground.
+    pub fn build_cost(self) -> i64 {
+        match self {
+            AirportSize::Small => StationType::Airport.build_cost(),
+            AirportSize::Large => StationType::Airport.build_cost() * 5 / 2,
+        }
+    }
+}
+
+/// The ground around an airport's terminal and who is using its runways.
+#[derive(Clone, Debug)]
+pub struct Airport {
+    pub size: AirportSize,
+    /// Aircraft cleared to land or waiting on the ground to take off
+    pub runway_users: Vec<VehicleId>,
+}
+
+impl Airport {
+    pub fn new(size: AirportSize) -> Self {
+        Self { size, runway_users: Vec::new() }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum CargoType {
     Passengers,
@@ -93,40 +564,153 @@ pub enum CargoType {
     Food,
 }
 
+/// Identifies a company; the human player is always 0.
+pub type CompanyId = u32;
+
 #[derive(Clone, Debug)]
 pub struct Tile {
     pub terrain: TerrainType,
     pub content: TileContent,
     pub height: u8,
+    /// Company that built the tile's track, road or station and pays its
+    /// upkeep. Towns and industries have no owner.
+    pub owner: Option<CompanyId>,
+}
+
+/// Everything needed to regenerate a map: the same config always yields the
+/// same tiles, towns and industries.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct WorldGenConfig {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    #[serde(default)]
+    pub terrain: TerrainConfig,
+}
+
+impl WorldGenConfig {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self { width, height, seed, terrain: TerrainConfig::default() }
+    }
+
+    pub fn with_random_seed(width: usize, height: usize) -> Self {
+        Self::new(width, height, rand::random())
+    }
 }
 
 pub struct World {
     pub width: usize,
     pub height: usize,
+    pub config: WorldGenConfig,
     pub tiles: Vec<Vec<Tile>>,
     pub towns: Vec<(usize, usize)>,
     pub industries: Vec<(usize, usize)>,
     pub stations: Vec<(usize, usize)>,
+    /// Ticks since the world was created; one tick is one day
+    pub ticks: u32,
+    /// News not yet picked up by the game
+    pub events: Vec<WorldEvent>,
+    /// Lets every company's vehicles use every company's infrastructure
+    pub shared_infrastructure: bool,
+    pub signals: HashMap<(usize, usize), Signal>,
+    /// Rail tiles held by a train: the one it stands on and any it has
+    /// reserved ahead of it
+    pub rail_reservations: HashMap<(usize, usize), VehicleId>,
+    /// Road lanes held by road vehicles, by tile and the way they are
+    /// heading, so traffic queues behind whoever is in front
+    pub road_occupancy: HashMap<((usize, usize), Direction), VehicleId>,
+    /// Straight track that a road crosses
+    pub level_crossings: HashSet<(usize, usize)>,
+    /// Trains run into road vehicles on level crossings rather than
+    /// waiting for them to clear
+    pub level_crossing_crashes: bool,
+    /// Road vehicles wrecked on level crossings, for their owners to write
+    /// off
+    pub crashed_vehicles: Vec<VehicleId>,
+    /// Land dug out into water for ships, with the terrain it had before
+    pub canals: HashMap<(usize, usize), TerrainType>,
+    /// Markers on open water that ships can be sent by on their way
+    pub buoys: HashSet<(usize, usize)>,
+    /// Airports by their terminal tile
+    pub airports: HashMap<(usize, usize), Airport>,
+    /// Every tile an airport covers, with the airport's terminal
+    pub airport_tiles: HashMap<(usize, usize), (usize, usize)>,
+    /// What was paid for whatever stands on each tile, part of which comes
+    /// back when it is demolished. Airports are kept under their terminal.
+    pub build_costs: HashMap<(usize, usize), i64>,
+    /// Weights vehicles use to choose their routes
+    pub path_costs: PathCosts,
+    /// Bumped whenever the content of a tile changes
+    pub network_version: u64,
+    /// The network version each changed tile last changed at
+    pub changed_tiles: HashMap<(usize, usize), u64>,
+    /// Routes found so far, shared by every vehicle
+    pub path_cache: PathCache,
+    pub rail_graph: NetworkGraph,
+    pub road_graph: NetworkGraph,
+    /// Bumped by changes on or beside water, where ships' routes run
+    pub water_revision: u64,
+    /// Monthly upkeep before inflation of everything each company owns,
+    /// kept up to date as tiles change
+    pub upkeep: HashMap<CompanyId, i64>,
+    /// Drives town growth, industry changes and cargo rounding, so a game
+    /// plays out the same from the same seed
+    pub rng: StdRng,
 }
 
 impl World {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::from_config(WorldGenConfig::with_random_seed(width, height))
+    }
+
+    pub fn from_config(config: WorldGenConfig) -> Self {
+        let (width, height) = (config.width, config.height);
+        let mut rng = StdRng::seed_from_u64(config.seed);
         let mut world = Self {
             width,
             height,
+            config,
             tiles: vec![vec![Tile {
                 terrain: TerrainType::Grass,
                 content: TileContent::Empty,
                 height: 0,
+                owner: None,
             }; width]; height],
             towns: Vec::new(),
             industries: Vec::new(),
             stations: Vec::new(),
+            ticks: 0,
+            events: Vec::new(),
+            shared_infrastructure: false,
+            signals: HashMap::new(),
+            rail_reservations: HashMap::new(),
+            road_occupancy: HashMap::new(),
+            level_crossings: HashSet::new(),
+            level_crossing_crashes: false,
+            crashed_vehicles: Vec::new(),
+            canals: HashMap::new(),
+            buoys: HashSet::new(),
+            airports: HashMap::new(),
+            airport_tiles: HashMap::new(),
+            build_costs: HashMap::new(),
+            path_costs: PathCosts::default(),
+            network_version: 0,
+            changed_tiles: HashMap::new(),
+            path_cache: PathCache::default(),
+            rail_graph: NetworkGraph::new(PathKind::Rail),
+            road_graph: NetworkGraph::new(PathKind::Road),
+            water_revision: 0,
+            upkeep: HashMap::new(),
+            rng: rng.clone(),
         };
         
-        world.generate_terrain();
-        world.generate_towns();
-        world.generate_industries();
+        // All generation draws from the one seeded RNG, so call order matters
+        world.generate_terrain(&mut rng);
+        world.generate_towns(&mut rng);
+        world.generate_industries(&mut rng);
+        world.rebuild_network_graphs();
+        // Play carries on the generator's sequence
+        world.rng = rng;
         world
     }
 
@@ -135,263 +719,1446 @@ impl World {
     }
 
     pub fn set_tile_content(&mut self, x: usize, y: usize, content: TileContent) {
+        self.count_upkeep(x, y, -1);
         if let Some(tile) = self.tiles.get_mut(y).and_then(|row| row.get_mut(x)) {
             tile.content = content;
+            self.network_changed((x, y));
         }
+        self.count_upkeep(x, y, 1);
     }
 
-    pub fn update(&mut self) {
-        for (x, y) in &self.towns.clone() {
-            if let Some(tile) = self.tiles.get_mut(*y).and_then(|row| row.get_mut(*x)) {
-                if let TileContent::Town(ref mut town) = tile.content {
-                    town.population = (town.population as f32 * (1.0 + town.growth_rate / 100.0)) as u32;
-                    
-                    // Generate cargo demand based on population
-                    let base_demand = town.population / 100; // 1 unit demand per 100 people
-                    *town.cargo_demand.entry(CargoType::Food).or_insert(0) = 
-                        town.cargo_demand.get(&CargoType::Food).unwrap_or(&0).saturating_add(base_demand);
-                    *town.cargo_demand.entry(CargoType::Goods).or_insert(0) = 
-                        town.cargo_demand.get(&CargoType::Goods).unwrap_or(&0).saturating_add(base_demand / 2);
-                    *town.cargo_demand.entry(CargoType::Mail).or_insert(0) = 
-                        town.cargo_demand.get(&CargoType::Mail).unwrap_or(&0).saturating_add(base_demand / 4);
-                    
-                    // Towns also generate passengers
-                    *town.cargo_supply.entry(CargoType::Passengers).or_insert(0) = 
-                        town.cargo_supply.get(&CargoType::Passengers).unwrap_or(&0).saturating_add(town.population / 200);
-                }
-            }
+    /// Records that vehicles may no longer get across `pos` the way they
+    /// used to, so routes over it are planned again.
+    fn network_changed(&mut self, pos: (usize, usize)) {
+        self.network_version += 1;
+        self.changed_tiles.insert(pos, self.network_version);
+        self.rail_graph.mark_changed(pos);
+        self.road_graph.mark_changed(pos);
+        let watery = self.get_tile(pos.0, pos.1).is_some_and(|tile| matches!(tile.terrain, TerrainType::Water));
+        if watery || self.is_coast(pos.0, pos.1) {
+            self.water_revision += 1;
         }
+    }
 
-        let industries = self.industries.clone();
-        for (x, y) in industries {
-            if let Some(tile) = self.tiles.get_mut(y).and_then(|row| row.get_mut(x)) {
-                if let TileContent::Industry(ref mut industry) = tile.content {
-                    Self::update_industry_production_static(industry);
-                }
-            }
+    /// Changes whenever vehicles on `kind` might find routes they couldn't
+    /// before, or lose ones they had; see `NetworkGraph::revision`.
+    pub fn network_revision(&mut self, kind: PathKind) -> u64 {
+        self.refresh_network_graphs();
+        match kind {
+            PathKind::Rail => self.rail_graph.revision(),
+            PathKind::Road => self.road_graph.revision(),
+            PathKind::Water => self.water_revision,
+        }
+    }
+
+    /// Grants or withdraws access to every company's infrastructure.
+    pub fn set_shared_infrastructure(&mut self, shared: bool) {
+        if self.shared_infrastructure != shared {
+            self.shared_infrastructure = shared;
+            self.rail_graph.touch();
+            self.road_graph.touch();
+            self.water_revision += 1;
         }
-        
-        // Transfer cargo from industries to nearby stations
-        self.transfer_cargo_to_stations();
-        
-        // Transfer passengers from towns to nearby stations
-        self.transfer_passengers_to_stations();
     }
 
-    fn generate_terrain(&mut self) {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    /// Open water has no graph; ships plan tile by tile.
+    pub fn network_graph(&self, kind: PathKind) -> Option<&NetworkGraph> {
+        match kind {
+            PathKind::Rail => Some(&self.rail_graph),
+            PathKind::Road => Some(&self.road_graph),
+            PathKind::Water => None,
+        }
+    }
 
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let terrain = match rng.gen_range(0..100) {
-                    0..=60 => TerrainType::Grass,
-                    61..=70 => TerrainType::Forest,
-                    71..=80 => TerrainType::Water,
-                    81..=90 => TerrainType::Mountain,
-                    _ => TerrainType::Desert,
-                };
+    /// Retraces the parts of the rail and road graphs that changed tiles
+    /// pass by.
+    pub fn refresh_network_graphs(&mut self) {
+        let mut rail_graph = std::mem::replace(&mut self.rail_graph, NetworkGraph::new(PathKind::Rail));
+        rail_graph.refresh(self);
+        self.rail_graph = rail_graph;
+        let mut road_graph = std::mem::replace(&mut self.road_graph, NetworkGraph::new(PathKind::Road));
+        road_graph.refresh(self);
+        self.road_graph = road_graph;
+    }
 
-                self.tiles[y][x].terrain = terrain;
-                self.tiles[y][x].height = rng.gen_range(0..10);
-            }
+    /// Traces the rail and road graphs from scratch.
+    pub fn rebuild_network_graphs(&mut self) {
+        let mut rail_graph = std::mem::replace(&mut self.rail_graph, NetworkGraph::new(PathKind::Rail));
+        rail_graph.rebuild(self);
+        self.rail_graph = rail_graph;
+        let mut road_graph = std::mem::replace(&mut self.road_graph, NetworkGraph::new(PathKind::Road));
+        road_graph.rebuild(self);
+        self.road_graph = road_graph;
+    }
+
+    /// Whether nothing on `route` has changed since the network was at
+    /// `version`. The graphs must be up to date.
+    pub fn route_is_current(&self, kind: PathKind, route: &Route, version: u64) -> bool {
+        match route {
+            Route::Tiles(path) => self.path_is_current(path, version),
+            Route::Hops(hops) => self.network_graph(kind).is_some_and(|graph| graph.hops_are_current(hops, version)),
         }
     }
 
-    fn generate_towns(&mut self) {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        let town_names = vec![
-            "Springfield", "Riverside", "Madison", "Georgetown", "Franklin",
-            "Clinton", "Chester", "Marion", "Greenwood", "Fairview",
-            "Oakwood", "Hillside", "Lakeland", "Millville", "Brookfield",
-            "Westbrook", "Eastport", "Northfield", "Southgate", "Centerville",
-            "Redwood", "Bluefield", "Goldmine", "Silverton", "Irondale",
-            "Stonehaven", "Woodbridge", "Clearwater", "Brightwood", "Darkhill",
-            "Snowpeak", "Sunnydale", "Stormport", "Windham", "Thornville",
-            "Rosehaven", "Violetfield", "Crystalbrook", "Diamondville", "Pearlton",
-            "Riverton", "Mountainview", "Valleygreen", "Plainfield", "Marshland"
-        ];
+    /// Whether none of the tiles on `path` has changed since the network
+    /// was at `version`.
+    pub fn path_is_current(&self, path: &[(usize, usize)], version: u64) -> bool {
+        version == self.network_version
+            || path.iter().all(|pos| self.changed_tiles.get(pos).is_none_or(|&changed| changed <= version))
+    }
 
-        // Scale towns with map size: roughly 1 town per 50,000 tiles
-        let town_count = ((self.width * self.height) / 50000).max(5).min(100);
-        for i in 0..town_count {
-            let x = rng.gen_range(0..self.width);
-            let y = rng.gen_range(0..self.height);
+    /// Drops cached routes that something has since been built over or
+    /// torn up; they would be searched for again anyway.
+    fn prune_path_cache(&mut self) {
+        self.refresh_network_graphs();
+        let mut cache = std::mem::take(&mut self.path_cache);
+        cache.retain(|kind, cached| self.route_is_current(kind, &cached.route, cached.version));
+        self.path_cache = cache;
+    }
 
-            if matches!(self.tiles[y][x].terrain, TerrainType::Grass | TerrainType::Forest) {
-                let town = Town {
-                    name: town_names[i % town_names.len()].to_string(),
-                    population: rng.gen_range(500..5000),
-                    growth_rate: rng.gen_range(0.1..2.0),
-                    cargo_demand: HashMap::new(),
-                    cargo_supply: HashMap::new(),
-                };
+    pub fn set_tile_owner(&mut self, x: usize, y: usize, owner: Option<CompanyId>) {
+        self.count_upkeep(x, y, -1);
+        if let Some(tile) = self.tiles.get_mut(y).and_then(|row| row.get_mut(x)) {
+            tile.owner = owner;
+            self.network_changed((x, y));
+        }
+        self.count_upkeep(x, y, 1);
+    }
 
-                self.tiles[y][x].content = TileContent::Town(town);
-                self.towns.push((x, y));
-            }
+    /// Whether `company` may run vehicles over the tile: its own and
+    /// unowned tiles always, other companies' only with shared access.
+    pub fn is_usable_by(&self, x: usize, y: usize, company: CompanyId) -> bool {
+        match self.get_tile(x, y) {
+            Some(tile) => self.shared_infrastructure || tile.owner.is_none_or(|owner| owner == company),
+            None => false,
         }
     }
 
-    fn generate_industries(&mut self) {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    /// Whether `company` built what stands on the tile.
+    pub fn is_owned_by(&self, x: usize, y: usize, company: CompanyId) -> bool {
+        self.get_tile(x, y).is_some_and(|tile| tile.owner == Some(company))
+    }
 
-        // Scale industries with map size: roughly 1 industry per 25,000 tiles
-        let industry_count = ((self.width * self.height) / 25000).max(8).min(200);
-        for _ in 0..industry_count {
-            let x = rng.gen_range(0..self.width);
-            let y = rng.gen_range(0..self.height);
+    /// Sides a train can leave the tile by: along the track, or any side of
+    /// a station. Empty for tiles without rail.
+    pub fn rail_connections(&self, pos: (usize, usize)) -> Vec<Direction> {
+        match self.get_tile(pos.0, pos.1).map(|tile| &tile.content) {
+            Some(TileContent::Track(track)) => track.connections(),
+            Some(TileContent::Station(_)) => Direction::ORTHOGONAL.to_vec(),
+            _ => Vec::new(),
+        }
+    }
 
-            if matches!(self.tiles[y][x].content, TileContent::Empty) {
-                let industry_type = match rng.gen_range(0..8) {
-                    0 => IndustryType::CoalMine,
-                    1 => IndustryType::IronOreMine,
-                    2 => IndustryType::SteelMill,
-                    3 => IndustryType::Factory,
-                    4 => IndustryType::Farm,
-                    5 => IndustryType::Sawmill,
-                    6 => IndustryType::OilRig,
-                    _ => IndustryType::Refinery,
-                };
+    /// Whether rail on `pos` leaves towards `direction` and the tile there
+    /// links back, so trains can pass between the two.
+    pub fn rail_linked(&self, pos: (usize, usize), direction: Direction) -> bool {
+        direction.step(pos).is_some_and(|next| {
+            self.rail_connections(pos).contains(&direction)
+                && self.rail_connections(next).contains(&direction.opposite())
+        })
+    }
 
-                let (input, output) = self.get_industry_cargo(&industry_type);
-                
-                let industry = Industry {
-                    industry_type,
-                    production_rate: rng.gen_range(10..100),
-                    cargo_input: input,
-                    cargo_output: output,
-                    stockpile: HashMap::new(),
-                };
+    /// Lays track for `owner`. Without an explicit `piece` the track joins
+    /// up with the company's neighbouring track and stations, bending loose
+    /// ends of neighbouring track towards it.
+    pub fn build_track(&mut self, x: usize, y: usize, piece: Option<TrackType>, owner: CompanyId) -> Result<(), String> {
+        let tile = self.get_tile(x, y).ok_or("Outside the map")?;
+        if matches!(tile.terrain, TerrainType::Water) {
+            return Err("Cannot build track on water".to_string());
+        }
+        if !self.is_clear(x, y) {
+            return Err(format!("Tile ({}, {}) is in the way", x, y));
+        }
 
-                self.tiles[y][x].content = TileContent::Industry(industry);
-                self.industries.push((x, y));
+        let piece = piece.unwrap_or_else(|| self.joining_track_piece((x, y), &[], owner));
+        let connections = piece.connections();
+        self.set_tile_content(x, y, TileContent::Track(piece));
+        self.set_tile_owner(x, y, Some(owner));
+
+        for direction in connections {
+            if let Some(next) = direction.step((x, y)) {
+                self.bend_track_towards(next, direction.opposite(), owner);
             }
         }
+        Ok(())
     }
 
-    fn get_industry_cargo(&self, industry_type: &IndustryType) -> (Vec<CargoType>, Vec<CargoType>) {
-        match industry_type {
-            IndustryType::CoalMine => (vec![], vec![CargoType::Coal]),
-            IndustryType::IronOreMine => (vec![], vec![CargoType::IronOre]),
-            IndustryType::SteelMill => (vec![CargoType::Coal, CargoType::IronOre], vec![CargoType::Steel]),
-            IndustryType::Factory => (vec![CargoType::Steel], vec![CargoType::Goods]),
-            IndustryType::Farm => (vec![], vec![CargoType::Food]),
-            IndustryType::Sawmill => (vec![], vec![CargoType::Wood]),
-            IndustryType::OilRig => (vec![], vec![CargoType::Oil]),
-            IndustryType::Refinery => (vec![CargoType::Oil], vec![CargoType::Goods]),
-        }
+    /// Whether new track may be joined into the owner's track on `(x, y)`.
+    /// Signals and level crossings need plain track, so not there.
+    pub fn can_join_track(&self, x: usize, y: usize, owner: CompanyId) -> bool {
+        matches!(self.get_tile(x, y).map(|tile| &tile.content), Some(TileContent::Track(_)))
+            && self.is_owned_by(x, y, owner)
+            && !self.signals.contains_key(&(x, y))
+            && !self.level_crossings.contains(&(x, y))
     }
 
-    fn update_industry_production_static(industry: &mut Industry) {
-        if industry.cargo_input.is_empty() {
-            for cargo_type in &industry.cargo_output {
-                *industry.stockpile.entry(cargo_type.clone()).or_insert(0) += industry.production_rate;
-            }
+    /// Relays the owner's track on `(x, y)` so it also leaves towards every
+    /// side in `directions`, making a junction where lines cross.
+    pub fn join_track(&mut self, x: usize, y: usize, directions: &[Direction], owner: CompanyId) -> Result<(), String> {
+        if !self.can_join_track(x, y, owner) {
+            return Err(format!("Can't join the track at ({}, {})", x, y));
         }
+        let mut connections = self.rail_connections((x, y));
+        connections.extend_from_slice(directions);
+        self.set_tile_content(x, y, TileContent::Track(TrackType::from_connections(&connections)));
+        Ok(())
     }
-    
-    fn transfer_cargo_to_stations(&mut self) {
-        let industries = self.industries.clone();
-        
-        for (industry_x, industry_y) in industries {
-            // Find cargo to transfer from this industry
-            let mut cargo_to_transfer = HashMap::new();
-            
-            if let Some(tile) = self.get_tile(industry_x, industry_y) {
-                if let TileContent::Industry(industry) = &tile.content {
-                    // Transfer some cargo from stockpile
-                    for (cargo_type, &amount) in &industry.stockpile {
-                        if amount > 0 {
-                            let transfer_amount = (amount / 4).max(1); // Transfer 25% of stockpile
-                            cargo_to_transfer.insert(cargo_type.clone(), transfer_amount);
-                        }
-                    }
-                }
-            }
-            
-            // Look for nearby stations to transfer to
-            for station_x in industry_x.saturating_sub(3)..=(industry_x + 3).min(self.width - 1) {
-                for station_y in industry_y.saturating_sub(3)..=(industry_y + 3).min(self.height - 1) {
-                    if let Some(tile) = self.tiles.get_mut(station_y).and_then(|row| row.get_mut(station_x)) {
-                        if let TileContent::Station(ref mut station) = tile.content {
-                            // Transfer cargo to this station
-                            for (cargo_type, &amount) in &cargo_to_transfer {
-                                *station.cargo_waiting.entry(cargo_type.clone()).or_insert(0) += amount;
-                            }
-                        }
-                    }
-                }
-            }
-            
-            // Remove transferred cargo from industry stockpile
-            if let Some(tile) = self.tiles.get_mut(industry_y).and_then(|row| row.get_mut(industry_x)) {
-                if let TileContent::Industry(ref mut industry) = tile.content {
-                    for (cargo_type, &amount) in &cargo_to_transfer {
-                        if let Some(stockpile_amount) = industry.stockpile.get_mut(&cargo_type) {
-                            *stockpile_amount = stockpile_amount.saturating_sub(amount);
-                        }
-                    }
-                }
+
+    /// The piece for new track on `pos` that leaves towards every side in
+    /// `required` and also joins up with the owner's neighbouring rail.
+    pub fn joining_track_piece(&self, pos: (usize, usize), required: &[Direction], owner: CompanyId) -> TrackType {
+        let mut connections = required.to_vec();
+        for direction in Direction::ALL {
+            if !connections.contains(&direction) && self.joins_towards(pos, direction, owner) {
+                connections.push(direction);
             }
         }
+        TrackType::from_connections(&connections)
     }
-    
-    fn transfer_passengers_to_stations(&mut self) {
-        let towns = self.towns.clone();
-        
-        for (town_x, town_y) in towns {
-            // Find passengers to transfer from this town
-            let mut passengers_to_transfer = 0;
-            
-            if let Some(tile) = self.get_tile(town_x, town_y) {
-                if let TileContent::Town(town) = &tile.content {
-                    if let Some(&passengers) = town.cargo_supply.get(&CargoType::Passengers) {
-                        passengers_to_transfer = passengers / 2; // Transfer half the passengers
-                    }
-                }
-            }
-            
-            if passengers_to_transfer > 0 {
-                // Look for nearby stations to transfer passengers to
-                for station_x in town_x.saturating_sub(2)..=(town_x + 2).min(self.width - 1) {
-                    for station_y in town_y.saturating_sub(2)..=(town_y + 2).min(self.height - 1) {
-                        if let Some(tile) = self.tiles.get_mut(station_y).and_then(|row| row.get_mut(station_x)) {
-                            if let TileContent::Station(ref mut station) = tile.content {
-                                *station.cargo_waiting.entry(CargoType::Passengers).or_insert(0) += passengers_to_transfer;
-                                break; // Only transfer to first station found
-                            }
-                        }
-                    }
-                }
-                
-                // Remove transferred passengers from town
-                if let Some(tile) = self.tiles.get_mut(town_y).and_then(|row| row.get_mut(town_x)) {
-                    if let TileContent::Town(ref mut town) = tile.content {
-                        if let Some(passenger_supply) = town.cargo_supply.get_mut(&CargoType::Passengers) {
-                            *passenger_supply = passenger_supply.saturating_sub(passengers_to_transfer);
-                        }
-                    }
-                }
-            }
+
+    /// Tiles from `from` to `to`: straight along the row first, then along
+    /// the column, giving a straight or L-shaped line.
+    pub fn line_path(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut path = vec![from];
+        let (mut x, mut y) = from;
+        while x != to.0 {
+            x = if x < to.0 { x + 1 } else { x - 1 };
+            path.push((x, y));
+        }
+        while y != to.1 {
+            y = if y < to.1 { y + 1 } else { y - 1 };
+            path.push((x, y));
         }
+        path
     }
 
-    pub fn get_ascii_char(&self, x: usize, y: usize) -> char {
-        if let Some(tile) = self.get_tile(x, y) {
+    /// Whether new track on `pos` should link towards `direction`: the
+    /// owner's rail there already points back, or is track with a loose
+    /// end that can be bent round.
+    fn joins_towards(&self, pos: (usize, usize), direction: Direction, owner: CompanyId) -> bool {
+        let Some(next) = direction.step(pos) else {
+            return false;
+        };
+        if !self.is_owned_by(next.0, next.1, owner) {
+            return false;
+        }
+        if self.rail_connections(next).contains(&direction.opposite()) {
+            return true;
+        }
+        !direction.is_diagonal() && self.can_bend_track(next)
+    }
+
+    /// Whether `pos` is track with a loose end that can be relaid. Track
+    /// under a signal or a level crossing stays as it is.
+    fn can_bend_track(&self, pos: (usize, usize)) -> bool {
+        matches!(self.get_tile(pos.0, pos.1).map(|tile| &tile.content), Some(TileContent::Track(_)))
+            && !self.signals.contains_key(&pos)
+            && !self.level_crossings.contains(&pos)
+            && self.linked_sides(pos).len() < 2
+    }
+
+    /// Relays track with fewer than two linked sides so it also leaves
+    /// towards `direction`.
+    fn bend_track_towards(&mut self, pos: (usize, usize), direction: Direction, owner: CompanyId) {
+        if !self.is_owned_by(pos.0, pos.1, owner)
+            || self.rail_connections(pos).contains(&direction)
+            || !self.can_bend_track(pos)
+        {
+            return;
+        }
+        let mut sides = self.linked_sides(pos);
+        sides.push(direction);
+        self.set_tile_content(pos.0, pos.1, TileContent::Track(TrackType::from_connections(&sides)));
+    }
+
+    fn linked_sides(&self, pos: (usize, usize)) -> Vec<Direction> {
+        self.rail_connections(pos).into_iter()
+            .filter(|direction| self.rail_linked(pos, *direction))
+            .collect()
+    }
+
+    /// Puts a signal on the owner's plain track, replacing any signal
+    /// already there. Junctions can't take signals.
+    pub fn build_signal(&mut self, x: usize, y: usize, signal: Signal, owner: CompanyId) -> Result<(), String> {
+        let Some(TileContent::Track(track)) = self.get_tile(x, y).map(|tile| &tile.content) else {
+            return Err("Signals can only go on track".to_string());
+        };
+        if !self.is_owned_by(x, y, owner) {
+            return Err("Signals can only go on your own track".to_string());
+        }
+        if matches!(track, TrackType::Junction { .. }) {
+            return Err("Signals can't go on a junction".to_string());
+        }
+        if self.level_crossings.contains(&(x, y)) {
+            return Err("Signals can't go on a level crossing".to_string());
+        }
+        if !track.connects(signal.facing) {
+            return Err(format!("The track doesn't run {:?}", signal.facing));
+        }
+        self.signals.insert((x, y), signal);
+        Ok(())
+    }
+
+    /// Takes the owner's signal off a tile and returns it.
+    pub fn remove_signal(&mut self, x: usize, y: usize, owner: CompanyId) -> Result<Signal, String> {
+        let Some(&signal) = self.signals.get(&(x, y)) else {
+            return Err("There is no signal here".to_string());
+        };
+        if !self.is_owned_by(x, y, owner) {
+            return Err("Signals can only be removed from your own track".to_string());
+        }
+        self.signals.remove(&(x, y));
+        self.network_changed((x, y));
+        Ok(signal)
+    }
+
+    /// Shows red while another train holds the track a signal protects:
+    /// any of the block behind a block signal, or the signal tile itself
+    /// for a path signal, which only turns green for a train that has
+    /// reserved its way through.
+    pub fn signal_state(&self, pos: (usize, usize)) -> Option<SignalState> {
+        let signal = self.signals.get(&pos)?;
+        let clear = match signal.signal_type {
+            SignalType::Block => !self.rail_reservations.contains_key(&pos)
+                && self.rail_block(pos, signal.facing).iter().all(|tile| !self.rail_reservations.contains_key(tile)),
+            SignalType::Path => self.rail_reservations.contains_key(&pos),
+        };
+        Some(if clear { SignalState::Green } else { SignalState::Red })
+    }
+
+    /// The block a signal leads into: rail linked to the tile beyond it in
+    /// `facing`, up to the next signals.
+    fn rail_block(&self, signal: (usize, usize), facing: Direction) -> HashSet<(usize, usize)> {
+        let mut block = HashSet::new();
+        let mut queue = VecDeque::new();
+        if self.rail_linked(signal, facing) {
+            queue.extend(facing.step(signal));
+        }
+        while let Some(pos) = queue.pop_front() {
+            if pos == signal || self.signals.contains_key(&pos) || !block.insert(pos) {
+                continue;
+            }
+            for direction in self.rail_connections(pos) {
+                if self.rail_linked(pos, direction) {
+                    queue.extend(direction.step(pos));
+                }
+            }
+        }
+        block
+    }
+
+    /// The signal on `pos` if it applies to a train coming from `from`: one
+    /// facing the side the train will leave by. On a curve that isn't the
+    /// way the train was heading when it came in.
+    pub fn signal_facing(&self, from: (usize, usize), pos: (usize, usize)) -> Option<Signal> {
+        let signal = self.signals.get(&pos)?;
+        let entry = Direction::between(pos, from)?;
+        // Signals only stand on plain track, which has exactly one other side
+        let exit = self.rail_connections(pos).into_iter().find(|&side| side != entry)?;
+        (signal.facing == exit).then_some(*signal)
+    }
+
+    /// Claims the next tile on a train's path, `ahead[0]`. A train passing
+    /// a signal needs more than that: a block signal wants the whole block
+    /// behind it empty, a path signal only the train's path up to the next
+    /// signal, which is then reserved in one go. Returns false, leaving
+    /// nothing claimed, while another train is in the way.
+    pub fn reserve_rail_ahead(&mut self, train: VehicleId, from: (usize, usize), ahead: &[(usize, usize)]) -> bool {
+        let Some(&next) = ahead.first() else {
+            return true;
+        };
+        let signal = self.signal_facing(from, next);
+
+        let mut claim = vec![next];
+        let mut must_be_free = vec![next];
+        if let Some(signal) = signal {
+            let stretch: Vec<(usize, usize)> = ahead[1..].iter()
+                .take_while(|pos| !self.signals.contains_key(pos))
+                .copied()
+                .collect();
+            match signal.signal_type {
+                SignalType::Block => must_be_free.extend(self.rail_block(next, signal.facing)),
+                SignalType::Path => must_be_free.extend(&stretch),
+            }
+            claim.extend(stretch);
+        }
+
+        let free = must_be_free.iter()
+            .all(|pos| self.rail_reservations.get(pos).is_none_or(|&holder| holder == train))
+            && (self.level_crossing_crashes || claim.iter().all(|&pos| self.road_vehicles_on(pos).is_empty()));
+        if free {
+            for pos in claim {
+                self.rail_reservations.insert(pos, train);
+            }
+        }
+        free
+    }
+
+    /// A train has rolled onto `pos`. Any road vehicle still on a level
+    /// crossing there is wrecked.
+    pub fn train_arrives(&mut self, pos: (usize, usize)) {
+        if !self.level_crossings.contains(&pos) {
+            return;
+        }
+        let wrecked = self.road_vehicles_on(pos);
+        if wrecked.is_empty() {
+            return;
+        }
+        self.road_occupancy.retain(|_, holder| !wrecked.contains(holder));
+        self.crashed_vehicles.extend(&wrecked);
+        self.events.push(WorldEvent {
+            message: format!("Level crossing crash! A train hit {} road vehicle(s)", wrecked.len()),
+            location: pos,
+        });
+    }
+
+    /// Holds the tile a train stands on, unless another train got there
+    /// first.
+    pub fn hold_rail_tile(&mut self, pos: (usize, usize), train: VehicleId) {
+        self.rail_reservations.entry(pos).or_insert(train);
+    }
+
+    /// Frees one tile a train no longer needs.
+    pub fn release_rail_tile(&mut self, pos: (usize, usize), train: VehicleId) {
+        if self.rail_reservations.get(&pos) == Some(&train) {
+            self.rail_reservations.remove(&pos);
+        }
+    }
+
+    /// Frees everything a train holds except the tile it stands on.
+    pub fn release_rail(&mut self, train: VehicleId, standing_on: (usize, usize)) {
+        self.rail_reservations.retain(|&pos, holder| *holder != train || pos == standing_on);
+    }
+
+    /// Lays a road across straight track so road vehicles can cross it.
+    pub fn build_level_crossing(&mut self, x: usize, y: usize) -> Result<(), String> {
+        match self.get_tile(x, y).map(|tile| &tile.content) {
+            Some(TileContent::Track(TrackType::Straight { .. })) => {}
+            Some(TileContent::Track(_)) => return Err("Roads can only cross straight track".to_string()),
+            _ => return Err("There is no track to cross here".to_string()),
+        }
+        if self.signals.contains_key(&(x, y)) {
+            return Err("Roads can't cross track with a signal".to_string());
+        }
+        if !self.level_crossings.insert((x, y)) {
+            return Err("There is already a level crossing here".to_string());
+        }
+        self.network_changed((x, y));
+        Ok(())
+    }
+
+    /// Sides a road vehicle may enter and leave a level crossing by:
+    /// straight across the rail. None if there is no crossing on `pos`.
+    pub fn crossing_road_sides(&self, pos: (usize, usize)) -> Option<[Direction; 2]> {
+        if !self.level_crossings.contains(&pos) {
+            return None;
+        }
+        match self.get_tile(pos.0, pos.1).map(|tile| &tile.content) {
+            Some(TileContent::Track(TrackType::Straight { horizontal: true })) => Some([Direction::North, Direction::South]),
+            Some(TileContent::Track(TrackType::Straight { horizontal: false })) => Some([Direction::East, Direction::West]),
+            _ => None,
+        }
+    }
+
+    /// Claims the lane a road vehicle drives into next: `pos`, heading
+    /// `heading`. Fails while another vehicle going the same way is there,
+    /// or while a train holds the level crossing on `pos`.
+    pub fn reserve_road(&mut self, pos: (usize, usize), heading: Direction, vehicle: VehicleId) -> bool {
+        if self.level_crossings.contains(&pos) && self.rail_reservations.contains_key(&pos) {
+            return false;
+        }
+        match self.road_occupancy.get(&(pos, heading)) {
+            Some(&holder) if holder != vehicle => false,
+            _ => {
+                self.road_occupancy.insert((pos, heading), vehicle);
+                true
+            }
+        }
+    }
+
+    /// Frees every lane a road vehicle holds except `keep`.
+    pub fn release_road(&mut self, vehicle: VehicleId, keep: Option<((usize, usize), Direction)>) {
+        self.road_occupancy.retain(|&lane, holder| *holder != vehicle || Some(lane) == keep);
+    }
+
+    /// Frees the track, road lanes and runways a vehicle holds, except the
+    /// rail and lanes of the tile it stands on and the runway of the
+    /// airport there, if it is standing anywhere.
+    pub fn release_vehicle(&mut self, vehicle: VehicleId, standing_on: Option<(usize, usize)>) {
+        self.rail_reservations.retain(|&pos, holder| *holder != vehicle || Some(pos) == standing_on);
+        self.road_occupancy.retain(|&(pos, _), holder| *holder != vehicle || Some(pos) == standing_on);
+        let airport_here = standing_on.and_then(|pos| self.airport_at(pos));
+        for (terminal, airport) in &mut self.airports {
+            if Some(*terminal) != airport_here {
+                airport.runway_users.retain(|&user| user != vehicle);
+            }
+        }
+    }
+
+    fn road_vehicles_on(&self, pos: (usize, usize)) -> Vec<VehicleId> {
+        self.road_occupancy.iter()
+            .filter(|((lane_pos, _), _)| *lane_pos == pos)
+            .map(|(_, &holder)| holder)
+            .collect()
+    }
+
+    /// Opens a new station for `owner` on empty land.
+    pub fn build_station(&mut self, x: usize, y: usize, station_type: StationType, owner: CompanyId) -> Result<(), String> {
+        let tile = self.get_tile(x, y).ok_or("Outside the map")?;
+        if matches!(tile.terrain, TerrainType::Water) {
+            return Err("Cannot build a station on water".to_string());
+        }
+        if !self.is_clear(x, y) {
+            return Err(format!("Tile ({}, {}) is in the way", x, y));
+        }
+        if matches!(station_type, StationType::Harbor) && !self.is_coast(x, y) {
+            return Err("Harbors must be built next to water".to_string());
+        }
+
+        let kind = match station_type {
+            StationType::Train => "Station",
+            StationType::Road => "Bus Stop",
+            StationType::Harbor => "Harbor",
+            StationType::Airport => "Airport",
+        };
+        let station = Station {
+            name: format!("{} {}", kind, self.stations.len() + 1),
+            station_type,
+            cargo_waiting: CargoList::new(),
+            connections: Vec::new(),
+            acceptance: Vec::new(),
+            ratings: HashMap::new(),
+        };
+        self.set_tile_content(x, y, TileContent::Station(station));
+        self.set_tile_owner(x, y, Some(owner));
+        self.stations.push((x, y));
+        Ok(())
+    }
+
+    /// Lays out an airport for `owner` with its terminal at `(x, y)` and
+    /// the rest of the field to the south east. The whole field must be
+    /// level, open land.
+    pub fn build_airport(&mut self, x: usize, y: usize, size: AirportSize, owner: CompanyId) -> Result<(), String> {
+        let height = self.get_tile(x, y).ok_or("Outside the map")?.height;
+        let field = size.tiles((x, y));
+        for &(field_x, field_y) in &field {
+            let tile = self.get_tile(field_x, field_y).ok_or("The airport would run off the map")?;
+            if matches!(tile.terrain, TerrainType::Water) {
+                return Err("Cannot build an airport on water".to_string());
+            }
+            if !self.is_clear(field_x, field_y) {
+                return Err(format!("Tile ({}, {}) is in the way", field_x, field_y));
+            }
+            if tile.height != height {
+                return Err("Airports need level ground".to_string());
+            }
+        }
+
+        self.build_station(x, y, StationType::Airport, owner)?;
+        for &(field_x, field_y) in &field[1..] {
+            self.set_tile_owner(field_x, field_y, Some(owner));
+        }
+        self.airports.insert((x, y), Airport::new(size));
+        for pos in field {
+            self.airport_tiles.insert(pos, (x, y));
+        }
+        Ok(())
+    }
+
+    /// The terminal of the airport the tile is part of, if any.
+    pub fn airport_at(&self, pos: (usize, usize)) -> Option<(usize, usize)> {
+        self.airport_tiles.get(&pos).copied()
+    }
+
+    /// Works out which tiles every airport covers again, after the
+    /// airports were replaced wholesale.
+    pub fn index_airports(&mut self) {
+        self.airport_tiles = self.airports.iter()
+            .flat_map(|(&terminal, airport)| airport.size.tiles(terminal).into_iter().map(move |pos| (pos, terminal)))
+            .collect();
+    }
+
+    /// Whether nothing has been built on the tile, airfields included.
+    pub fn is_clear(&self, x: usize, y: usize) -> bool {
+        self.get_tile(x, y).is_some_and(|tile| matches!(tile.content, TileContent::Empty))
+            && self.airport_at((x, y)).is_none()
+    }
+
+    /// Clears an aircraft to land at `pos` if that airport has a runway
+    /// free, or it already has one. Anywhere but an airport needs no
+    /// clearance.
+    pub fn claim_runway(&mut self, pos: (usize, usize), plane: VehicleId) -> bool {
+        let Some(airport) = self.airports.get_mut(&pos) else {
+            return true;
+        };
+        if airport.runway_users.contains(&plane) {
+            return true;
+        }
+        if airport.runway_users.len() >= airport.size.runways() {
+            return false;
+        }
+        airport.runway_users.push(plane);
+        true
+    }
+
+    /// Frees the runway the aircraft was using at the airport at `pos`.
+    pub fn release_runway(&mut self, pos: (usize, usize), plane: VehicleId) {
+        if let Some(airport) = self.airports.get_mut(&pos) {
+            airport.runway_users.retain(|&user| user != plane);
+        }
+    }
+
+    /// Whether land at `(x, y)` borders water on one of its sides.
+    pub fn is_coast(&self, x: usize, y: usize) -> bool {
+        Direction::ORTHOGONAL.iter()
+            .filter_map(|direction| direction.step((x, y)))
+            .any(|(nx, ny)| self.get_tile(nx, ny).is_some_and(|tile| matches!(tile.terrain, TerrainType::Water)))
+    }
+
+    /// Digs a canal for `owner`, turning empty land into water ships can
+    /// sail.
+    pub fn build_canal(&mut self, x: usize, y: usize, owner: CompanyId) -> Result<(), String> {
+        let tile = self.get_tile(x, y).ok_or("Outside the map")?;
+        if matches!(tile.terrain, TerrainType::Water) {
+            return Err("There is water here already".to_string());
+        }
+        if !self.is_clear(x, y) {
+            return Err(format!("Tile ({}, {}) is in the way", x, y));
+        }
+        let terrain = std::mem::replace(&mut self.tiles[y][x].terrain, TerrainType::Water);
+        self.canals.insert((x, y), terrain);
+        self.set_tile_owner(x, y, Some(owner));
+        Ok(())
+    }
+
+    /// Puts a buoy on open water for `owner`. Ships of every company may
+    /// sail past it.
+    pub fn build_buoy(&mut self, x: usize, y: usize, owner: CompanyId) -> Result<(), String> {
+        let tile = self.get_tile(x, y).ok_or("Outside the map")?;
+        if !matches!(tile.terrain, TerrainType::Water) || self.canals.contains_key(&(x, y)) {
+            return Err("Buoys can only be placed on open water".to_string());
+        }
+        if !self.buoys.insert((x, y)) {
+            return Err("There is already a buoy here".to_string());
+        }
+        self.set_tile_owner(x, y, Some(owner));
+        Ok(())
+    }
+
+    /// Clears the track, road, station, canal or buoy on a tile and returns
+    /// what stood there. Stations are also dropped from `stations`.
+    pub fn demolish(&mut self, x: usize, y: usize) -> Result<TileContent, String> {
+        // Any part of an airport takes the whole field with it
+        let (x, y) = self.airport_at((x, y)).unwrap_or((x, y));
+        self.build_costs.remove(&(x, y));
+        if let Some(airport) = self.airports.remove(&(x, y)) {
+            let field = airport.size.tiles((x, y));
+            for &pos in &field {
+                self.airport_tiles.remove(&pos);
+            }
+            for &(field_x, field_y) in &field[1..] {
+                self.set_tile_owner(field_x, field_y, None);
+            }
+        }
+        if self.buoys.remove(&(x, y)) {
+            self.set_tile_owner(x, y, None);
+            return Ok(TileContent::Empty);
+        }
+        if self.canals.contains_key(&(x, y)) {
+            // Fill the canal back in, once its upkeep is off the owner's books
+            self.set_tile_owner(x, y, None);
+            if let Some(terrain) = self.canals.remove(&(x, y)) {
+                self.tiles[y][x].terrain = terrain;
+            }
+            return Ok(TileContent::Empty);
+        }
+        let tile = self.get_tile(x, y).ok_or("Outside the map")?;
+        if !matches!(tile.content, TileContent::Track(_) | TileContent::Road | TileContent::Station(_)) {
+            return Err("Nothing here can be demolished".to_string());
+        }
+        self.count_upkeep(x, y, -1);
+        let tile = &mut self.tiles[y][x];
+        tile.owner = None;
+        let content = std::mem::replace(&mut tile.content, TileContent::Empty);
+        self.network_changed((x, y));
+        self.stations.retain(|&station| station != (x, y));
+        self.signals.remove(&(x, y));
+        self.level_crossings.remove(&(x, y));
+        Ok(content)
+    }
+
+    /// Adds `cost` to what has been paid for the tile at `pos`.
+    pub fn record_build_cost(&mut self, pos: (usize, usize), cost: i64) {
+        *self.build_costs.entry(pos).or_insert(0) += cost;
+    }
+
+    /// Hands every tile a company owns back to nobody, e.g. when it goes
+    /// bankrupt.
+    pub fn release_company_tiles(&mut self, owner: CompanyId) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.tiles[y][x].owner == Some(owner) {
+                    self.set_tile_owner(x, y, None);
+                }
+            }
+        }
+        self.rail_reservations.retain(|_, (company, _)| *company != owner);
+        self.road_occupancy.retain(|_, (company, _)| *company != owner);
+        for airport in self.airports.values_mut() {
+            airport.runway_users.retain(|(company, _)| *company != owner);
+        }
+    }
+
+    /// Monthly upkeep, before inflation, of everything `owner` has built.
+    pub fn maintenance_cost(&self, owner: CompanyId) -> i64 {
+        self.upkeep.get(&owner).copied().unwrap_or(0)
+    }
+
+    /// The company paying for a tile and its monthly upkeep before
+    /// inflation, if anyone owns it.
+    fn tile_upkeep(&self, x: usize, y: usize) -> Option<(CompanyId, i64)> {
+        let tile = self.get_tile(x, y)?;
+        let cost = match &tile.content {
+            TileContent::Track(_) => TRACK_MAINTENANCE,
+            TileContent::Road => ROAD_MAINTENANCE,
+            TileContent::Station(station) => station.station_type.maintenance_cost(),
+            _ => 0,
+        } + if self.canals.contains_key(&(x, y)) { CANAL_MAINTENANCE } else { 0 };
+        Some((tile.owner?, cost))
+    }
+
+    /// Adds a tile's upkeep to its owner's total (`sign` 1) or takes it off
+    /// again (`sign` -1). Every change of owner or content must take the
+    /// old upkeep off before and add the new upkeep after.
+    fn count_upkeep(&mut self, x: usize, y: usize, sign: i64) {
+        if let Some((owner, cost)) = self.tile_upkeep(x, y) {
+            *self.upkeep.entry(owner).or_insert(0) += sign * cost;
+        }
+    }
+
+    /// Totals every company's upkeep from scratch, after tiles were
+    /// replaced wholesale.
+    pub fn recount_upkeep(&mut self) {
+        self.upkeep.clear();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.count_upkeep(x, y, 1);
+            }
+        }
+    }
+
+    /// Largest height step from a tile to any of its four neighbours: 0 is
+    /// flat, 1 a gentle slope, 2 or more a cliff that must be levelled.
+    pub fn slope_at(&self, x: usize, y: usize) -> u8 {
+        let Some(centre) = self.get_tile(x, y).map(|tile| tile.height as i32) else {
+            return 0;
+        };
+        [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .filter_map(|(dx, dy): (isize, isize)| {
+                x.checked_add_signed(dx)
+                    .zip(y.checked_add_signed(dy))
+                    .and_then(|(nx, ny)| self.get_tile(nx, ny))
+            })
+            .map(|tile| (tile.height as i32 - centre).unsigned_abs())
+            .max()
+            .unwrap_or(0) as u8
+    }
+
+    /// Applies the slope surcharge to a construction cost: gentle slopes cost
+    /// half as much again, steeper ground has to be levelled first.
+    pub fn construction_cost(&self, x: usize, y: usize, base_cost: i64) -> Result<i64, String> {
+        match self.slope_at(x, y) {
+            0 => Ok(base_cost),
+            1 => Ok(base_cost + base_cost / 2),
+            _ => Err(format!("Ground at ({}, {}) is too steep, level it first", x, y)),
+        }
+    }
+
+    /// Levels climbed moving from one tile to the next; zero when going downhill.
+    pub fn climb_between(&self, from: (usize, usize), to: (usize, usize)) -> u8 {
+        match (self.get_tile(from.0, from.1), self.get_tile(to.0, to.1)) {
+            (Some(a), Some(b)) => b.height.saturating_sub(a.height),
+            _ => 0,
+        }
+    }
+
+    /// Raises a tile by one level, dragging neighbours up with it where
+    /// needed. Nothing changes if the cost exceeds `budget`. Returns the
+    /// total cost.
+    pub fn raise_land(&mut self, x: usize, y: usize, budget: i64) -> Result<i64, String> {
+        let current = self.get_tile(x, y).ok_or("Outside the map")?.height;
+        if current >= MAX_HEIGHT {
+            return Err("Land is already at maximum height".to_string());
+        }
+        self.terraform(&[((x, y), current + 1)], budget)
+    }
+
+    /// Lowers a tile by one level, dragging neighbours down with it where
+    /// needed. Land never drops to sea level. Nothing changes if the cost
+    /// exceeds `budget`. Returns the total cost.
+    pub fn lower_land(&mut self, x: usize, y: usize, budget: i64) -> Result<i64, String> {
+        let current = self.get_tile(x, y).ok_or("Outside the map")?.height;
+        if current <= 1 {
+            return Err("Land cannot be lowered below sea level".to_string());
+        }
+        self.terraform(&[((x, y), current - 1)], budget)
+    }
+
+    /// Flattens the rectangle between two corners to the height of `from`.
+    /// Nothing changes if the cost exceeds `budget`. Returns the total cost.
+    pub fn level_area(&mut self, from: (usize, usize), to: (usize, usize), budget: i64) -> Result<i64, String> {
+        let level = self.get_tile(from.0, from.1).ok_or("Outside the map")?.height;
+        let targets: Vec<_> = (from.1.min(to.1)..=from.1.max(to.1))
+            .flat_map(|y| (from.0.min(to.0)..=from.0.max(to.0)).map(move |x| ((x, y), level)))
+            .collect();
+        self.terraform(&targets, budget)
+    }
+
+    /// Plans every height change needed to bring `targets` to their new
+    /// heights and applies them only if the whole plan is possible and
+    /// affordable.
+    fn terraform(&mut self, targets: &[((usize, usize), u8)], budget: i64) -> Result<i64, String> {
+        let mut planned: HashMap<(usize, usize), u8> = HashMap::new();
+        // Each entry is a tile and the range its height has to end up in
+        let mut queue: VecDeque<_> = targets.iter().map(|&(pos, height)| (pos, height, height)).collect();
+
+        while let Some(((x, y), lowest, highest)) = queue.pop_front() {
+            let tile = self.get_tile(x, y).ok_or("Outside the map")?;
+            let current = planned.get(&(x, y)).copied().unwrap_or(tile.height);
+            let new_height = current.clamp(lowest, highest);
+            if new_height == current {
+                continue;
+            }
+            if matches!(tile.terrain, TerrainType::Water) {
+                return Err(format!("Cannot terraform water at ({}, {})", x, y));
+            }
+            if !self.is_clear(x, y) {
+                return Err(format!("Tile ({}, {}) is in the way", x, y));
+            }
+            planned.insert((x, y), new_height);
+            if planned.len() > MAX_TERRAFORM_TILES {
+                return Err("Terraforming would affect too much land".to_string());
+            }
+
+            // Neighbours may differ by at most one level; the coast is left as a cliff
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) else {
+                    continue;
+                };
+                if let Some(neighbour) = self.get_tile(nx, ny)
+                    && !matches!(neighbour.terrain, TerrainType::Water)
+                {
+                    queue.push_back(((nx, ny), new_height.saturating_sub(1).max(1), new_height + 1));
+                }
+            }
+        }
+
+        let cost: i64 = planned.iter()
+            .map(|(&(x, y), &new_height)| (self.tiles[y][x].height as i64 - new_height as i64).abs() * TERRAFORM_COST_PER_LEVEL)
+            .sum();
+        if cost > budget {
+            return Err(format!("Terraforming costs ${}, more than you can afford", cost));
+        }
+        for ((x, y), new_height) in planned {
+            self.tiles[y][x].height = new_height;
+        }
+        Ok(cost)
+    }
+
+    pub fn update(&mut self) {
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(30) {
+            self.prune_path_cache();
+            let mut rng = self.rng.clone();
+            self.grow_towns(&mut rng);
+            self.update_industry_lifecycle(&mut rng);
+            self.rng = rng;
+        }
+
+        for (x, y) in &self.towns.clone() {
+            if let Some(tile) = self.tiles.get_mut(*y).and_then(|row| row.get_mut(*x))
+                && let TileContent::Town(ref mut town) = tile.content
+            {
+                // Generate cargo demand based on population
+                let base_demand = town.population / 100; // 1 unit demand per 100 people
+                *town.cargo_demand.entry(CargoType::Food).or_insert(0) = 
+                    town.cargo_demand.get(&CargoType::Food).unwrap_or(&0).saturating_add(base_demand);
+                *town.cargo_demand.entry(CargoType::Goods).or_insert(0) = 
+                    town.cargo_demand.get(&CargoType::Goods).unwrap_or(&0).saturating_add(base_demand / 2);
+                *town.cargo_demand.entry(CargoType::Mail).or_insert(0) = 
+                    town.cargo_demand.get(&CargoType::Mail).unwrap_or(&0).saturating_add(base_demand / 4);
+                
+                // Towns also generate passengers
+                *town.cargo_supply.entry(CargoType::Passengers).or_insert(0) = 
+                    town.cargo_supply.get(&CargoType::Passengers).unwrap_or(&0).saturating_add(town.population / 200);
+            }
+        }
+
+        let industries = self.industries.clone();
+        for (x, y) in industries {
+            if let Some(tile) = self.tiles.get_mut(y).and_then(|row| row.get_mut(x))
+                && let TileContent::Industry(ref mut industry) = tile.content
+            {
+                Self::update_industry_production_static(industry);
+            }
+        }
+        
+        self.update_station_acceptance();
+        self.update_station_ratings();
+
+        // Transfer cargo from industries to nearby stations
+        self.transfer_cargo_to_stations();
+        
+        // Transfer passengers from towns to nearby stations
+        self.transfer_passengers_to_stations();
+    }
+
+    fn generate_terrain(&mut self, rng: &mut StdRng) {
+        let heightmap = Heightmap::generate(self.width, self.height, &self.config.terrain, rng);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = heightmap.index(x, y);
+                self.tiles[y][x].terrain = heightmap.terrain[i].clone();
+                self.tiles[y][x].height = heightmap.elevation[i];
+            }
+        }
+    }
+
+    fn generate_towns(&mut self, rng: &mut StdRng) {
+        let town_names = vec![
+            "Springfield", "Riverside", "Madison", "Georgetown", "Franklin",
+            "Clinton", "Chester", "Marion", "Greenwood", "Fairview",
+            "Oakwood", "Hillside", "Lakeland", "Millville", "Brookfield",
+            "Westbrook", "Eastport", "Northfield", "Southgate", "Centerville",
+            "Redwood", "Bluefield", "Goldmine", "Silverton", "Irondale",
+            "Stonehaven", "Woodbridge", "Clearwater", "Brightwood", "Darkhill",
+            "Snowpeak", "Sunnydale", "Stormport", "Windham", "Thornville",
+            "Rosehaven", "Violetfield", "Crystalbrook", "Diamondville", "Pearlton",
+            "Riverton", "Mountainview", "Valleygreen", "Plainfield", "Marshland"
+        ];
+
+        // Scale towns with map size: roughly 1 town per 50,000 tiles
+        let town_count = ((self.width * self.height) / 50000).clamp(5, 100);
+        for i in 0..town_count {
+            let x = rng.gen_range(0..self.width);
+            let y = rng.gen_range(0..self.height);
+
+            if matches!(self.tiles[y][x].terrain, TerrainType::Grass | TerrainType::Forest) {
+                let town = Town {
+                    name: town_names[i % town_names.len()].to_string(),
+                    population: rng.gen_range(200..1000),
+                    growth_rate: 0.0,
+                    cargo_demand: HashMap::new(),
+                    cargo_supply: HashMap::new(),
+                    houses: Vec::new(),
+                    roads: Vec::new(),
+                    passengers_moved: 0,
+                    goods_delivered: 0,
+                };
+
+                self.tiles[y][x].content = TileContent::Town(town);
+                self.towns.push((x, y));
+
+                // Give each town a head start so it begins life as more than one tile
+                for _ in 0..rng.gen_range(20..80) {
+                    self.try_grow_town((x, y), rng);
+                }
+            }
+        }
+    }
+
+    /// Monthly town growth. Towns always grow a little; every passenger moved
+    /// and every load of goods or food delivered speeds them up.
+    fn grow_towns(&mut self, rng: &mut impl Rng) {
+        for centre in self.towns.clone() {
+            let Some(TileContent::Town(town)) = self.get_tile(centre.0, centre.1).map(|tile| &tile.content) else {
+                continue;
+            };
+            let service = town.passengers_moved + town.goods_delivered * 2;
+            let population_before = town.population;
+
+            let attempts = (service / 50).min(12) + u32::from(rng.gen_bool(0.3));
+            for _ in 0..attempts {
+                self.try_grow_town(centre, rng);
+            }
+
+            if let TileContent::Town(ref mut town) = self.tiles[centre.1][centre.0].content {
+                let growth = town.population.saturating_sub(population_before) as f32;
+                town.growth_rate = growth / population_before.max(1) as f32 * 100.0;
+                town.passengers_moved = 0;
+                town.goods_delivered = 0;
+            }
+        }
+    }
+
+    /// Adds one road or house next to one of the town's roads, giving up
+    /// after a few spots turn out to be taken. Roads follow a grid through
+    /// the centre; the blocks between fill with houses.
+    fn try_grow_town(&mut self, centre: (usize, usize), rng: &mut impl Rng) -> bool {
+        let roads = match self.get_tile(centre.0, centre.1).map(|tile| &tile.content) {
+            Some(TileContent::Town(town)) if town.houses.len() < self.town_capacity(centre) => town.roads.clone(),
+            _ => return false,
+        };
+
+        for _ in 0..10 {
+            // The centre itself counts as a road junction
+            let from = roads.get(rng.gen_range(0..=roads.len())).copied().unwrap_or(centre);
+            let (dx, dy) = [(-1, 0), (1, 0), (0, -1), (0, 1)][rng.gen_range(0..4)];
+            let (Some(x), Some(y)) = (from.0.checked_add_signed(dx), from.1.checked_add_signed(dy)) else {
+                continue;
+            };
+            if x.abs_diff(centre.0) > TOWN_RADIUS || y.abs_diff(centre.1) > TOWN_RADIUS || !self.is_town_buildable(x, y) {
+                continue;
+            }
+            if !self.is_clear(x, y) {
+                continue;
+            }
+
+            let on_street = x.abs_diff(centre.0).is_multiple_of(TOWN_BLOCK_SIZE)
+                || y.abs_diff(centre.1).is_multiple_of(TOWN_BLOCK_SIZE);
+            let residents = rng.gen_range(20..80);
+            let content = if on_street {
+                TileContent::Road
+            } else {
+                TileContent::House(House { town: centre, residents })
+            };
+            self.set_tile_content(x, y, content);
+
+            if let TileContent::Town(ref mut town) = self.tiles[centre.1][centre.0].content {
+                if on_street {
+                    town.roads.push((x, y));
+                } else {
+                    town.houses.push((x, y));
+                    town.population += residents;
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Most houses the land around a town can hold: two thirds of the
+    /// buildable tiles within reach, the rest being left for streets.
+    pub fn town_capacity(&self, centre: (usize, usize)) -> usize {
+        let x_range = centre.0.saturating_sub(TOWN_RADIUS)..=(centre.0 + TOWN_RADIUS).min(self.width - 1);
+        let y_range = centre.1.saturating_sub(TOWN_RADIUS)..=(centre.1 + TOWN_RADIUS).min(self.height - 1);
+        let buildable = y_range
+            .flat_map(|y| x_range.clone().map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_town_buildable(x, y))
+            .count();
+        buildable * 2 / 3
+    }
+
+    fn is_town_buildable(&self, x: usize, y: usize) -> bool {
+        self.get_tile(x, y).is_some_and(|tile| {
+            !matches!(tile.terrain, TerrainType::Water | TerrainType::Mountain) && self.slope_at(x, y) <= 1
+        })
+    }
+
+    /// Finds the town owning the tile within `radius` closest to a position,
+    /// whether that tile is the town centre or one of its houses.
+    pub fn town_near(&self, x: usize, y: usize, radius: usize) -> Option<(usize, usize)> {
+        let mut nearest = None;
+        for check_y in y.saturating_sub(radius)..=(y + radius).min(self.height - 1) {
+            for check_x in x.saturating_sub(radius)..=(x + radius).min(self.width - 1) {
+                let town = match &self.tiles[check_y][check_x].content {
+                    TileContent::Town(_) => (check_x, check_y),
+                    TileContent::House(house) => house.town,
+                    _ => continue,
+                };
+                let distance = check_x.abs_diff(x) + check_y.abs_diff(y);
+                if nearest.is_none_or(|(best, _)| distance < best) {
+                    nearest = Some((distance, town));
+                }
+            }
+        }
+        nearest.map(|(_, town)| town)
+    }
+
+    /// Credits a town with cargo moved through a station near it, which
+    /// feeds into its growth at the end of the month.
+    pub fn record_town_service(&mut self, station: (usize, usize), cargo_type: CargoType, amount: u32) {
+        let Some(radius) = self.catchment_radius_at(station.0, station.1) else {
+            return;
+        };
+        let Some((x, y)) = self.town_near(station.0, station.1, radius) else {
+            return;
+        };
+        if let TileContent::Town(ref mut town) = self.tiles[y][x].content {
+            match cargo_type {
+                CargoType::Passengers | CargoType::Mail => town.passengers_moved += amount,
+                CargoType::Goods | CargoType::Food => town.goods_delivered += amount,
+                _ => {}
+            }
+        }
+    }
+
+    fn generate_industries(&mut self, rng: &mut StdRng) {
+        for _ in 0..self.target_industry_count() {
+            let x = rng.gen_range(0..self.width);
+            let y = rng.gen_range(0..self.height);
+            self.try_place_industry(x, y, rng);
+        }
+    }
+
+    fn target_industry_count(&self) -> usize {
+        // Scale industries with map size: roughly 1 industry per 25,000 tiles
+        ((self.width * self.height) / 25000).clamp(8, 200)
+    }
+
+    /// Places a random industry type on the tile if the tile suits it.
+    fn try_place_industry(&mut self, x: usize, y: usize, rng: &mut impl Rng) -> Option<&'static IndustrySpec> {
+        if !self.is_clear(x, y) {
+            return None;
+        }
+        let spec = &INDUSTRY_SPECS[rng.gen_range(0..INDUSTRY_SPECS.len())];
+
+        // Offshore industries stand on water; everything else needs dry land
+        let on_water = matches!(self.tiles[y][x].terrain, TerrainType::Water);
+        if on_water != spec.offshore {
+            return None;
+        }
+
+        let industry = Industry {
+            industry_type: spec.industry_type.clone(),
+            production_rate: rng.gen_range(10..100),
+            cargo_input: spec.input_cargo(),
+            cargo_output: spec.output_cargo(),
+            stockpile: HashMap::new(),
+            input_stockpile: HashMap::new(),
+            produced_this_month: 0,
+            transported_this_month: 0,
+            delivered_this_month: 0,
+            last_month_transported: 0,
+            months_unserviced: 0,
+        };
+
+        self.tiles[y][x].content = TileContent::Industry(industry);
+        self.industries.push((x, y));
+        Some(spec)
+    }
+
+    /// Monthly industry review, after classic TTD: well served industries
+    /// may grow, neglected ones shrink and eventually close, and now and
+    /// then a new industry opens somewhere on the map.
+    fn update_industry_lifecycle(&mut self, rng: &mut impl Rng) {
+        let mut closed = Vec::new();
+
+        for (x, y) in self.industries.clone() {
+            let TileContent::Industry(ref mut industry) = self.tiles[y][x].content else {
+                continue;
+            };
+            let spec = IndustrySpec::for_type(&industry.industry_type);
+
+            let transported = (industry.transported_this_month * 100)
+                .checked_div(industry.produced_this_month)
+                .unwrap_or(0);
+            let serviced = industry.transported_this_month > 0 || industry.delivered_this_month > 0;
+            industry.last_month_transported = transported.min(100);
+            industry.months_unserviced = if serviced { 0 } else { industry.months_unserviced + 1 };
+            industry.produced_this_month = 0;
+            industry.transported_this_month = 0;
+            industry.delivered_this_month = 0;
+
+            let old_rate = industry.production_rate;
+            let mut change = None;
+            if industry.months_unserviced >= INDUSTRY_CLOSURE_MONTHS && rng.gen_bool(0.1) {
+                closed.push((x, y));
+                change = Some("announces imminent closure!");
+            } else if rng.gen_bool(0.04) {
+                if transported >= 60 {
+                    industry.production_rate = (old_rate + (old_rate / 4).max(1)).min(500);
+                    change = Some("increases production!");
+                } else if transported < 20 && (spec.is_primary() || !serviced) && old_rate > 5 {
+                    industry.production_rate = (old_rate - old_rate / 4).max(5);
+                    change = Some("cuts production");
+                }
+            }
+
+            if let Some(change) = change {
+                let message = format!("{} {} {}", self.nearest_town_name(x, y), spec.name, change);
+                self.events.push(WorldEvent { message, location: (x, y) });
+            }
+        }
+
+        for (x, y) in closed {
+            self.set_tile_content(x, y, TileContent::Empty);
+            self.industries.retain(|&pos| pos != (x, y));
+        }
+
+        // Openings come quickly while closures have left the map short
+        let chance = if self.industries.len() < self.target_industry_count() { 0.25 } else { 0.05 };
+        if rng.gen_bool(chance) {
+            for _ in 0..50 {
+                let x = rng.gen_range(0..self.width);
+                let y = rng.gen_range(0..self.height);
+                if let Some(spec) = self.try_place_industry(x, y, rng) {
+                    let message = format!("New {} under construction near {}", spec.name, self.nearest_town_name(x, y));
+                    self.events.push(WorldEvent { message, location: (x, y) });
+                    break;
+                }
+            }
+        }
+    }
+
+    fn nearest_town_name(&self, x: usize, y: usize) -> String {
+        self.towns.iter()
+            .min_by_key(|&&(tx, ty)| tx.abs_diff(x) + ty.abs_diff(y))
+            .and_then(|&(tx, ty)| match &self.tiles[ty][tx].content {
+                TileContent::Town(town) => Some(town.name.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| format!("({}, {})", x, y))
+    }
+
+    /// Takes the events gathered since the last call.
+    pub fn drain_events(&mut self) -> Vec<WorldEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Primary industries produce every tick. Secondary industries turn
+    /// delivered inputs into outputs, at most `production_rate` batches of
+    /// their recipe per tick.
+    fn update_industry_production_static(industry: &mut Industry) {
+        let spec = IndustrySpec::for_type(&industry.industry_type);
+
+        let batches = if spec.is_primary() {
+            industry.production_rate
+        } else {
+            let available = spec.inputs.iter()
+                .map(|(cargo_type, per_batch)| industry.input_stockpile.get(cargo_type).unwrap_or(&0) / per_batch)
+                .min()
+                .unwrap_or(0);
+            available.min(industry.production_rate)
+        };
+        if batches == 0 {
+            return;
+        }
+
+        for (cargo_type, per_batch) in spec.inputs {
+            if let Some(amount) = industry.input_stockpile.get_mut(cargo_type) {
+                *amount -= batches * per_batch;
+            }
+        }
+        for (cargo_type, per_batch) in spec.outputs {
+            *industry.stockpile.entry(*cargo_type).or_insert(0) += batches * per_batch;
+            industry.produced_this_month += batches * per_batch;
+        }
+    }
+
+    /// Hands cargo unloaded at a station to the first industry within
+    /// `radius` that uses it. Returns how much was taken.
+    pub fn deliver_to_industry(&mut self, station: (usize, usize), cargo_type: CargoType, amount: u32, radius: usize) -> u32 {
+        let (x, y) = station;
+        for check_y in y.saturating_sub(radius)..=(y + radius).min(self.height - 1) {
+            for check_x in x.saturating_sub(radius)..=(x + radius).min(self.width - 1) {
+                if let TileContent::Industry(ref mut industry) = self.tiles[check_y][check_x].content
+                    && IndustrySpec::for_type(&industry.industry_type).accepts(cargo_type)
+                {
+                    *industry.input_stockpile.entry(cargo_type).or_insert(0) += amount;
+                    industry.delivered_this_month += amount;
+                    return amount;
+                }
+            }
+        }
+        0
+    }
+    
+    fn transfer_cargo_to_stations(&mut self) {
+        let industries = self.industries.clone();
+        
+        for (industry_x, industry_y) in industries {
+            // Cargo with no station to go to stays in the stockpile
+            let stations = self.stations_covering(&[(industry_x, industry_y)]);
+            if stations.is_empty() {
+                continue;
+            }
+
+            // Find cargo to transfer from this industry
+            let mut cargo_to_transfer = Vec::new();
+            
+            if let Some(tile) = self.get_tile(industry_x, industry_y)
+                && let TileContent::Industry(industry) = &tile.content
+            {
+                // The whole stockpile is offered; what the stations don't take is lost
+                for (cargo_type, &amount) in &industry.stockpile {
+                    if amount > 0 {
+                        cargo_to_transfer.push((*cargo_type, amount));
+                    }
+                }
+            }
+            
+            for (cargo_type, amount) in cargo_to_transfer {
+                let moved = self.share_between_stations((industry_x, industry_y), &stations, cargo_type, amount);
+
+                // Remove transferred cargo from industry stockpile
+                if let TileContent::Industry(ref mut industry) = self.tiles[industry_y][industry_x].content
+                    && let Some(stockpile_amount) = industry.stockpile.get_mut(&cargo_type)
+                {
+                    *stockpile_amount = stockpile_amount.saturating_sub(amount);
+                    industry.transported_this_month += moved;
+                }
+            }
+        }
+    }
+    
+    fn transfer_passengers_to_stations(&mut self) {
+        let towns = self.towns.clone();
+        
+        for (town_x, town_y) in towns {
+            // Find passengers to transfer from this town, and every tile of it
+            // a station might reach
+            let mut passengers_to_transfer = 0;
+            let mut town_tiles = vec![(town_x, town_y)];
+            
+            if let Some(tile) = self.get_tile(town_x, town_y)
+                && let TileContent::Town(town) = &tile.content
+            {
+                if let Some(&passengers) = town.cargo_supply.get(&CargoType::Passengers) {
+                    passengers_to_transfer = passengers;
+                }
+                town_tiles.extend(&town.houses);
+            }
+            
+            if passengers_to_transfer > 0 {
+                let stations = self.stations_covering(&town_tiles);
+                if stations.is_empty() {
+                    continue;
+                }
+                self.share_between_stations((town_x, town_y), &stations, CargoType::Passengers, passengers_to_transfer);
+
+                // Passengers the stations didn't take give up
+                if let TileContent::Town(ref mut town) = self.tiles[town_y][town_x].content
+                    && let Some(passenger_supply) = town.cargo_supply.get_mut(&CargoType::Passengers)
+                {
+                    *passenger_supply = passenger_supply.saturating_sub(passengers_to_transfer);
+                }
+            }
+        }
+    }
+
+    /// Stations whose catchment reaches any of `tiles`.
+    fn stations_covering(&self, tiles: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        self.stations.iter().copied().filter(|&(station_x, station_y)| {
+            let Some(radius) = self.catchment_radius_at(station_x, station_y) else {
+                return false;
+            };
+            tiles.iter().any(|&(x, y)| x.abs_diff(station_x) <= radius && y.abs_diff(station_y) <= radius)
+        }).collect()
+    }
+
+    /// Offers `amount` of cargo produced at `source` to competing stations.
+    /// The best rating for the cargo decides how much of it gets picked up
+    /// at all, and that is split between the stations in proportion to their
+    /// ratings, rounding in favour of the best rated one. Returns how much
+    /// reached a station.
+    fn share_between_stations(&mut self, source: (usize, usize), stations: &[(usize, usize)], cargo_type: CargoType, amount: u32) -> u32 {
+        let ratings: Vec<u64> = stations.iter().map(|&(x, y)| match &self.tiles[y][x].content {
+            TileContent::Station(station) => station.rating(cargo_type) as u64,
+            _ => 0,
+        }).collect();
+        let total_rating: u64 = ratings.iter().sum();
+        let Some(best) = (0..ratings.len()).max_by_key(|&i| ratings[i]) else {
+            return 0;
+        };
+
+        // Round at random so small amounts still get through on average
+        let moved = ((amount as u64 * ratings[best] + self.rng.gen_range(0..255)) / 255) as u32;
+        let mut shares: Vec<u32> = ratings.iter()
+            .map(|&rating| (moved as u64 * rating).checked_div(total_rating).unwrap_or(0) as u32)
+            .collect();
+        shares[best] += moved - shares.iter().sum::<u32>();
+
+        for (&(x, y), share) in stations.iter().zip(shares) {
+            if let TileContent::Station(ref mut station) = self.tiles[y][x].content {
+                station.ratings.entry(cargo_type).or_default();
+                station.cargo_waiting.push(CargoPacket::new(cargo_type, share, source, (x, y)));
+            }
+        }
+        moved
+    }
+
+    /// Catchment radius of the station at (x, y), if there is one.
+    pub fn catchment_radius_at(&self, x: usize, y: usize) -> Option<usize> {
+        match self.get_tile(x, y).map(|tile| &tile.content) {
+            Some(TileContent::Station(station)) => Some(station.station_type.catchment_radius()),
+            _ => None,
+        }
+    }
+
+    /// Works out which cargo a station at (x, y) can deliver. Any house or
+    /// town centre in the catchment takes passengers and mail, goods and food
+    /// need the centre or a few houses, and industries take their inputs.
+    pub fn station_acceptance(&self, x: usize, y: usize, radius: usize) -> Vec<CargoType> {
+        let mut reaches_centre = false;
+        let mut houses = 0;
+        let mut industry_inputs = Vec::new();
+
+        for check_y in y.saturating_sub(radius)..=(y + radius).min(self.height - 1) {
+            for check_x in x.saturating_sub(radius)..=(x + radius).min(self.width - 1) {
+                match &self.tiles[check_y][check_x].content {
+                    TileContent::Town(_) => reaches_centre = true,
+                    TileContent::House(_) => houses += 1,
+                    TileContent::Industry(industry) => {
+                        industry_inputs.extend(IndustrySpec::for_type(&industry.industry_type).input_cargo());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut accepted = Vec::new();
+        if reaches_centre || houses > 0 {
+            accepted.extend([CargoType::Passengers, CargoType::Mail]);
+        }
+        if reaches_centre || houses >= HOUSES_ACCEPTING_GOODS {
+            accepted.extend([CargoType::Goods, CargoType::Food]);
+        }
+        for cargo_type in industry_inputs {
+            if !accepted.contains(&cargo_type) {
+                accepted.push(cargo_type);
+            }
+        }
+        accepted
+    }
+
+    fn update_station_ratings(&mut self) {
+        for (x, y) in self.stations.clone() {
+            if let TileContent::Station(ref mut station) = self.tiles[y][x].content {
+                station.update_ratings();
+            }
+        }
+    }
+
+    fn update_station_acceptance(&mut self) {
+        for (x, y) in self.stations.clone() {
+            let Some(radius) = self.catchment_radius_at(x, y) else {
+                continue;
+            };
+            let acceptance = self.station_acceptance(x, y, radius);
+            if let TileContent::Station(ref mut station) = self.tiles[y][x].content {
+                station.acceptance = acceptance;
+            }
+        }
+    }
+
+    pub fn get_ascii_char(&self, x: usize, y: usize) -> char {
+        if let Some(tile) = self.get_tile(x, y) {
             match &tile.content {
                 TileContent::Town(_) => '◉',
                 TileContent::Industry(_) => '▓',
                 TileContent::Station(_) => '■',
-                TileContent::Track(track_type) => match track_type {
-                    TrackType::Straight { horizontal: true } => '─',
-                    TrackType::Straight { horizontal: false } => '│',
-                    TrackType::Curve { .. } => '┐',
-                    TrackType::Junction => '┼',
-                },
+                TileContent::Track(TrackType::Straight { horizontal }) if self.level_crossings.contains(&(x, y)) => {
+                    if *horizontal { '╪' } else { '╫' }
+                }
+                TileContent::Track(track_type) => track_type.glyph(),
                 TileContent::Road => '.',
+                TileContent::House(_) => '▪',
+                TileContent::Empty if self.buoys.contains(&(x, y)) => '◊',
+                TileContent::Empty if self.canals.contains_key(&(x, y)) => '≈',
+                TileContent::Empty if self.airport_at((x, y)).is_some() => '▒',
                 TileContent::Empty => match tile.terrain {
                     TerrainType::Grass => ' ',
                     TerrainType::Water => '~',
@@ -429,4 +2196,340 @@ impl World {
             crate::vehicle::VehicleType::Aircraft { .. } => 'A',
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+impl World {
+    /// Level grassland with nothing built on it, for tests that place
+    /// exactly what they need.
+    pub(crate) fn flat(width: usize, height: usize) -> Self {
+        let mut world = Self::from_config(WorldGenConfig::new(width, height, 0));
+        for row in &mut world.tiles {
+            for tile in row {
+                *tile = Tile { terrain: TerrainType::Grass, content: TileContent::Empty, height: 1, owner: None };
+            }
+        }
+        world.towns.clear();
+        world.industries.clear();
+        world.rebuild_network_graphs();
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Terrain, height and what stands on every tile, plus where towns and
+    /// industries went.
+    fn layout(world: &World) -> String {
+        let mut out = String::new();
+        for y in 0..world.height {
+            for x in 0..world.width {
+                let tile = &world.tiles[y][x];
+                out.push_str(&format!("{:?}{}{}", tile.terrain, tile.height, world.get_ascii_char(x, y)));
+            }
+            out.push('\n');
+        }
+        out.push_str(&format!("{:?}{:?}", world.towns, world.industries));
+        out
+    }
+
+    #[test]
+    fn same_seed_generates_same_world() {
+        let first = World::from_config(WorldGenConfig::new(128, 96, 42));
+        let second = World::from_config(WorldGenConfig::new(128, 96, 42));
+        assert_eq!(layout(&first), layout(&second));
+        assert!(!first.towns.is_empty());
+        assert!(!first.industries.is_empty());
+    }
+
+    #[test]
+    fn different_seeds_generate_different_worlds() {
+        let first = World::from_config(WorldGenConfig::new(128, 96, 42));
+        let second = World::from_config(WorldGenConfig::new(128, 96, 43));
+        assert_ne!(layout(&first), layout(&second));
+    }
+
+    #[test]
+    fn slope_is_the_largest_step_to_a_neighbour() {
+        let mut world = World::flat(8, 8);
+        assert_eq!(world.slope_at(3, 3), 0);
+
+        // An even ramp climbs one level per tile
+        for x in 0..8 {
+            for y in 0..8 {
+                world.tiles[y][x].height = 1 + x as u8;
+            }
+        }
+        assert_eq!(world.slope_at(3, 3), 1);
+        assert_eq!(world.construction_cost(3, 3, 100), Ok(150));
+
+        world.tiles[3][4].height = 7;
+        assert_eq!(world.slope_at(3, 3), 3);
+        assert!(world.construction_cost(3, 3, 100).is_err());
+    }
+
+    #[test]
+    fn rail_only_links_where_both_pieces_meet() {
+        let horizontal = TrackType::Straight { horizontal: true };
+        let mut world = World::flat(6, 6);
+        world.build_track(0, 2, Some(horizontal.clone()), 0).unwrap();
+        world.build_track(0, 3, Some(horizontal.clone()), 0).unwrap();
+        world.build_track(1, 2, Some(TrackType::Straight { horizontal: false }), 0).unwrap();
+
+        // Side by side, or meeting end on, the pieces don't join up
+        assert!(!world.rail_linked((0, 2), Direction::South));
+        assert!(!world.rail_linked((0, 2), Direction::East));
+        assert!(!world.rail_linked((0, 2), Direction::West));
+        assert!(crate::pathfinding::rail_neighbors(&world, (0, 2), 0).is_empty());
+
+        // Nor can track go on water or over other track
+        world.tiles[4][4].terrain = TerrainType::Water;
+        assert!(world.build_track(4, 4, None, 0).is_err());
+        assert!(world.build_track(0, 3, Some(horizontal), 0).is_err());
+    }
+
+    fn heights(world: &World) -> Vec<Vec<u8>> {
+        world.tiles.iter().map(|row| row.iter().map(|tile| tile.height).collect()).collect()
+    }
+
+    #[test]
+    fn raising_land_drags_neighbours_up_one_level_apart() {
+        let mut world = World::flat(9, 9);
+        assert_eq!(world.raise_land(4, 4, i64::MAX), Ok(TERRAFORM_COST_PER_LEVEL));
+        assert_eq!(world.tiles[4][5].height, 1);
+
+        // The second level needs the four sides raised with it
+        assert_eq!(world.raise_land(4, 4, i64::MAX), Ok(5 * TERRAFORM_COST_PER_LEVEL));
+        assert_eq!(world.tiles[4][4].height, 3);
+        for (x, y) in [(3, 4), (5, 4), (4, 3), (4, 5)] {
+            assert_eq!(world.tiles[y][x].height, 2);
+        }
+        for y in 0..9 {
+            for x in 0..9 {
+                assert!(world.slope_at(x, y) <= 1);
+            }
+        }
+
+        assert_eq!(world.lower_land(4, 4, i64::MAX), Ok(TERRAFORM_COST_PER_LEVEL));
+        assert_eq!(world.tiles[4][4].height, 2);
+        assert!(world.lower_land(0, 0, i64::MAX).is_err());
+    }
+
+    #[test]
+    fn terraforming_changes_nothing_unless_all_of_it_can_be_done() {
+        let mut world = World::flat(9, 9);
+        world.raise_land(4, 4, i64::MAX).unwrap();
+        let before = heights(&world);
+
+        assert!(world.raise_land(4, 4, 4 * TERRAFORM_COST_PER_LEVEL).is_err());
+        assert_eq!(heights(&world), before);
+
+        world.build_track(5, 4, None, 0).unwrap();
+        assert!(world.raise_land(4, 4, i64::MAX).is_err());
+        assert_eq!(heights(&world), before);
+    }
+
+    #[test]
+    fn levelling_flattens_the_area_to_its_first_corner() {
+        let mut world = World::flat(9, 9);
+        world.raise_land(4, 4, i64::MAX).unwrap();
+        world.raise_land(4, 4, i64::MAX).unwrap();
+        assert_eq!(world.level_area((2, 2), (6, 6), i64::MAX), Ok(6 * TERRAFORM_COST_PER_LEVEL));
+        assert!(heights(&world).iter().flatten().all(|&height| height == 1));
+    }
+
+    #[test]
+    fn town_near_picks_the_closest_town() {
+        let mut world = World::flat(12, 12);
+        world.tiles[3][3].content = TileContent::House(House { town: (1, 1), residents: 10 });
+        world.tiles[6][6].content = TileContent::House(House { town: (9, 9), residents: 10 });
+        assert_eq!(world.town_near(5, 5, 4), Some((9, 9)));
+        assert_eq!(world.town_near(2, 2, 1), Some((1, 1)));
+        assert_eq!(world.town_near(10, 1, 2), None);
+    }
+
+    #[test]
+    fn same_seed_plays_out_the_same() {
+        let mut first = World::from_config(WorldGenConfig::new(128, 96, 7));
+        let mut second = World::from_config(WorldGenConfig::new(128, 96, 7));
+        for _ in 0..180 {
+            first.update();
+            second.update();
+        }
+        assert_eq!(layout(&first), layout(&second));
+    }
+
+    #[test]
+    fn upkeep_follows_building_and_demolishing() {
+        let mut world = World::flat(24, 24);
+        world.build_track(2, 2, None, 0).unwrap();
+        world.build_track(3, 2, None, 0).unwrap();
+        world.build_station(5, 5, StationType::Road, 0).unwrap();
+        world.build_canal(7, 7, 0).unwrap();
+        world.build_airport(10, 10, AirportSize::Small, 0).unwrap();
+        world.build_track(2, 20, None, 1).unwrap();
+        let expected = 2 * TRACK_MAINTENANCE
+            + StationType::Road.maintenance_cost()
+            + CANAL_MAINTENANCE
+            + StationType::Airport.maintenance_cost();
+        assert_eq!(world.maintenance_cost(0), expected);
+        assert_eq!(world.maintenance_cost(1), TRACK_MAINTENANCE);
+        world.recount_upkeep();
+        assert_eq!(world.maintenance_cost(0), expected);
+
+        for pos in [(2, 2), (3, 2), (5, 5), (7, 7), (11, 11)] {
+            world.demolish(pos.0, pos.1).unwrap();
+        }
+        assert_eq!(world.maintenance_cost(0), 0);
+        world.release_company_tiles(1);
+        assert_eq!(world.maintenance_cost(1), 0);
+    }
+
+    #[test]
+    fn from_connections_picks_the_simplest_piece() {
+        use Direction::*;
+        assert_eq!(TrackType::from_connections(&[]), TrackType::Straight { horizontal: true });
+        assert_eq!(TrackType::from_connections(&[West, East]), TrackType::Straight { horizontal: true });
+        assert_eq!(TrackType::from_connections(&[South]), TrackType::Straight { horizontal: false });
+        assert_eq!(TrackType::from_connections(&[SouthWest, NorthEast]), TrackType::Diagonal { rising: true });
+        assert_eq!(TrackType::from_connections(&[NorthWest]), TrackType::Diagonal { rising: false });
+        // Order and repeats don't matter
+        assert_eq!(
+            TrackType::from_connections(&[East, North, North]),
+            TrackType::from_connections(&[North, East]),
+        );
+        assert!(matches!(TrackType::from_connections(&[North, East]), TrackType::Curve { .. }));
+        assert!(matches!(TrackType::from_connections(&[North, SouthEast]), TrackType::Junction { .. }));
+        assert!(matches!(TrackType::from_connections(&[North, East, South]), TrackType::Junction { .. }));
+    }
+
+    #[test]
+    fn from_connections_keeps_every_side() {
+        let sides: Vec<Vec<Direction>> = vec![
+            vec![Direction::North, Direction::East],
+            vec![Direction::West, Direction::South],
+            vec![Direction::North, Direction::East, Direction::South, Direction::West],
+            vec![Direction::North, Direction::SouthWest],
+        ];
+        for wanted in sides {
+            let mut connections = TrackType::from_connections(&wanted).connections();
+            let mut wanted = wanted.clone();
+            connections.sort_by_key(|direction| *direction as u8);
+            wanted.sort_by_key(|direction| *direction as u8);
+            assert_eq!(connections, wanted);
+        }
+    }
+
+    const FIRST: VehicleId = (0, 1);
+    const SECOND: VehicleId = (0, 2);
+
+    /// Track from (1, 5) east to a curve on (3, 5), then north up to (3, 1).
+    fn bend_with_signal(signal_type: SignalType) -> World {
+        let mut world = World::flat(8, 8);
+        for x in 1..3 {
+            world.build_track(x, 5, Some(TrackType::Straight { horizontal: true }), 0).unwrap();
+        }
+        world.build_track(3, 5, Some(TrackType::from_connections(&[Direction::West, Direction::North])), 0).unwrap();
+        for y in 1..5 {
+            world.build_track(3, y, Some(TrackType::Straight { horizontal: false }), 0).unwrap();
+        }
+        world.build_signal(3, 5, Signal { signal_type, facing: Direction::North }, 0).unwrap();
+        world
+    }
+
+    #[test]
+    fn signal_on_a_curve_faces_the_exit_side() {
+        let world = bend_with_signal(SignalType::Block);
+        // Coming in from the west, heading east, the train leaves north
+        assert!(world.signal_facing((2, 5), (3, 5)).is_some());
+        assert!(world.signal_facing((3, 4), (3, 5)).is_none());
+    }
+
+    #[test]
+    fn block_signal_holds_trains_out_of_an_occupied_block() {
+        let mut world = bend_with_signal(SignalType::Block);
+        world.hold_rail_tile((3, 2), FIRST);
+        assert!(!world.reserve_rail_ahead(SECOND, (2, 5), &[(3, 5), (3, 4)]));
+        assert!(!world.rail_reservations.contains_key(&(3, 5)));
+
+        world.release_rail_tile((3, 2), FIRST);
+        assert!(world.reserve_rail_ahead(SECOND, (2, 5), &[(3, 5), (3, 4)]));
+        assert_eq!(world.rail_reservations.get(&(3, 5)), Some(&SECOND));
+
+        // Trains the other way pass the signal like plain track
+        let mut world = bend_with_signal(SignalType::Block);
+        world.hold_rail_tile((1, 5), FIRST);
+        assert!(world.reserve_rail_ahead(SECOND, (3, 4), &[(3, 5), (2, 5)]));
+    }
+
+    #[test]
+    fn path_signal_reserves_up_to_the_next_signal() {
+        let mut world = bend_with_signal(SignalType::Path);
+        world.build_signal(3, 2, Signal { signal_type: SignalType::Block, facing: Direction::North }, 0).unwrap();
+        let ahead = [(3, 5), (3, 4), (3, 3), (3, 2), (3, 1)];
+
+        world.hold_rail_tile((3, 3), FIRST);
+        assert!(!world.reserve_rail_ahead(SECOND, (2, 5), &ahead));
+        world.release_rail_tile((3, 3), FIRST);
+
+        assert!(world.reserve_rail_ahead(SECOND, (2, 5), &ahead));
+        for pos in &ahead[..3] {
+            assert_eq!(world.rail_reservations.get(pos), Some(&SECOND));
+        }
+        assert!(!world.rail_reservations.contains_key(&(3, 2)));
+    }
+
+    #[test]
+    fn only_the_owner_can_remove_a_signal() {
+        let mut world = bend_with_signal(SignalType::Block);
+        let version = world.network_version;
+        assert!(world.remove_signal(3, 5, 1).is_err());
+        assert!(world.signals.contains_key(&(3, 5)));
+        assert_eq!(world.network_version, version);
+
+        // Routes planned through the signal are looked at again
+        assert_eq!(world.remove_signal(3, 5, 0).map(|signal| signal.facing), Ok(Direction::North));
+        assert!(!world.path_is_current(&[(2, 5), (3, 5), (3, 4)], version));
+        assert!(world.remove_signal(3, 5, 0).is_err());
+    }
+
+    #[test]
+    fn new_track_leaves_signals_and_crossings_alone() {
+        let straight = TrackType::Straight { horizontal: true };
+        let mut world = World::flat(8, 8);
+        world.build_track(2, 2, Some(straight.clone()), 0).unwrap();
+        world.build_track(5, 2, Some(straight.clone()), 0).unwrap();
+        world.build_signal(2, 2, Signal { signal_type: SignalType::Block, facing: Direction::East }, 0).unwrap();
+        world.build_level_crossing(5, 2).unwrap();
+
+        world.build_track(2, 3, None, 0).unwrap();
+        world.build_track(5, 3, None, 0).unwrap();
+        assert_eq!(world.rail_connections((2, 2)), straight.connections());
+        assert_eq!(world.rail_connections((5, 2)), straight.connections());
+        assert!(!world.rail_connections((2, 3)).contains(&Direction::North));
+
+        // Plain track with a loose end is bent round to meet it
+        world.build_track(1, 5, Some(straight.clone()), 0).unwrap();
+        world.build_track(1, 6, None, 0).unwrap();
+        assert!(world.rail_connections((1, 5)).contains(&Direction::South));
+    }
+
+    #[test]
+    fn airport_tiles_lead_to_their_terminal() {
+        let mut world = World::flat(12, 12);
+        world.build_airport(4, 4, AirportSize::Large, 0).unwrap();
+        for pos in AirportSize::Large.tiles((4, 4)) {
+            assert_eq!(world.airport_at(pos), Some((4, 4)));
+            assert!(!world.is_clear(pos.0, pos.1));
+        }
+        assert_eq!(world.airport_at((7, 4)), None);
+        assert!(world.build_airport(6, 6, AirportSize::Small, 0).is_err());
+
+        world.demolish(6, 6).unwrap();
+        assert!(world.airports.is_empty());
+        assert!(world.airport_tiles.is_empty());
+        assert!(world.is_clear(5, 5));
+    }
+}